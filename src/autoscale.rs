@@ -0,0 +1,125 @@
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::AutoscaleConfig;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleEvent {
+    pub from: usize,
+    pub to: usize,
+    pub reason: String,
+}
+
+// Decides how many of the spawned workers are allowed to step. Workers above
+// the active count park until they're needed again.
+//
+// Low CPU use means workers mostly wait on the target, as with HTTP, so more
+// of them keep it busy. Saturated cores, or latency climbing well past what
+// it was with fewer workers, means they're only queueing on each other.
+pub struct Autoscaler {
+    config: AutoscaleConfig,
+    active: Arc<AtomicUsize>,
+    last_at: Instant,
+    last_cpu: Option<Duration>,
+    latency: Duration,
+    commands: usize,
+    // Average latency seen at the minimum worker count, the reference for
+    // spotting queueing.
+    floor_latency: Option<Duration>,
+}
+
+impl Autoscaler {
+    pub fn new(config: AutoscaleConfig, initial: usize) -> Self {
+        let initial = initial.clamp(config.min, config.max);
+        Autoscaler {
+            config,
+            active: Arc::new(AtomicUsize::new(initial)),
+            last_at: Instant::now(),
+            last_cpu: process_cpu_time(),
+            latency: Duration::default(),
+            commands: 0,
+            floor_latency: None,
+        }
+    }
+
+    pub fn active(&self) -> Arc<AtomicUsize> {
+        self.active.clone()
+    }
+
+    pub fn observe(&mut self, latency: Duration) {
+        self.observe_many(latency, 1);
+    }
+
+    pub fn observe_many(&mut self, total_latency: Duration, commands: usize) {
+        self.latency += total_latency;
+        self.commands += commands;
+    }
+
+    // Called periodically, returns the change made if any.
+    pub fn poll(&mut self) -> Option<ScaleEvent> {
+        let elapsed = self.last_at.elapsed();
+        if elapsed < self.config.interval {
+            return None;
+        }
+        let cpu = process_cpu_time();
+        let utilisation = match (cpu, self.last_cpu) {
+            (Some(now), Some(before)) => Some(
+                now.saturating_sub(before).as_secs_f64()
+                    / (elapsed.as_secs_f64() * num_cpus::get() as f64),
+            ),
+            _ => None,
+        };
+        let avg_latency = if self.commands > 0 {
+            Some(self.latency / self.commands as u32)
+        } else {
+            None
+        };
+        self.last_at = Instant::now();
+        self.last_cpu = cpu;
+        self.latency = Duration::default();
+        self.commands = 0;
+
+        let from = self.active.load(Ordering::SeqCst);
+        if from == self.config.min {
+            if let Some(l) = avg_latency {
+                self.floor_latency = Some(l);
+            }
+        }
+        let queueing = match (avg_latency, self.floor_latency) {
+            (Some(l), Some(floor)) => l > floor * 2,
+            _ => false,
+        };
+        let (to, reason) = match utilisation {
+            Some(u) if u > 0.9 && from > self.config.min => {
+                (from - 1, format!("CPU at {:.0}%", u * 100.0))
+            }
+            _ if queueing && from > self.config.min => (
+                from - 1,
+                format!(
+                    "latency {:?} over twice the {:?} seen at {} workers",
+                    avg_latency.unwrap_or_default(),
+                    self.floor_latency.unwrap_or_default(),
+                    self.config.min
+                ),
+            ),
+            Some(u) if u < 0.5 && !queueing && from < self.config.max => {
+                (from + 1, format!("CPU at {:.0}%", u * 100.0))
+            }
+            _ => return None,
+        };
+        self.active.store(to, Ordering::SeqCst);
+        Some(ScaleEvent { from, to, reason })
+    }
+}
+
+// Linux only, from /proc/self/stat, assuming the usual 100 ticks a second.
+fn process_cpu_time() -> Option<Duration> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name can contain spaces, fields are counted after it.
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Duration::from_millis((utime + stime) * 10))
+}
@@ -0,0 +1,34 @@
+#[cfg(not(feature = "embedded"))]
+use failure::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use brdgme_cmd::requester;
+use brdgme_game::Gamer;
+
+use std::fmt::Debug;
+
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(not(feature = "embedded"))]
+pub mod process;
+pub mod reload;
+pub mod session;
+pub mod trace;
+
+pub fn gamer<G>() -> impl requester::Requester
+where
+    G: Gamer + Debug + Clone + Serialize + DeserializeOwned + 'static,
+{
+    requester::gamer::new::<G>()
+}
+
+// Same as brdgme_cmd's parse_args, plus `--subprocess [OPTIONS] BINARY
+// ARGS...` to run the game through process::ProcessRequester.
+#[cfg(not(feature = "embedded"))]
+pub fn from_args(args: &[String]) -> Result<Box<dyn requester::Requester>, Error> {
+    match args.get(1).map(String::as_str) {
+        Some("--subprocess") => Ok(Box::new(process::ProcessRequester::from_args(&args[2..])?)),
+        _ => requester::parse_args(args),
+    }
+}
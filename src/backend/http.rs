@@ -0,0 +1,30 @@
+use failure::Error;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use crate::wallclock;
+
+pub struct HttpRequester {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpRequester {
+    pub fn new(url: impl Into<String>) -> Self {
+        HttpRequester {
+            url: url.into(),
+            agent: ureq::Agent::new(),
+        }
+    }
+}
+
+impl Requester for HttpRequester {
+    fn request(&mut self, req: &api::Request) -> Result<api::Response, Error> {
+        let mut request = self.agent.post(&self.url);
+        if let Some(now) = wallclock::virtual_now() {
+            request = request.set(wallclock::HEADER, &now.to_string());
+        }
+        Ok(request.send_json(req)?.into_json()?)
+    }
+}
@@ -0,0 +1,530 @@
+use failure::{bail, format_err, Error, Fail};
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::wallclock;
+
+// Numbers each request's strace output file.
+static TRACES: AtomicUsize = AtomicUsize::new(0);
+// Numbers protocol logs, each requester, and so each worker, having its own.
+static PROTOCOL_LOGS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Workers can share a thread, as they do when simulated, so each swaps
+    // its own lines in with swap_stderr for the length of a step.
+    static STDERR: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+}
+
+// The last lines the game wrote to stderr for the worker stepping on this
+// thread, oldest first.
+pub fn stderr_excerpt() -> Vec<String> {
+    STDERR.with(|s| s.borrow().iter().cloned().collect())
+}
+
+// Installs a worker's stderr lines on this thread, returning the ones they
+// replace.
+pub fn swap_stderr(lines: VecDeque<String>) -> VecDeque<String> {
+    STDERR.with(|s| mem::replace(&mut *s.borrow_mut(), lines))
+}
+
+// Per request limits on the game process, set with ulimit in a wrapping
+// shell so no libc bindings are needed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub cpu_secs: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub open_files: Option<u64>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    fn script(&self) -> String {
+        let mut script = String::new();
+        // With the hard limit a second past the soft one, going over gets
+        // the game SIGXCPU rather than the SIGKILL anything could send.
+        if let Some(secs) = self.cpu_secs {
+            script.push_str(&format!(
+                "ulimit -S -t {} && ulimit -H -t {} && ",
+                secs,
+                secs + 1
+            ));
+        }
+        for (flag, limit) in [
+            ("-v", self.memory_mb.map(|mb| mb * 1024)),
+            ("-n", self.open_files),
+        ] {
+            if let Some(limit) = limit {
+                script.push_str(&format!("ulimit {} {} && ", flag, limit));
+            }
+        }
+        script.push_str("exec \"$@\"");
+        script
+    }
+
+    // Which limit the game ran into, judged by how it died.
+    fn exceeded(&self, status: ExitStatus, stderr: &str) -> Option<Limit> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // SIGXCPU, from the soft limit. A game that handles it and
+            // carries on is killed at the hard limit, like any SIGKILL.
+            if self.cpu_secs.is_some() && status.signal() == Some(24) {
+                return Some(Limit::Cpu);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = status;
+        if self.memory_mb.is_some()
+            && (stderr.contains("memory allocation of") || stderr.contains("out of memory"))
+        {
+            return Some(Limit::Memory);
+        }
+        if self.open_files.is_some() && stderr.contains("Too many open files") {
+            return Some(Limit::OpenFiles);
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Cpu,
+    Memory,
+    OpenFiles,
+}
+
+// Returned when a request failed because the game hit one of its limits,
+// which engines report as a resource finding rather than a request failure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub binary: PathBuf,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limit = match self.limit {
+            Limit::Cpu => "CPU time",
+            Limit::Memory => "memory",
+            Limit::OpenFiles => "open file",
+        };
+        write!(f, "{} exceeded its {} limit", self.binary.display(), limit)
+    }
+}
+
+impl Fail for LimitExceeded {}
+
+// Errors only the sandbox hands back, from the read-only root and the
+// unshared network namespace bwrap sets up, as they show up in a game's
+// stderr. Plain EPERM ("Operation not permitted") isn't one of them: the
+// sandbox has no seccomp filter producing it, and games get it outside any
+// sandbox too, such as from signalling another user's process.
+const SANDBOX_DENIALS: &[&str] = &["Read-only file system", "Network is unreachable"];
+
+// Returned when a sandboxed game tried something the sandbox refused, which
+// engines report with Security severity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandboxViolation {
+    pub binary: PathBuf,
+    pub denial: String,
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} was denied by the sandbox: {}",
+            self.binary.display(),
+            self.denial
+        )
+    }
+}
+
+impl Fail for SandboxViolation {}
+
+// The exact bytes sent to and received from the game, for debugging framing
+// bugs that otherwise only show up as serde errors. Opened on the first
+// request and written until it reaches `max_bytes`.
+pub struct ProtocolLog {
+    dir: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    written: u64,
+    requests: usize,
+}
+
+impl ProtocolLog {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        ProtocolLog {
+            dir: dir.into(),
+            max_bytes,
+            file: None,
+            written: 0,
+            requests: 0,
+        }
+    }
+
+    fn record(&mut self, request: &[u8], output: &std::process::Output) -> Result<(), Error> {
+        self.requests += 1;
+        if self.written >= self.max_bytes {
+            return Ok(());
+        }
+        if self.file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let path = self.dir.join(format!(
+                "protocol-{}-{}.log",
+                process::id(),
+                PROTOCOL_LOGS.fetch_add(1, Ordering::Relaxed)
+            ));
+            self.file = Some(File::create(path)?);
+        }
+        let mut entry =
+            format!(">>> request {} ({} bytes)\n", self.requests, request.len()).into_bytes();
+        entry.extend_from_slice(request);
+        entry.extend_from_slice(
+            format!(
+                "\n<<< response {} ({} bytes, {})\n",
+                self.requests,
+                output.stdout.len(),
+                output.status
+            )
+            .as_bytes(),
+        );
+        entry.extend_from_slice(&output.stdout);
+        entry.push(b'\n');
+        let room = (self.max_bytes - self.written) as usize;
+        if entry.len() > room {
+            entry.truncate(room);
+            entry.extend_from_slice(b"\n... protocol log size limit reached\n");
+        }
+        if let Some(ref mut file) = self.file {
+            file.write_all(&entry)?;
+        }
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+}
+
+// Runs the game binary once per request, speaking the brdgme_cmd CLI
+// protocol over stdin and stdout. Unlike the requesters in brdgme_cmd it
+// keeps the child's stderr, so debug output and panic messages can be
+// attached to findings.
+pub struct ProcessRequester {
+    binary: PathBuf,
+    args: Vec<String>,
+    stderr_lines: usize,
+    // Runs the game under strace and fails any request during which it
+    // touched the network, for games that must be pure.
+    offline: bool,
+    pub limits: Limits,
+    // Runs the game in a bubblewrap sandbox with a read-only filesystem, a
+    // private /tmp and no network.
+    sandbox: bool,
+    pub protocol_log: Option<ProtocolLog>,
+}
+
+impl ProcessRequester {
+    pub fn new(binary: impl Into<PathBuf>, args: Vec<String>, stderr_lines: usize) -> Self {
+        ProcessRequester {
+            binary: binary.into(),
+            args,
+            stderr_lines,
+            offline: false,
+            limits: Limits::default(),
+            sandbox: false,
+            protocol_log: None,
+        }
+    }
+
+    // `[--stderr-lines N] [--offline] [--sandbox] [--cpu-limit SECS]
+    // [--memory-limit MB] [--fd-limit N] [--protocol-log DIR
+    // [--protocol-log-max-bytes N]] BINARY ARGS...`
+    pub fn from_args(args: &[String]) -> Result<Self, Error> {
+        let (mut stderr_lines, mut offline, mut sandbox) = (50, false, false);
+        let mut limits = Limits::default();
+        let (mut protocol_log, mut protocol_log_max_bytes) = (None, 10 * 1024 * 1024);
+        let mut rest = args;
+        loop {
+            match rest {
+                [flag, n, tail @ ..]
+                    if ["--cpu-limit", "--memory-limit", "--fd-limit"].contains(&flag.as_str()) =>
+                {
+                    let n = n
+                        .parse()
+                        .map_err(|e| format_err!("invalid value for {}: {}", flag, e))?;
+                    match flag.as_str() {
+                        "--cpu-limit" => limits.cpu_secs = Some(n),
+                        "--memory-limit" => limits.memory_mb = Some(n),
+                        _ => limits.open_files = Some(n),
+                    }
+                    rest = tail;
+                }
+                [flag, dir, tail @ ..] if flag == "--protocol-log" => {
+                    protocol_log = Some(PathBuf::from(dir));
+                    rest = tail;
+                }
+                [flag, n, tail @ ..] if flag == "--protocol-log-max-bytes" => {
+                    protocol_log_max_bytes = n.parse().map_err(|e| {
+                        format_err!("invalid value for --protocol-log-max-bytes: {}", e)
+                    })?;
+                    rest = tail;
+                }
+                [flag, n, tail @ ..] if flag == "--stderr-lines" => {
+                    stderr_lines = n
+                        .parse()
+                        .map_err(|e| format_err!("invalid value for --stderr-lines: {}", e))?;
+                    rest = tail;
+                }
+                [flag, tail @ ..] if flag == "--offline" => {
+                    offline = true;
+                    rest = tail;
+                }
+                [flag, tail @ ..] if flag == "--sandbox" => {
+                    sandbox = true;
+                    rest = tail;
+                }
+                _ => break,
+            }
+        }
+        let mut requester = match rest.split_first() {
+            Some((binary, args)) => ProcessRequester::new(binary, args.to_vec(), stderr_lines),
+            None => bail!("--subprocess requires a game binary"),
+        };
+        if offline {
+            requester.offline()?;
+        }
+        if sandbox {
+            requester.sandbox()?;
+        }
+        requester.limits = limits;
+        requester.protocol_log =
+            protocol_log.map(|dir| ProtocolLog::new(dir, protocol_log_max_bytes));
+        Ok(requester)
+    }
+
+    pub fn offline(&mut self) -> Result<(), Error> {
+        if !cfg!(target_os = "linux") {
+            bail!("--offline is only supported on Linux");
+        }
+        match Command::new("strace").arg("-V").output() {
+            Ok(ref o) if o.status.success() => {}
+            _ => bail!("--offline requires strace"),
+        }
+        self.offline = true;
+        Ok(())
+    }
+
+    pub fn sandbox(&mut self) -> Result<(), Error> {
+        if !cfg!(target_os = "linux") {
+            bail!("--sandbox is only supported on Linux");
+        }
+        match Command::new("bwrap").arg("--version").output() {
+            Ok(ref o) if o.status.success() => {}
+            _ => bail!("--sandbox requires bubblewrap (bwrap)"),
+        }
+        self.sandbox = true;
+        Ok(())
+    }
+
+    fn command(&self, trace: &Path) -> Command {
+        let mut argv: Vec<String> = vec![self.binary.display().to_string()];
+        argv.extend(self.args.iter().cloned());
+        if !self.limits.is_empty() {
+            let wrapper = [
+                "sh".to_string(),
+                "-c".to_string(),
+                self.limits.script(),
+                "sh".to_string(),
+            ];
+            argv.splice(0..0, wrapper);
+        }
+        if self.sandbox {
+            let bwrap = [
+                "bwrap",
+                "--ro-bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--tmpfs",
+                "/tmp",
+                "--unshare-all",
+                "--die-with-parent",
+                "--new-session",
+                "--",
+            ];
+            argv.splice(0..0, bwrap.iter().map(|s| s.to_string()));
+        }
+        // Outermost, so strace sees the sandbox being set up too.
+        if self.offline {
+            let strace = ["strace", "-f", "-qq", "-e", "trace=%network", "-o"]
+                .iter()
+                .map(|s| s.to_string())
+                .chain([trace.display().to_string(), "--".to_string()]);
+            argv.splice(0..0, strace);
+        }
+        let mut command = Command::new(&argv[0]);
+        command.args(&argv[1..]);
+        command
+    }
+
+    fn keep_stderr(&self, stderr: &str) {
+        STDERR.with(|s| {
+            let mut s = s.borrow_mut();
+            s.extend(stderr.lines().map(String::from));
+            while s.len() > self.stderr_lines {
+                s.pop_front();
+            }
+        });
+    }
+}
+
+// Unix sockets are how plenty of libc internals talk to local daemons, so
+// only internet sockets count. Returns the call without its pid or result.
+fn network_call(trace: &str) -> Option<String> {
+    trace.lines().find(|l| l.contains("AF_INET")).map(|l| {
+        let call = match l.split_once(' ') {
+            Some((pid, call)) if pid.chars().all(|c| c.is_ascii_digit()) => call,
+            _ => l,
+        }
+        .trim();
+        call.split(" = ").next().unwrap_or(call).to_string()
+    })
+}
+
+fn sandbox_denial(stderr: &str) -> Option<String> {
+    stderr
+        .lines()
+        .find(|l| SANDBOX_DENIALS.iter().any(|d| l.contains(d)))
+        .map(|l| l.trim().to_string())
+}
+
+impl Requester for ProcessRequester {
+    fn request(&mut self, req: &api::Request) -> Result<api::Response, Error> {
+        let trace = env::temp_dir().join(format!(
+            "brdgme-fuzz-net-{}-{}",
+            process::id(),
+            TRACES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut command = self.command(&trace);
+        if let Some(now) = wallclock::virtual_now() {
+            command.env(wallclock::ENV, now.to_string());
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let input = serde_json::to_vec(req)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&input)?;
+        }
+        let output = child.wait_with_output()?;
+        if let Some(ref mut log) = self.protocol_log {
+            if let Err(e) = log.record(&input, &output) {
+                eprintln!("\nfailed to write protocol log, disabling: {}", e);
+                self.protocol_log = None;
+            }
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        self.keep_stderr(&stderr);
+        if self.offline {
+            let traced = fs::read_to_string(&trace).unwrap_or_default();
+            let _ = fs::remove_file(&trace);
+            if let Some(call) = network_call(&traced) {
+                bail!("{} accessed the network: {}", self.binary.display(), call);
+            }
+        }
+        if self.sandbox {
+            if let Some(denial) = sandbox_denial(&stderr) {
+                return Err(SandboxViolation {
+                    binary: self.binary.clone(),
+                    denial,
+                }
+                .into());
+            }
+        }
+        if !output.status.success() {
+            if let Some(limit) = self.limits.exceeded(output.status, &stderr) {
+                return Err(LimitExceeded {
+                    limit,
+                    binary: self.binary.clone(),
+                }
+                .into());
+            }
+            // The panic message, if any, is what signatures are built from.
+            let lines: Vec<&str> = stderr.lines().collect();
+            let from = lines
+                .iter()
+                .position(|l| l.contains("panicked at"))
+                .unwrap_or_else(|| lines.len().saturating_sub(5));
+            bail!(
+                "{} exited with {}: {}",
+                self.binary.display(),
+                output.status,
+                lines[from..]
+                    .iter()
+                    .take(5)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_sandbox_errors_are_denials() {
+        assert_eq!(
+            sandbox_denial("saving\n  open /data/save: Read-only file system (os error 30)\n"),
+            Some("open /data/save: Read-only file system (os error 30)".to_string())
+        );
+        assert!(sandbox_denial("connect: Network is unreachable").is_some());
+        assert_eq!(
+            sandbox_denial("kill 1: Operation not permitted (os error 1)"),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_sigxcpu_is_the_cpu_limit() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let limits = Limits {
+            cpu_secs: Some(2),
+            ..Limits::default()
+        };
+        assert!(limits
+            .script()
+            .starts_with("ulimit -S -t 2 && ulimit -H -t 3 && "));
+        assert_eq!(
+            limits.exceeded(ExitStatus::from_raw(24), ""),
+            Some(Limit::Cpu)
+        );
+        // Killed by an OOM killer, or anyone else.
+        assert_eq!(limits.exceeded(ExitStatus::from_raw(9), ""), None);
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+type Version = (SystemTime, u64);
+
+pub struct BinaryWatcher {
+    path: PathBuf,
+    current: Option<Version>,
+    changed: Option<Version>,
+    generation: Arc<AtomicUsize>,
+}
+
+impl BinaryWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        BinaryWatcher {
+            current: version(&path),
+            changed: None,
+            path,
+            generation: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn generation(&self) -> Arc<AtomicUsize> {
+        self.generation.clone()
+    }
+
+    // A change is only reported once the binary has stopped changing between
+    // two polls, so we don't recycle onto a half-written build.
+    pub fn poll(&mut self) -> Option<usize> {
+        let now = version(&self.path)?;
+        if Some(now) == self.current {
+            self.changed = None;
+            return None;
+        }
+        if self.changed != Some(now) {
+            self.changed = Some(now);
+            return None;
+        }
+        self.current = Some(now);
+        self.changed = None;
+        Some(self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+}
+
+fn version(path: &Path) -> Option<Version> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
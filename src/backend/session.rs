@@ -0,0 +1,115 @@
+// Session mode lets a backend keep game state between plays so the fuzzer only
+// has to send commands. It rides on the existing protocol, the `game` field of
+// a Play carries a session reference instead of a state:
+//
+//   @session:ID          play against the state stored for ID
+//   @session:ID\nSTATE   checkpoint, play against STATE and store the result
+//                        for ID
+//
+// A Status for `@session:ID` answers with the state stored for ID, so the
+// fuzzer can see what the backend is holding.
+//
+// Only a Play response with no remaining input is stored. User errors and
+// partial parses leave the stored state as it was, the fuzzer doesn't move
+// its own copy of the game for them either.
+//
+// A backend that doesn't hold ID answers with an error containing
+// `no session`. The fuzzer then drops session mode and resends the play with
+// the full state, any other error is the target's and reported as such.
+//
+// Backends advertise support by answering a Render for the `@session:probe`
+// game. Anything else, including an error, means the backend only speaks the
+// stateless protocol.
+
+use failure::Error;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+pub const PREFIX: &str = "@session:";
+pub const PROBE: &str = "@session:probe";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionRef<'a> {
+    Resume { id: &'a str },
+    Checkpoint { id: &'a str, state: &'a str },
+}
+
+// For backends implementing session mode, returns None for plain states.
+pub fn parse(game: &str) -> Option<SessionRef<'_>> {
+    let rest = game.strip_prefix(PREFIX)?;
+    Some(match rest.find('\n') {
+        Some(i) => SessionRef::Checkpoint {
+            id: &rest[..i],
+            state: &rest[i + 1..],
+        },
+        None => SessionRef::Resume { id: rest },
+    })
+}
+
+// Whether a session play failed because the backend lost or never had the
+// session, rather than the command failing.
+pub fn lost(error: &Error) -> bool {
+    error.to_string().to_lowercase().contains("no session")
+}
+
+pub fn negotiate(client: &mut (impl Requester + ?Sized)) -> bool {
+    matches!(
+        client.request(&api::Request::Render {
+            player: None,
+            game: PROBE.to_string(),
+        }),
+        Ok(api::Response::Render { .. })
+    )
+}
+
+pub struct Session {
+    id: String,
+    every: usize,
+    synced: bool,
+    since_checkpoint: usize,
+    checkpointing: bool,
+}
+
+impl Session {
+    // Sends a full checkpoint every `every` accepted plays so a backend that
+    // lost or corrupted its copy is resynced.
+    pub fn new(id: impl Into<String>, every: usize) -> Self {
+        Session {
+            id: id.into(),
+            every: every.max(1),
+            synced: false,
+            since_checkpoint: 0,
+            checkpointing: false,
+        }
+    }
+
+    pub fn game_field(&mut self, state: &str) -> String {
+        self.checkpointing = !self.synced || self.since_checkpoint >= self.every;
+        if self.checkpointing {
+            format!("{}{}\n{}", PREFIX, self.id, state)
+        } else {
+            format!("{}{}", PREFIX, self.id)
+        }
+    }
+
+    // Refers to the backend's copy without sending a checkpoint, None until
+    // the backend has one.
+    pub fn resume_field(&self) -> Option<String> {
+        if self.synced {
+            Some(format!("{}{}", PREFIX, self.id))
+        } else {
+            None
+        }
+    }
+
+    // Rejected plays leave the backend's copy untouched, so only accepted
+    // ones move the checkpoint counter.
+    pub fn accepted(&mut self) {
+        if self.checkpointing {
+            self.synced = true;
+            self.since_checkpoint = 0;
+        }
+        self.since_checkpoint += 1;
+    }
+}
@@ -0,0 +1,100 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::redact::Redactor;
+use crate::wallclock;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub request: api::Request,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<api::Response>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // The virtual time the request was made at, see wallclock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub now: Option<u64>,
+}
+
+impl TraceEntry {
+    pub fn new(request: &api::Request, res: &Result<api::Response, Error>) -> Self {
+        TraceEntry {
+            request: request.clone(),
+            response: res.as_ref().ok().cloned(),
+            error: res.as_ref().err().map(|e| e.to_string()),
+            now: wallclock::virtual_now(),
+        }
+    }
+}
+
+pub fn read_trace(path: &Path) -> Result<Vec<TraceEntry>, Error> {
+    let mut entries = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+pub struct TraceRequester<R> {
+    inner: R,
+    out: Option<BufWriter<File>>,
+    pub redactor: Redactor,
+}
+
+impl<R: Requester> TraceRequester<R> {
+    pub fn new(inner: R) -> Self {
+        TraceRequester {
+            inner,
+            out: None,
+            redactor: Redactor::default(),
+        }
+    }
+
+    pub fn start(&mut self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.out = Some(BufWriter::new(File::create(path)?));
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut out) = self.out.take() {
+            let _ = out.flush();
+        }
+    }
+
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.out.is_some()
+    }
+}
+
+impl<R: Requester> Requester for TraceRequester<R> {
+    fn request(&mut self, req: &api::Request) -> Result<api::Response, Error> {
+        let res = self.inner.request(req);
+        if let Some(ref mut out) = self.out {
+            let entry = self.redactor.redact_trace(&TraceEntry::new(req, &res));
+            let written = serde_json::to_writer(&mut *out, &entry)
+                .map_err(Error::from)
+                .and_then(|_| Ok(writeln!(out)?));
+            if written.is_err() {
+                self.out = None;
+            }
+        }
+        res
+    }
+}
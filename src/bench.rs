@@ -0,0 +1,146 @@
+use criterion::{black_box, Criterion, Throughput};
+use failure::{bail, Error};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+use brdgme_game::Gamer;
+
+use std::fmt::Debug;
+
+use crate::backend;
+use crate::engine::{exec_command, player_command_spec, unique_players, CommandResponse, FuzzGame};
+use crate::names::{DefaultNames, NameProvider};
+use crate::strategy::{self, RandomStrategy, Strategy};
+
+// Commands rejected in a row before a game is dropped from the sequence and
+// another started.
+const MAX_REJECTIONS: usize = 1000;
+
+// A command from a fuzz sequence with the state it was played on, so it can
+// be played again on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchCommand {
+    pub player: usize,
+    pub command: String,
+    pub state: String,
+    pub names: Vec<String>,
+}
+
+// Fuzzes games from the seed until it has the number of commands asked for,
+// rejected ones included, as the same mix is what fuzzing puts an engine
+// through. The same seed gives the same sequence on the same engine.
+pub fn sequence(
+    client: &mut (impl Requester + ?Sized),
+    seed: u64,
+    commands: usize,
+) -> Result<Vec<BenchCommand>, Error> {
+    let player_counts = match client.request(&api::Request::PlayerCounts)? {
+        api::Response::PlayerCounts { player_counts } => player_counts,
+        v => bail!("invalid response to player counts request: {:?}", v),
+    };
+    let mut sequence = Vec::with_capacity(commands);
+    let mut strategy = RandomStrategy;
+    let mut game_number = 0;
+    while sequence.len() < commands {
+        let mut rng = strategy::seeded_rng(strategy::derive_seed(seed, game_number));
+        game_number += 1;
+        let players = match rng.choose(&player_counts) {
+            Some(&players) => players,
+            None => bail!("could not get player counts from {:?}", player_counts),
+        };
+        let names = DefaultNames.names(players, &mut rng);
+        let mut game = match client.request(&api::Request::New { players })? {
+            api::Response::New {
+                game,
+                logs,
+                player_renders,
+                ..
+            } => FuzzGame::new(game, player_renders, logs),
+            v => bail!("invalid response for new game: {:?}", v),
+        };
+        let mut rejections = 0;
+        while sequence.len() < commands && rejections < MAX_REJECTIONS {
+            let player = match game.game.status {
+                brdgme_game::Status::Active { ref whose_turn, .. } => {
+                    match rng.choose(&unique_players(whose_turn)) {
+                        Some(&player) => player,
+                        None => break,
+                    }
+                }
+                brdgme_game::Status::Finished { .. } => break,
+            };
+            let spec = player_command_spec(&game.player_renders, player)?;
+            let command = strategy.command(&spec, &names, &mut rng);
+            sequence.push(BenchCommand {
+                player,
+                command: command.clone(),
+                state: game.game.state.clone(),
+                names: names.clone(),
+            });
+            match exec_command(
+                client,
+                command,
+                game.game.state.clone(),
+                player,
+                names.clone(),
+            )? {
+                CommandResponse::Ok(next) => {
+                    game = next;
+                    rejections = 0;
+                }
+                _ => rejections += 1,
+            }
+        }
+    }
+    Ok(sequence)
+}
+
+// Times the client playing a fixed fuzz sequence, generated before timing
+// starts so only the engine's work is measured.
+pub fn bench_sequence(
+    c: &mut Criterion,
+    name: &str,
+    client: &mut (impl Requester + ?Sized),
+    seed: u64,
+    commands: usize,
+) {
+    let sequence = match sequence(client, seed, commands) {
+        Ok(sequence) => sequence,
+        Err(e) => panic!("failed to generate a fuzz sequence for {}: {}", name, e),
+    };
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(sequence.len() as u64));
+    group.bench_function(format!("seed {}, {} commands", seed, commands), |b| {
+        b.iter(|| {
+            for entry in &sequence {
+                let _ = black_box(exec_command(
+                    client,
+                    entry.command.clone(),
+                    entry.state.clone(),
+                    entry.player,
+                    entry.names.clone(),
+                ));
+            }
+        })
+    });
+    group.finish();
+}
+
+// For a game crate's benches, tracking its engine's throughput over fuzzed
+// play from `cargo bench`:
+//
+//   fn engine(c: &mut Criterion) {
+//       brdgme_fuzz::bench::bench_game_engine::<MyGame>(c, 42, 1000);
+//   }
+//   criterion_group!(benches, engine);
+//   criterion_main!(benches);
+pub fn bench_game_engine<G>(c: &mut Criterion, seed: u64, commands: usize)
+where
+    G: Gamer + Debug + Clone + Serialize + DeserializeOwned + 'static,
+{
+    let name = std::any::type_name::<G>();
+    bench_sequence(c, name, &mut backend::gamer::<G>(), seed, commands);
+}
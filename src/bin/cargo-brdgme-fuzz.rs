@@ -0,0 +1,265 @@
+#[cfg(not(feature = "embedded"))]
+use failure::{bail, format_err, Error};
+#[cfg(not(feature = "embedded"))]
+use serde_json::Value;
+
+#[cfg(not(feature = "embedded"))]
+use brdgme_cmd::requester;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::replay::{self, Reproduction};
+
+#[cfg(not(feature = "embedded"))]
+use std::env;
+#[cfg(not(feature = "embedded"))]
+use std::fs;
+#[cfg(not(feature = "embedded"))]
+use std::path::{Path, PathBuf};
+#[cfg(not(feature = "embedded"))]
+use std::process::{self, Command};
+
+#[cfg(not(feature = "embedded"))]
+const USAGE: &str =
+    "Usage: cargo brdgme-fuzz [-p PACKAGE] [--bin NAME] [--harness GAME_TYPE] [FUZZ_ARGS...]";
+
+#[cfg(not(feature = "embedded"))]
+struct Args {
+    package: Option<String>,
+    bin: Option<String>,
+    harness: Option<String>,
+    fuzz_args: Vec<String>,
+}
+
+#[cfg(not(feature = "embedded"))]
+fn parse_args() -> Result<Args, Error> {
+    let mut args = env::args().skip(1).peekable();
+    // Cargo passes the subcommand name as the first argument.
+    if args.peek().map(|a| a.as_ref()) == Some("brdgme-fuzz") {
+        args.next();
+    }
+    let mut parsed = Args {
+        package: None,
+        bin: None,
+        harness: None,
+        fuzz_args: vec!["brdgme-fuzz".to_string()],
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format_err!("{} requires a value", arg))
+        };
+        match arg.as_ref() {
+            "-p" | "--package" => parsed.package = Some(value()?),
+            "--bin" => parsed.bin = Some(value()?),
+            "--harness" => parsed.harness = Some(value()?),
+            _ => parsed.fuzz_args.push(arg),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(not(feature = "embedded"))]
+fn metadata() -> Result<Value, Error> {
+    let output = Command::new(cargo())
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+#[cfg(not(feature = "embedded"))]
+fn find_package<'a>(metadata: &'a Value, name: Option<&str>) -> Result<&'a Value, Error> {
+    let packages = metadata["packages"]
+        .as_array()
+        .ok_or_else(|| format_err!("no packages in cargo metadata"))?;
+    let cwd = env::current_dir()?;
+    packages
+        .iter()
+        .find(|p| match name {
+            Some(name) => p["name"] == name,
+            None => p["manifest_path"]
+                .as_str()
+                .and_then(|m| Path::new(m).parent())
+                .map(|dir| cwd.starts_with(dir))
+                .unwrap_or(false),
+        })
+        .or_else(|| match (name, packages.len()) {
+            (None, 1) => packages.first(),
+            _ => None,
+        })
+        .ok_or_else(|| match name {
+            Some(name) => format_err!("package {} not found in workspace", name),
+            None => format_err!("could not determine game package, use --package"),
+        })
+}
+
+#[cfg(not(feature = "embedded"))]
+fn find_bin(package: &Value, name: Option<&str>) -> Result<String, Error> {
+    let bins: Vec<&str> = package["targets"]
+        .as_array()
+        .map(|targets| {
+            targets
+                .iter()
+                .filter(|t| {
+                    t["kind"]
+                        .as_array()
+                        .is_some_and(|k| k.iter().any(|k| k == "bin"))
+                })
+                .filter_map(|t| t["name"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    match (name, bins.as_slice()) {
+        (Some(name), _) if bins.contains(&name) => Ok(name.to_string()),
+        (Some(name), _) => bail!("binary {} not found, available: {:?}", name, bins),
+        (None, [bin]) => Ok(bin.to_string()),
+        (None, []) => bail!("package has no binaries, use --harness GAME_TYPE"),
+        (None, _) => bail!("package has several binaries, use --bin: {:?}", bins),
+    }
+}
+
+#[cfg(not(feature = "embedded"))]
+fn build_bin(package: &str, bin: &str, target_dir: &Path) -> Result<PathBuf, Error> {
+    let status = Command::new(cargo())
+        .args(["build", "--release", "--package", package, "--bin", bin])
+        .status()?;
+    if !status.success() {
+        bail!("failed to build {}", bin);
+    }
+    Ok(target_dir
+        .join("release")
+        .join(format!("{}{}", bin, env::consts::EXE_SUFFIX)))
+}
+
+#[cfg(not(feature = "embedded"))]
+fn run_harness(
+    package: &Value,
+    game_type: &str,
+    target_dir: &Path,
+    fuzz_args: &[String],
+) -> Result<(), Error> {
+    let name = package["name"].as_str().unwrap_or_default();
+    let dir = Path::new(package["manifest_path"].as_str().unwrap_or_default())
+        .parent()
+        .ok_or_else(|| format_err!("invalid manifest path for {}", name))?;
+    let harness = target_dir.join("brdgme-fuzz-harness");
+    fs::create_dir_all(harness.join("src"))?;
+    fs::write(
+        harness.join("Cargo.toml"),
+        format!(
+            r#"[package]
+name = "brdgme-fuzz-harness"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+game = {{ path = {:?}, package = {:?} }}
+brdgme-fuzz = {{ git = "https://github.com/brdgme/fuzz.git" }}
+
+[workspace]
+"#,
+            dir, name
+        ),
+    )?;
+    fs::write(
+        harness.join("src").join("main.rs"),
+        format!(
+            r#"fn main() {{
+    let args: Vec<String> = std::env::args().collect();
+    let cli = brdgme_fuzz::cli::parse(&args).expect("invalid arguments");
+    brdgme_fuzz::fuzz_with_config(brdgme_fuzz::backend::gamer::<game::{}>, cli.config);
+}}
+"#,
+            game_type
+        ),
+    )?;
+    let status = Command::new(cargo())
+        .arg("run")
+        .arg("--release")
+        .arg("--manifest-path")
+        .arg(harness.join("Cargo.toml"))
+        .arg("--")
+        .args(&fuzz_args[1..])
+        .status()?;
+    if !status.success() {
+        bail!("harness exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "embedded"))]
+fn cargo() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
+}
+
+#[cfg(not(feature = "embedded"))]
+fn run() -> Result<(), Error> {
+    let args = parse_args()?;
+    let metadata = metadata()?;
+    let package = find_package(&metadata, args.package.as_deref())?;
+    let target_dir = PathBuf::from(
+        metadata["target_directory"]
+            .as_str()
+            .ok_or_else(|| format_err!("no target directory in cargo metadata"))?,
+    );
+    if let Some(ref game_type) = args.harness {
+        return run_harness(package, game_type, &target_dir, &args.fuzz_args);
+    }
+    let bin = find_bin(package, args.bin.as_deref())?;
+    let path = build_bin(
+        package["name"].as_str().unwrap_or_default(),
+        &bin,
+        &target_dir,
+    )?;
+    let mut fuzz_args = args.fuzz_args;
+    fuzz_args.push(path.to_string_lossy().into_owned());
+    let cli = brdgme_fuzz::cli::parse(&fuzz_args)?;
+    let requester_args = cli.requester_args;
+    if let brdgme_fuzz::cli::Command::CheckRepro { ref path } = cli.command {
+        let finding = brdgme_fuzz::Finding::load(path)?;
+        let mut client = requester::parse_args(&requester_args)?;
+        let reproduction = replay::check_repro(
+            &mut client,
+            &finding,
+            &mut (cli.config.oracles)(),
+            &cli.config.cancel,
+        )?;
+        println!("{:?}", reproduction);
+        process::exit(reproduction.exit_code());
+    }
+    let fail_on_regression = cli.config.fail_on_regression;
+    let summary = brdgme_fuzz::fuzz_with_config(
+        move || requester::parse_args(&requester_args).unwrap(),
+        cli.config,
+    );
+    if summary.failed(fail_on_regression) {
+        bail!("fuzzing failed");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "embedded"))]
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}\n{}", e, USAGE);
+        // A build that fails while bisecting says nothing about the finding,
+        // so the commit is skipped rather than marked good or bad.
+        let bisecting = env::args().any(|a| a == "--check-repro");
+        process::exit(if bisecting {
+            Reproduction::Inconclusive.exit_code()
+        } else {
+            1
+        });
+    }
+}
+
+// The embedded build is only the library.
+#[cfg(feature = "embedded")]
+fn main() {
+    eprintln!("cargo-brdgme-fuzz was built with the embedded feature, which has no command line");
+    std::process::exit(1);
+}
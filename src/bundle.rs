@@ -0,0 +1,200 @@
+use failure::{bail, Error};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+#[cfg(not(feature = "embedded"))]
+use std::process;
+#[cfg(not(feature = "embedded"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(not(feature = "embedded"))]
+use crate::config::Config;
+#[cfg(not(feature = "embedded"))]
+use crate::finding;
+use crate::finding::Finding;
+use crate::storage::Storage;
+
+// Everything about the run that produced a finding which isn't in the
+// finding itself, so it can be reproduced on another machine long after the
+// fuzzing server has moved on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub fuzz_version: String,
+    pub target: Option<String>,
+    pub target_hash: Option<String>,
+    pub rustc: Option<String>,
+    pub os: String,
+    pub kernel: Option<String>,
+    pub args: Vec<String>,
+    pub seed: Option<u64>,
+    pub workers: usize,
+    pub strategy: String,
+    pub names: String,
+    pub oracles: Vec<String>,
+    pub captured_at: u64,
+}
+
+impl Environment {
+    // Runs rustc and reads the clock, which the embedded build has neither
+    // of.
+    #[cfg(not(feature = "embedded"))]
+    pub fn capture(config: &Config, args: &[String], target: Option<&Path>) -> Self {
+        let rustc = process::Command::new("rustc")
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+        Environment {
+            fuzz_version: env!("CARGO_PKG_VERSION").to_string(),
+            target: target.map(|t| t.display().to_string()),
+            target_hash: target
+                .and_then(|t| fs::read(t).ok())
+                .map(|bytes| finding::bytes_hash(&bytes)),
+            rustc,
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            kernel: fs::read_to_string("/proc/sys/kernel/osrelease")
+                .ok()
+                .map(|k| k.trim().to_string()),
+            args: args.to_vec(),
+            seed: config.seed,
+            workers: config.workers,
+            strategy: (config.strategy)().name().to_string(),
+            names: (config.names)().name().to_string(),
+            oracles: (config.oracles)()
+                .stats()
+                .filter(|(_, enabled, _)| *enabled)
+                .map(|(name, _, _)| name.to_string())
+                .collect(),
+            captured_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+
+    // What differs from another environment in ways that could stop a
+    // finding reproducing.
+    pub fn differences(&self, other: &Environment) -> Vec<String> {
+        let mut out = vec![];
+        let mut compare = |what: &str, a: Option<&str>, b: Option<&str>| {
+            if a != b {
+                out.push(format!(
+                    "{} was {} and is now {}",
+                    what,
+                    a.unwrap_or("unknown"),
+                    b.unwrap_or("unknown")
+                ));
+            }
+        };
+        compare(
+            "fuzz version",
+            Some(&self.fuzz_version),
+            Some(&other.fuzz_version),
+        );
+        compare(
+            "target hash",
+            self.target_hash.as_deref(),
+            other.target_hash.as_deref(),
+        );
+        compare("rustc", self.rustc.as_deref(), other.rustc.as_deref());
+        compare("os", Some(&self.os), Some(&other.os));
+        compare("strategy", Some(&self.strategy), Some(&other.strategy));
+        compare("names", Some(&self.names), Some(&other.names));
+        out
+    }
+}
+
+pub struct Bundle {
+    pub finding: Finding,
+    pub environment: Environment,
+}
+
+const FINDING: &str = "finding.json";
+const ENVIRONMENT: &str = "environment.json";
+const BLOCK: usize = 512;
+
+// Writes the finding and its environment as a plain tar next to the finding
+// JSON, named after the finding so the two sort together.
+// Returns where it was saved.
+pub fn save(
+    storage: &dyn Storage,
+    finding: &Finding,
+    environment: &Environment,
+) -> Result<String, Error> {
+    let key = format!("finding-{}.tar", finding.id);
+    let mut tar = vec![];
+    append(
+        &mut tar,
+        FINDING,
+        serde_json::to_string_pretty(finding)?.as_bytes(),
+        environment.captured_at,
+    )?;
+    append(
+        &mut tar,
+        ENVIRONMENT,
+        serde_json::to_string_pretty(environment)?.as_bytes(),
+        environment.captured_at,
+    )?;
+    tar.resize(tar.len() + 2 * BLOCK, 0);
+    storage.put(&key, &tar)?;
+    Ok(storage.locate(&key))
+}
+
+pub fn load(path: &Path) -> Result<Bundle, Error> {
+    let tar = fs::read(path)?;
+    let (mut finding, mut environment) = (None, None);
+    let mut offset = 0;
+    while offset + BLOCK <= tar.len() && tar[offset] != 0 {
+        let header = &tar[offset..offset + BLOCK];
+        let name = field(&header[0..100]);
+        let size = usize::from_str_radix(field(&header[124..136]).trim(), 8)?;
+        let start = offset + BLOCK;
+        let content = match tar.get(start..start + size) {
+            Some(c) => c,
+            None => bail!("{} is truncated", path.display()),
+        };
+        match name {
+            FINDING => finding = Some(serde_json::from_slice(content)?),
+            ENVIRONMENT => environment = Some(serde_json::from_slice(content)?),
+            _ => {}
+        }
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    match (finding, environment) {
+        (Some(finding), Some(environment)) => Ok(Bundle {
+            finding,
+            environment,
+        }),
+        _ => bail!("{} isn't a finding bundle", path.display()),
+    }
+}
+
+fn field(bytes: &[u8]) -> &str {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    std::str::from_utf8(&bytes[..end]).unwrap_or_default()
+}
+
+// A minimal ustar entry, which every tar implementation can extract.
+fn append(tar: &mut Vec<u8>, name: &str, content: &[u8], mtime: u64) -> Result<(), Error> {
+    if name.len() >= 100 {
+        bail!("{} is too long for a tar entry name", name);
+    }
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", mtime).as_bytes());
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    let checksum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    tar.extend_from_slice(&header);
+    tar.extend_from_slice(content);
+    tar.resize(tar.len().div_ceil(BLOCK) * BLOCK, 0);
+    Ok(())
+}
@@ -0,0 +1,1061 @@
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use brdgme_cmd::requester;
+use brdgme_game::Gamer;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::autoscale::Autoscaler;
+use crate::backend::{self, reload::BinaryWatcher};
+use crate::bundle;
+use crate::config::Config;
+use crate::dictionary::Dictionary;
+use crate::engine::{FuzzStep, Fuzzer, TraceSampler};
+use crate::finding::{self, Finding};
+use crate::plateau::{Plateau, PlateauEvent};
+use crate::replay::Reproduction;
+use crate::report::acceptance::{AcceptanceTable, Outcome};
+use crate::report::baseline::Baseline;
+use crate::report::batch::BatchReport;
+use crate::report::budget;
+use crate::report::coverage::Coverage;
+use crate::report::eta::Eta;
+use crate::report::fairness::Fairness;
+use crate::report::finish::FinishReasons;
+use crate::report::header::RunHeader;
+use crate::report::heatmap::Heatmap;
+use crate::report::placings::WinTable;
+use crate::report::reads::ReadTable;
+use crate::report::render_parse::RenderParse;
+use crate::report::rotate::RotatingLog;
+use crate::report::spec_nodes::SpecNodeTable;
+use crate::report::suppress::Suppressions;
+use crate::report::user_errors::UserErrors;
+use crate::report::warmup::{Window, Windows};
+use crate::report::webhook::Notifier;
+use crate::report::{self, FuzzTally, Summary};
+use crate::seen::SeenSet;
+use crate::stats::ShardedStats;
+use crate::stop::Progress;
+use crate::storage::{self, Storage};
+use crate::strategy;
+use crate::throttle::{Admission, FindingThrottle};
+
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+// A ULID, which sorts by creation time, naming one campaign in its logs,
+// stats, metrics and artifact directory.
+pub fn new_campaign_id() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let mut rng = rand::thread_rng();
+    let (high, low): (u16, u64) = (rng.gen(), rng.gen());
+    let random = (u128::from(high) << 64) | u128::from(low);
+    let id = (u128::from(millis & 0xffff_ffff_ffff) << 80) | random;
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((id >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+pub fn fuzz<F, R>(new_requester: F)
+where
+    F: Fn() -> R + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    fuzz_with_config(new_requester, Config::default());
+}
+
+pub fn fuzz_gamer<G>()
+where
+    G: Gamer + Debug + Clone + Serialize + DeserializeOwned + 'static,
+{
+    fuzz(backend::gamer::<G>)
+}
+
+pub fn fuzz_with_config<F, R>(new_requester: F, config: Config) -> Summary
+where
+    F: Fn() -> R + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    // Cancelled when the campaign is done to stop its workers.
+    let cancel = config.cancel.child();
+    let new_requester = Arc::new(Mutex::new(new_requester));
+    let (step_tx, step_rx) = channel();
+    let watcher = config.watch.as_ref().map(BinaryWatcher::new);
+    let generation = watcher.as_ref().map(|w| w.generation()).unwrap_or_default();
+    let trace_budget = Arc::new(AtomicUsize::new(config.trace.games));
+    // Commands are counted in per-worker shards, only the rarer steps go
+    // through the channel.
+    let mut stats = ShardedStats::default();
+
+    let autoscaler = config
+        .autoscale
+        .clone()
+        .map(|a| Autoscaler::new(a, config.workers));
+    let spawned = match config.autoscale {
+        Some(ref a) => a.max,
+        None => config.workers,
+    };
+    let active = match autoscaler {
+        Some(ref a) => a.active(),
+        None => Arc::new(AtomicUsize::new(spawned)),
+    };
+    let plateau = config
+        .plateau
+        .clone()
+        .map(|p| Plateau::new(p, config.dedup.open("plateau")));
+    let escalations = plateau
+        .as_ref()
+        .map(|p| p.escalations())
+        .unwrap_or_default();
+
+    // Workers are joined before the campaign finishes, so every command
+    // they counted is in the final report.
+    let campaign = thread::scope(|scope| {
+        for worker in 0..spawned {
+            let cancel = cancel.clone();
+            let step_tx = step_tx.clone();
+            let new_requester = new_requester.clone();
+            let worker_config = config.clone();
+            let generation = generation.clone();
+            let active = active.clone();
+            let escalations = escalations.clone();
+            let mut stats = stats.shard();
+            let seed = config.worker_seed(worker);
+            let trace_sampler = TraceSampler {
+                config: config.trace.clone(),
+                budget: trace_budget.clone(),
+            };
+            scope.spawn(move || {
+                let client = new_requester.lock().unwrap()();
+                let mut fuzzer = Fuzzer::new(worker, seed, Box::new(client), &worker_config)
+                    .expect("expected to create fuzzer");
+                fuzzer.trace_sampler = Some(trace_sampler);
+                let mut seen_generation = generation.load(Ordering::SeqCst);
+                let (mut seen_escalation, mut seen_reseeds) = (0, 0);
+                while !cancel.is_cancelled() {
+                    stats.hand_over_if_wanted();
+                    if worker >= active.load(Ordering::SeqCst) {
+                        cancel.sleep(Duration::from_millis(100));
+                        continue;
+                    }
+                    let current_generation = generation.load(Ordering::SeqCst);
+                    if current_generation != seen_generation {
+                        seen_generation = current_generation;
+                        fuzzer.recycle(Box::new(new_requester.lock().unwrap()()));
+                    }
+                    if escalations.generation() != seen_escalation {
+                        seen_escalation = escalations.generation();
+                        if let Some(s) = escalations.strategy().and_then(|n| strategy::by_name(&n))
+                        {
+                            fuzzer.set_strategy(s);
+                        }
+                        if escalations.reseeds() != seen_reseeds {
+                            seen_reseeds = escalations.reseeds();
+                            fuzzer.reseed(strategy::derive_seed(seed, seen_reseeds as u64));
+                        }
+                    }
+                    let step = fuzzer.next().expect("failed to get something from fuzzer");
+                    stats.add_overhead(&fuzzer.take_overhead());
+                    match step {
+                        FuzzStep::Command {
+                            head,
+                            outcome,
+                            latency,
+                            user_error,
+                            accepted,
+                        } => stats.record(&head, outcome, latency, user_error, accepted),
+                        // The campaign finished between this worker checking
+                        // the token and sending.
+                        step => {
+                            if step_tx.send(step).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut campaign = Campaign::new(config, watcher);
+        campaign.reverify(&mut new_requester.lock().unwrap()());
+        campaign.tally.active_workers = active.load(Ordering::SeqCst);
+        campaign.autoscaler = autoscaler;
+        campaign.plateau = plateau;
+        campaign.stats = stats;
+        let output_interval = Duration::from_secs(1);
+        // Workers stop sending steps once cancelled, so the wait for one is
+        // kept short enough for the cancellation to be noticed promptly too.
+        let poll_interval = Duration::from_millis(100);
+        let mut last_output_at = Instant::now();
+
+        loop {
+            if last_output_at.elapsed() > output_interval {
+                campaign.tick();
+                last_output_at = Instant::now();
+            }
+            if campaign.should_stop() {
+                break;
+            }
+            match step_rx.recv_timeout(poll_interval) {
+                Ok(step) => {
+                    if campaign.record(step) {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        cancel.cancel();
+        campaign
+    });
+
+    campaign.finish()
+}
+
+// Runs every worker on the calling thread, picking which one steps next from
+// the seed, so a whole campaign including coordination replays exactly.
+pub fn simulate<F, R>(new_requester: F, config: Config) -> Summary
+where
+    F: Fn() -> R,
+    R: requester::Requester + 'static,
+{
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let mut scheduler = strategy::seeded_rng(seed);
+    let trace_budget = Arc::new(AtomicUsize::new(config.trace.games));
+    let mut fuzzers: Vec<Fuzzer> = (0..config.workers.max(1))
+        .map(|worker| {
+            let mut fuzzer = Fuzzer::new(
+                worker,
+                strategy::derive_seed(seed, worker as u64),
+                Box::new(new_requester()),
+                &config,
+            )
+            .expect("expected to create fuzzer");
+            fuzzer.trace_sampler = Some(TraceSampler {
+                config: config.trace.clone(),
+                budget: trace_budget.clone(),
+            });
+            fuzzer
+        })
+        .collect();
+    let mut campaign = Campaign::new(config, None);
+    campaign.reverify(&mut new_requester());
+    campaign.tally.active_workers = fuzzers.len();
+    // Only used for overhead, commands are recorded as steps.
+    let mut shards: Vec<_> = fuzzers.iter().map(|_| campaign.stats.shard()).collect();
+    loop {
+        campaign.check_stop();
+        if campaign.should_stop() {
+            break;
+        }
+        let worker = scheduler.gen_range(0, fuzzers.len());
+        let step = fuzzers[worker].advance();
+        shards[worker].add_overhead(&fuzzers[worker].take_overhead());
+        if campaign.record(step) {
+            break;
+        }
+    }
+    // Hands the overhead over.
+    drop(shards);
+    campaign.finish()
+}
+
+// Runs the same bounded campaign once per seed. Each run is simulated so any
+// cell of the resulting matrix can be reproduced from its seed alone.
+pub fn batch<F, R>(new_requester: F, config: Config, seeds: &[u64]) -> BatchReport
+where
+    F: Fn() -> R,
+    R: requester::Requester + 'static,
+{
+    let mut report = BatchReport::default();
+    for &seed in seeds {
+        let summary = simulate(
+            &new_requester,
+            Config {
+                seed: Some(seed),
+                ..config.clone()
+            },
+        );
+        report.record(seed, &summary);
+    }
+    eprintln!("\n{}", report.render());
+    report
+}
+
+struct Campaign {
+    config: Config,
+    tally: FuzzTally,
+    findings: Vec<Finding>,
+    acceptance: AcceptanceTable,
+    started_at: Instant,
+    watcher: Option<BinaryWatcher>,
+    log: Option<RotatingLog>,
+    last_stats_at: Instant,
+    steps: usize,
+    artifacts: Vec<PathBuf>,
+    // Where artifacts are saved, if anywhere.
+    store: Option<Arc<dyn Storage>>,
+    autoscaler: Option<Autoscaler>,
+    stats: ShardedStats,
+    user_errors: UserErrors,
+    finish_reasons: FinishReasons,
+    fairness: Fairness,
+    wins: WinTable,
+    reads: ReadTable,
+    render_parse: RenderParse,
+    spec_nodes: SpecNodeTable,
+    coverage: Option<Coverage>,
+    plateau: Option<Plateau>,
+    // Total command latency, and the totals once warm-up was over.
+    latency: Duration,
+    warmup: Option<Window>,
+    dictionary: Dictionary,
+    // Signatures of protocol violations already reported.
+    violations: SeenSet,
+    notifier: Option<Notifier>,
+    // Set once a plateau is hit with no escalations left.
+    plateaued: bool,
+    stop_met: bool,
+    header: RunHeader,
+    suppressions: Suppressions,
+    reverified: BTreeMap<String, Result<Reproduction, String>>,
+    // Shadow findings by signature, with how often each was seen.
+    shadow: BTreeMap<String, (Finding, usize)>,
+    heatmap: Option<Heatmap>,
+    throttle: Option<FindingThrottle>,
+    diagnosis: Option<String>,
+}
+
+impl Campaign {
+    fn new(mut config: Config, watcher: Option<BinaryWatcher>) -> Self {
+        let id = config.campaign_id.clone().unwrap_or_else(new_campaign_id);
+        // Each campaign saves its artifacts to a directory of its own, so
+        // runs sharing an artifact directory can be told apart.
+        config.artifact_dir = config.artifact_dir.map(|dir| dir.join(&id));
+        let store = match config.storage {
+            Some(ref storage) => Some(storage::scoped(storage.clone(), &id)),
+            None => config.artifact_dir.as_deref().map(storage::local),
+        };
+        let coverage = if config.collect_coverage {
+            Some(Coverage::default())
+        } else {
+            None
+        };
+        let log = config.soak.log_file.as_ref().and_then(|path| {
+            RotatingLog::open(
+                path,
+                config.soak.log_max_bytes,
+                config.soak.log_max_age,
+                config.soak.log_keep,
+            )
+            .map_err(|e| eprintln!("failed to open log {}: {}", path.display(), e))
+            .ok()
+        });
+        let header = RunHeader::new(&id, &config);
+        eprintln!("{}", header.render());
+        let heatmap = config
+            .heatmap_out
+            .as_ref()
+            .map(|_| Heatmap::new(config.heatmap_bucket));
+        let finish_reasons = FinishReasons::new(config.expected_finish_reasons.clone());
+        let fairness = Fairness::new(config.fairness_tolerance);
+        let violations = config.dedup.open("violations");
+        // Without its suppressions every finding fails the run, which errs
+        // on the safe side.
+        let suppressions = match config.suppressions {
+            Some(ref path) => Suppressions::load(path)
+                .map_err(|e| {
+                    eprintln!("failed to load suppressions from {}: {}", path.display(), e)
+                })
+                .unwrap_or_default(),
+            None => Suppressions::default(),
+        };
+        let notifier = if config.webhooks.is_empty() {
+            None
+        } else {
+            Some(Notifier::new(config.webhooks.clone()))
+        };
+        let throttle = config.max_findings_per_minute.map(FindingThrottle::new);
+        let mut campaign = Campaign {
+            config,
+            tally: FuzzTally {
+                campaign_id: id,
+                ..FuzzTally::default()
+            },
+            findings: vec![],
+            acceptance: AcceptanceTable::default(),
+            started_at: Instant::now(),
+            watcher,
+            log,
+            last_stats_at: Instant::now(),
+            steps: 0,
+            artifacts: vec![],
+            store,
+            autoscaler: None,
+            stats: ShardedStats::default(),
+            user_errors: UserErrors::default(),
+            finish_reasons,
+            fairness,
+            wins: WinTable::default(),
+            reads: ReadTable::default(),
+            render_parse: RenderParse::default(),
+            spec_nodes: SpecNodeTable::default(),
+            coverage,
+            plateau: None,
+            latency: Duration::default(),
+            warmup: None,
+            dictionary: Dictionary::default(),
+            violations,
+            notifier,
+            plateaued: false,
+            stop_met: false,
+            header,
+            suppressions,
+            reverified: BTreeMap::new(),
+            shadow: BTreeMap::new(),
+            heatmap,
+            throttle,
+            diagnosis: None,
+        };
+        for line in campaign.header.render().lines() {
+            campaign.log(line);
+        }
+        campaign
+    }
+
+    fn reverify(&mut self, client: &mut dyn requester::Requester) {
+        if !self.config.reverify_suppressions || self.suppressions.is_empty() {
+            return;
+        }
+        self.reverified =
+            self.suppressions
+                .reverify(client, &mut (self.config.oracles)(), &self.config.cancel);
+        if let Some(reverified) = self.suppressions.render_reverified(&self.reverified) {
+            eprintln!("{}", reverified);
+            for line in reverified.lines() {
+                self.log(line);
+            }
+        }
+    }
+
+    fn log(&mut self, line: &str) {
+        if let Some(ref mut log) = self.log {
+            let line = format!("[{}] {}", self.tally.campaign_id, line);
+            if let Err(e) = log.write_line(&line) {
+                eprintln!("\nfailed to write log, disabling: {}", e);
+                self.log = None;
+            }
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stop_met
+            || self.config.cancel.is_cancelled()
+            || self
+                .config
+                .duration
+                .is_some_and(|d| self.started_at.elapsed() >= d)
+            || self
+                .config
+                .max_steps
+                .is_some_and(|m| self.steps + self.stats.counts().commands >= m)
+    }
+
+    fn progress(&self) -> Progress {
+        let mut signatures: Vec<&str> =
+            self.findings.iter().map(|f| f.signature.as_str()).collect();
+        signatures.sort_unstable();
+        signatures.dedup();
+        Progress {
+            elapsed: self.started_at.elapsed(),
+            games: self.tally.finished,
+            commands: self.tally.commands + self.stats.counts().commands,
+            unique_findings: signatures.len(),
+            plateaued: self.plateaued,
+        }
+    }
+
+    fn check_stop(&mut self) {
+        let condition = match self.config.stop {
+            Some(ref condition) if !self.stop_met => condition,
+            _ => return,
+        };
+        if condition.met(&self.progress()) {
+            let line = format!("stop condition {} met", condition);
+            eprintln!("\n{}", line);
+            self.stop_met = true;
+            self.log(&line);
+        }
+    }
+
+    fn sync_stats(&mut self) {
+        let mut drained = AcceptanceTable::default();
+        let delta = self
+            .stats
+            .drain(&mut drained, &mut self.user_errors, &mut self.dictionary);
+        if let Some(ref mut heatmap) = self.heatmap {
+            heatmap.merge(self.started_at.elapsed(), &drained);
+        }
+        self.acceptance.merge(drained);
+        self.tally.commands += delta.commands;
+        self.tally.invalid_input += delta.invalid_input;
+        self.latency += delta.latency;
+        if let Some(ref mut autoscaler) = self.autoscaler {
+            autoscaler.observe_many(delta.latency, delta.commands);
+        }
+    }
+
+    // Coverage here is coarse: each command head reaching each outcome, and
+    // every distinct user error, finish reason and finding signature.
+    fn poll_plateau(&mut self) -> Option<PlateauEvent> {
+        let plateau = self.plateau.as_mut()?;
+        for (head, row) in &self.acceptance.rows {
+            for (outcome, n) in &[
+                ("accepted", row.accepted),
+                ("rejected", row.rejected),
+                ("partial", row.partial),
+            ] {
+                if *n > 0 {
+                    plateau.observe(&format!("command {} {}", head, outcome));
+                }
+            }
+        }
+        for message in self.user_errors.counts.keys() {
+            plateau.observe(&format!("user error {}", message));
+        }
+        for reason in self.finish_reasons.counts.keys() {
+            plateau.observe(&format!("finish {}", reason));
+        }
+        for finding in &self.findings {
+            plateau.observe(&format!("finding {}", finding.signature));
+        }
+        plateau.poll()
+    }
+
+    fn check_warmup(&mut self) {
+        let warmup = match self.config.warmup {
+            Some(warmup) if self.warmup.is_none() => warmup,
+            _ => return,
+        };
+        let elapsed = self.started_at.elapsed();
+        if elapsed >= warmup {
+            self.sync_stats();
+            self.warmup = Some(Window::new(&self.tally, elapsed, self.latency));
+            self.log(&format!("warm-up over after {:.1}s", elapsed.as_secs_f64()));
+        }
+    }
+
+    fn tick(&mut self) {
+        self.sync_stats();
+        self.check_warmup();
+        self.tally.progress = Eta::estimate(
+            &self.config,
+            &self.progress(),
+            self.steps + self.stats.counts().commands,
+        );
+        report::output_tally(&self.tally);
+        let line = self.tally.render();
+        self.log(&line);
+        if let Some(line) = self.user_errors.render_top(3) {
+            eprintln!("{}", line);
+            self.log(&line);
+        }
+        if let Some(ref mut watcher) = self.watcher {
+            if let Some(version) = watcher.poll() {
+                let line = format!(
+                    "{} changed, recycling workers onto version {}",
+                    watcher.path().display(),
+                    version
+                );
+                eprintln!("\n{}", line);
+                self.log(&line);
+            }
+        }
+        if let Some(event) = self.autoscaler.as_mut().and_then(|a| a.poll()) {
+            self.tally.active_workers = event.to;
+            self.tally.scaling_events += 1;
+            let line = format!(
+                "scaling workers from {} to {}: {}",
+                event.from, event.to, event.reason
+            );
+            eprintln!("\n{}", line);
+            self.log(&line);
+        }
+        if let Some(event) = self.poll_plateau() {
+            self.tally.plateaus += 1;
+            self.plateaued |= event.action.is_none();
+            let line = format!(
+                "no new coverage in {:.0}s, {}",
+                event.idle.as_secs_f64(),
+                match event.action {
+                    Some(action) => action.to_string(),
+                    None => "no escalations left".to_string(),
+                }
+            );
+            eprintln!("\n{}", line);
+            self.log(&line);
+        }
+        if self.last_stats_at.elapsed() >= self.config.soak.stats_interval {
+            self.flush_stats();
+            self.last_stats_at = Instant::now();
+        }
+        self.check_stop();
+    }
+
+    fn flush_stats(&self) {
+        if let Some(ref path) = self.config.soak.stats_file {
+            let written = serde_json::to_string_pretty(&self.tally)
+                .map_err(failure::Error::from)
+                .and_then(|s| Ok(std::fs::write(path, s)?));
+            if let Err(e) = written {
+                eprintln!("\nfailed to write stats to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    // Returns true when the campaign should stop.
+    fn record(&mut self, step: FuzzStep) -> bool {
+        // Spec choices and states reached ride along with a command rather than being a step of
+        // their own.
+        if !matches!(step, FuzzStep::SpecNodes { .. } | FuzzStep::Reached(_)) {
+            self.steps += 1;
+        }
+        self.check_warmup();
+        match step {
+            FuzzStep::Created => self.tally.started += 1,
+            FuzzStep::Finished {
+                turns,
+                reason,
+                players,
+                offered,
+                moves,
+                placings,
+            } => {
+                self.finish_reasons.record(&reason);
+                self.fairness.record(players, &offered, &moves);
+                if let Some(ref placings) = placings {
+                    self.wins.record(players, placings);
+                }
+                self.tally.finished += 1;
+                self.tally.finished_turns += turns;
+                self.tally.longest_game = self.tally.longest_game.max(turns);
+            }
+            FuzzStep::Command {
+                head,
+                outcome,
+                latency,
+                user_error,
+                accepted,
+            } => {
+                if let Some(sample) = user_error {
+                    self.user_errors.record(sample);
+                }
+                if let Some(command) = accepted {
+                    self.dictionary.insert(&command);
+                }
+                self.tally.commands += 1;
+                if outcome != Outcome::Accepted {
+                    self.tally.invalid_input += 1;
+                }
+                self.acceptance.record(&head, outcome, latency);
+                if let Some(ref mut heatmap) = self.heatmap {
+                    heatmap.record(self.started_at.elapsed(), &head, outcome);
+                }
+                self.latency += latency;
+                if let Some(ref mut autoscaler) = self.autoscaler {
+                    autoscaler.observe(latency);
+                }
+            }
+            FuzzStep::Read { kind, latency, ok } => self.reads.record(kind, latency, ok),
+            FuzzStep::BlindSpot(blind_spot) => {
+                match blind_spot.player {
+                    Some(player) => {
+                        self.tally.skipped_turns += 1;
+                        self.log(&format!(
+                            "game {} skipped player {}'s turn after {} rejected commands",
+                            blind_spot.game_id, player, blind_spot.attempts
+                        ));
+                    }
+                    None => {
+                        self.tally.blind_spots += 1;
+                        self.log(&format!(
+                            "game {} abandoned after {} rejected commands",
+                            blind_spot.game_id, blind_spot.attempts
+                        ));
+                    }
+                }
+                if let Some(ref store) = self.store {
+                    match blind_spot.save(store.as_ref(), &self.config.canonical) {
+                        Ok(path) => {
+                            let path = PathBuf::from(path);
+                            if !self.artifacts.contains(&path) {
+                                self.artifacts.push(path);
+                            }
+                        }
+                        Err(e) => eprintln!("\nfailed to save blind spot: {}", e),
+                    }
+                }
+            }
+            FuzzStep::SpecNodes { choices, outcome } => {
+                if let Some(ref mut coverage) = self.coverage {
+                    coverage.record(choices.as_deref(), outcome);
+                }
+                self.spec_nodes.record(choices, outcome)
+            }
+            FuzzStep::Reached(state) => {
+                if let Some(ref mut coverage) = self.coverage {
+                    coverage.reach(state);
+                }
+            }
+            FuzzStep::RenderParse { latency, bytes, ok } => {
+                self.render_parse.record(latency, bytes, ok)
+            }
+            FuzzStep::Finding(finding) => {
+                // Known issues don't stop the campaign, or it would keep
+                // stopping on the same bug.
+                let known = self.suppressions.suppresses(&finding);
+                self.finding(*finding);
+                return !self.config.keep_going && !known;
+            }
+            FuzzStep::Shadow(finding) => self.shadow(*finding),
+            FuzzStep::Violation(finding) => {
+                self.tally.protocol_violations += 1;
+                if self.violations.insert(&finding.signature) {
+                    self.finding(*finding);
+                }
+            }
+        }
+        false
+    }
+
+    // Logged and saved once per signature, away from the real findings.
+    fn shadow(&mut self, mut finding: Finding) {
+        if let Some(entry) = self.shadow.get_mut(&finding.signature) {
+            entry.1 += 1;
+            return;
+        }
+        finding.campaign_id = Some(self.tally.campaign_id.clone());
+        let line = format!(
+            "[shadow] {}: {}",
+            finding.oracle.as_deref().unwrap_or("unknown"),
+            finding.message
+        );
+        eprintln!("\n{}", line);
+        self.log(&line);
+        if let Some(ref store) = self.store {
+            let redacted = self.config.redact.redact_finding(&finding);
+            if let Err(e) = redacted.store(storage::scoped(store.clone(), "shadow").as_ref()) {
+                eprintln!("\nfailed to save shadow finding: {}", e);
+            }
+        }
+        self.shadow.insert(finding.signature.clone(), (finding, 1));
+    }
+
+    fn finding(&mut self, mut finding: Finding) {
+        finding.campaign_id = Some(self.tally.campaign_id.clone());
+        let admission = self.throttle.as_mut().map(|t| t.admit());
+        if admission == Some(Admission::Capped) {
+            self.capped();
+        }
+        if admission.is_some_and(|a| a != Admission::Process) {
+            self.findings.push(finding);
+            return;
+        }
+        println!("\n{}", finding);
+        self.log(&finding.to_string());
+        let mut saved = None;
+        if let Some(ref store) = self.store {
+            let redacted = self.config.redact.redact_finding(&finding);
+            match redacted.store(store.as_ref()) {
+                Ok(path) => {
+                    println!("\nSaved finding to {}", path);
+                    self.artifacts.push(PathBuf::from(&path));
+                    saved = Some(path);
+                }
+                Err(e) => eprintln!("\nfailed to save finding: {}", e),
+            }
+            if let Some(ref environment) = self.config.bundle {
+                match bundle::save(store.as_ref(), &redacted, environment) {
+                    Ok(path) => {
+                        println!("Bundled with its environment in {}", path);
+                        self.artifacts.push(PathBuf::from(&path));
+                        saved = Some(path);
+                    }
+                    Err(e) => eprintln!("\nfailed to bundle finding: {}", e),
+                }
+            }
+            // Only the artifact directory is evicted from, storage elsewhere
+            // is left to its own expiry rules.
+            let local = self.config.storage.is_none();
+            if let (Some(max_bytes), Some(dir), true) = (
+                self.config.soak.artifact_max_bytes,
+                &self.config.artifact_dir,
+                local,
+            ) {
+                match finding::evict(dir, max_bytes) {
+                    Ok(evicted) => {
+                        for path in evicted {
+                            self.log(&format!("evicted {}", path.display()));
+                            self.artifacts.retain(|p| *p != path);
+                        }
+                    }
+                    Err(e) => eprintln!("\nfailed to evict findings: {}", e),
+                }
+            }
+        }
+        if let Some(ref notifier) = self.notifier {
+            if !self.suppressions.suppresses(&finding)
+                && !self
+                    .findings
+                    .iter()
+                    .any(|f| f.signature == finding.signature)
+            {
+                notifier.finding(
+                    &self.config.redact.redact_finding(&finding),
+                    saved.as_deref(),
+                );
+            }
+        }
+        self.findings.push(finding);
+    }
+
+    // With no game finished the target is most likely broken outright, and
+    // the earliest finding is the one worth looking at.
+    fn capped(&mut self) {
+        let throttle = match self.throttle {
+            Some(ref t) => t,
+            None => return,
+        };
+        let line = match self.findings.first() {
+            Some(earliest) if self.tally.finished == 0 => {
+                let line = format!(
+                    "Target appears fundamentally broken: {} findings and no finished game in {:.0}s. Earliest finding: {} {}",
+                    self.findings.len() + 1,
+                    self.started_at.elapsed().as_secs_f64(),
+                    earliest.id,
+                    earliest.message
+                );
+                self.diagnosis = Some(line.clone());
+                line
+            }
+            _ => format!(
+                "Finding cap reached, not printing or saving findings for {:.0}s",
+                throttle.remaining().as_secs_f64()
+            ),
+        };
+        eprintln!("\n{}", line);
+        self.log(&line);
+    }
+
+    fn finish(mut self) -> Summary {
+        self.sync_stats();
+        // Progress only means something while the campaign is running.
+        self.tally.progress = None;
+        self.flush_stats();
+        let elapsed = self.started_at.elapsed();
+        let total = Window::new(&self.tally, elapsed, self.latency);
+        let windows = self.config.warmup.map(|_| match self.warmup {
+            Some(ref warmup) => Windows {
+                steady: total.since(warmup),
+                warmup: warmup.clone(),
+            },
+            // The campaign never got past warm-up.
+            None => Windows {
+                warmup: total.clone(),
+                steady: Window::default(),
+            },
+        });
+        // Baselines compare steady-state numbers when there are any, so
+        // start-up cost doesn't count against a run.
+        let current = match windows {
+            Some(ref w) if w.steady.commands > 0 => {
+                Baseline::from_tally(&w.steady.tally(), w.steady.elapsed)
+            }
+            _ => Baseline::from_tally(&self.tally, elapsed),
+        };
+        if let Some(ref path) = self.config.save_baseline {
+            if let Err(e) = current.save(path) {
+                eprintln!("\nfailed to save baseline to {}: {}", path.display(), e);
+            }
+        }
+        let regressions = match self.config.baseline {
+            Some(ref path) => match Baseline::load(path) {
+                Ok(baseline) => baseline.compare(&current, &self.config.baseline_thresholds),
+                Err(e) => {
+                    eprintln!("\nfailed to load baseline from {}: {}", path.display(), e);
+                    vec![]
+                }
+            },
+            None => vec![],
+        };
+        for r in &regressions {
+            eprintln!(
+                "\nRegression in {}: baseline {:.3}, current {:.3}",
+                r.metric, r.baseline, r.current
+            );
+        }
+        eprintln!("\n{}", self.acceptance.render());
+        if let Some(ref path) = self.config.dictionary_out {
+            match self.dictionary.save(path) {
+                Ok(()) => eprintln!(
+                    "\nSaved {} accepted commands to {}",
+                    self.dictionary.len(),
+                    path.display()
+                ),
+                Err(e) => eprintln!("\nfailed to save dictionary to {}: {}", path.display(), e),
+            }
+        }
+        self.config.dedup.save("violations", &self.violations);
+        let mut dedup = vec![("violation signatures".to_string(), self.violations.stats())];
+        if let Some(ref plateau) = self.plateau {
+            self.config.dedup.save("plateau", plateau.seen());
+            dedup.push(("plateau fingerprints".to_string(), plateau.seen().stats()));
+        }
+        if let (Some(ref path), Some(ref heatmap)) = (&self.config.heatmap_out, &self.heatmap) {
+            if let Err(e) = heatmap.save(path) {
+                eprintln!("\nfailed to save heatmap to {}: {}", path.display(), e);
+            }
+        }
+        if let Some(ref path) = self.config.spec_stats_out {
+            if let Err(e) = self.spec_nodes.save(path) {
+                eprintln!(
+                    "\nfailed to save spec node stats to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        let reports = [
+            self.config.soak.log_file.as_ref(),
+            self.config.soak.stats_file.as_ref(),
+            self.config.save_baseline.as_ref(),
+            self.config.dictionary_out.as_ref(),
+            self.config.spec_stats_out.as_ref(),
+            self.config.heatmap_out.as_ref(),
+            self.config.dedup.state_dir.as_ref(),
+            Some(&self.config.trace.dir),
+        ]
+        .iter()
+        .flatten()
+        .filter(|p| p.exists())
+        .map(|p| p.to_path_buf())
+        .collect();
+        let mut summary = Summary {
+            header: self.header,
+            tally: self.tally,
+            elapsed,
+            findings: self.findings,
+            acceptance: self.acceptance,
+            regressions,
+            artifacts: self.artifacts,
+            reports,
+            user_errors: self.user_errors,
+            finish_reasons: self.finish_reasons,
+            fairness: self.fairness,
+            wins: self.wins,
+            reads: self.reads,
+            render_parse: self.render_parse,
+            windows,
+            breaches: vec![],
+            dedup,
+            suppressions: self.suppressions,
+            reverified: self.reverified,
+            shadow: self.shadow.into_values().collect(),
+            overhead: self.stats.overheads(),
+            throttled: self.throttle.map(|t| t.throttled).unwrap_or_default(),
+            diagnosis: self.diagnosis,
+            coverage: self.coverage,
+        };
+        summary.breaches = budget::check(&self.config.budgets, &summary);
+        eprintln!("\n{}", summary.render());
+        if let Some(notifier) = self.notifier {
+            notifier.finished(&summary);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use brdgme_cmd::api;
+    use brdgme_cmd::requester::Requester;
+
+    use crate::mock::{MockRequester, ToyGame};
+
+    // A toy game failing every fifth command, so the campaign has findings
+    // to agree on.
+    fn flaky() -> MockRequester {
+        let mut toy = ToyGame::default();
+        let mut plays = 0;
+        MockRequester::new().fallback(move |req| {
+            if let api::Request::Play { .. } = *req {
+                plays += 1;
+                if plays % 5 == 0 {
+                    failure::bail!("play {} failed", plays);
+                }
+            }
+            toy.request(req)
+        })
+    }
+
+    #[test]
+    fn simulations_replay_exactly() {
+        let config = Config {
+            workers: 3,
+            seed: Some(42),
+            max_steps: Some(300),
+            keep_going: true,
+            campaign_id: Some("simulated".to_string()),
+            ..Config::default()
+        };
+        let first = simulate(flaky, config.clone());
+        let second = simulate(flaky, config);
+
+        assert_eq!(
+            serde_json::to_value(&first.tally).unwrap(),
+            serde_json::to_value(&second.tally).unwrap()
+        );
+        assert!(!first.findings.is_empty());
+        let findings = |summary: &Summary| -> Vec<(String, Vec<finding::HistoryEntry>)> {
+            summary
+                .findings
+                .iter()
+                .map(|f| (f.id.clone(), f.history.to_vec()))
+                .collect()
+        };
+        assert_eq!(findings(&first), findings(&second));
+    }
+
+    #[test]
+    fn reports_every_command_workers_counted() {
+        let summary = fuzz_with_config(
+            ToyGame::default,
+            Config {
+                workers: 3,
+                max_steps: Some(500),
+                keep_going: true,
+                ..Config::default()
+            },
+        );
+        let tabled: usize = summary.acceptance.rows.values().map(|r| r.generated).sum();
+        assert!(summary.tally.commands > 0);
+        assert_eq!(tabled, summary.tally.commands);
+    }
+}
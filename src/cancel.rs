@@ -0,0 +1,75 @@
+use failure::{Error, Fail};
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(not(feature = "embedded"))]
+use std::thread;
+#[cfg(not(feature = "embedded"))]
+use std::time::{Duration, Instant};
+
+// Asks running work to stop. Clones share the flag, so one handed to a
+// campaign through Config::cancel stops it from any thread, and long
+// operations check it between steps instead of running to the end.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    parent: Option<Arc<CancelToken>>,
+}
+
+impl CancelToken {
+    // A token cancelled along with this one, which can also be cancelled on
+    // its own without touching this one, such as a campaign stopping its
+    // workers when it's done.
+    pub fn child(&self) -> Self {
+        CancelToken {
+            cancelled: Arc::default(),
+            parent: Some(Arc::new(self.clone())),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    // For bailing out of operations that return errors.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+
+    // Sleeps for the duration unless cancelled first, returning whether it
+    // was.
+    #[cfg(not(feature = "embedded"))]
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let until = Instant::now() + duration;
+        while !self.is_cancelled() {
+            let left = until.saturating_duration_since(Instant::now());
+            if left == Duration::default() {
+                return false;
+            }
+            thread::sleep(left.min(Duration::from_millis(100)));
+        }
+        true
+    }
+}
+
+// Returned by operations that stopped part way because their token was
+// cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl Fail for Cancelled {}
@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+use std::sync::Arc;
+
+use crate::finding;
+
+pub type CanonicalHook = Arc<dyn Fn(&mut Value) + Send + Sync>;
+
+// Applied to game states before they're hashed, compared or diffed, so
+// fields that legitimately differ between otherwise identical states, such
+// as timestamps, caches or sets serialised in hash order, don't turn up as
+// divergences or new states. Only JSON states are canonicalised.
+#[derive(Clone, Default)]
+pub struct Canonicalizer {
+    // JSON pointers to drop.
+    ignored: Vec<String>,
+    // JSON pointers to arrays whose order doesn't matter.
+    unordered: Vec<String>,
+    hooks: Vec<CanonicalHook>,
+}
+
+impl Canonicalizer {
+    pub fn ignore(&mut self, pointer: String) {
+        self.ignored.push(pointer);
+    }
+
+    pub fn unordered(&mut self, pointer: String) {
+        self.unordered.push(pointer);
+    }
+
+    // For anything the pointer rules can't express, run after them.
+    pub fn hook(&mut self, hook: impl Fn(&mut Value) + Send + Sync + 'static) {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ignored.is_empty() && self.unordered.is_empty() && self.hooks.is_empty()
+    }
+
+    pub fn canonicalize(&self, value: &mut Value) {
+        for pointer in &self.ignored {
+            remove(value, pointer);
+        }
+        for pointer in &self.unordered {
+            if let Some(Value::Array(items)) = value.pointer_mut(pointer) {
+                items.sort_by_cached_key(|v| v.to_string());
+            }
+        }
+        for hook in &self.hooks {
+            hook(value);
+        }
+    }
+
+    // The canonical serialisation of a state, or the state as it is when
+    // there's nothing to do or it isn't JSON.
+    pub fn state(&self, state: &str) -> String {
+        if self.is_empty() {
+            return state.to_string();
+        }
+        match serde_json::from_str::<Value>(state) {
+            Ok(mut value) => {
+                self.canonicalize(&mut value);
+                value.to_string()
+            }
+            Err(_) => state.to_string(),
+        }
+    }
+
+    pub fn hash(&self, state: &str) -> String {
+        finding::state_hash(&self.state(state))
+    }
+
+    // Compares parsed states so formatting differences don't count either.
+    pub fn same(&self, a: &str, b: &str) -> bool {
+        match (
+            serde_json::from_str::<Value>(a),
+            serde_json::from_str::<Value>(b),
+        ) {
+            (Ok(mut a), Ok(mut b)) => {
+                self.canonicalize(&mut a);
+                self.canonicalize(&mut b);
+                a == b
+            }
+            _ => a == b,
+        }
+    }
+}
+
+// Array elements are nulled rather than removed so later indexes still line
+// up between states.
+fn remove(value: &mut Value, pointer: &str) {
+    let (parent, key) = match pointer.rfind('/') {
+        Some(i) => (&pointer[..i], &pointer[i + 1..]),
+        None => return,
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.remove(&key);
+        }
+        Some(Value::Array(items)) => {
+            if let Some(item) = key.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                *item = Value::Null;
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,793 @@
+use failure::{bail, format_err, Error};
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+use crate::backend::http::HttpRequester;
+use crate::bundle::Environment;
+use crate::config::{
+    AutoscaleConfig, BotConfig, BotFactory, Config, PlateauConfig, PlayerCounts, ResignConfig,
+};
+use crate::dashboard::Tenant;
+use crate::dictionary::{Dictionary, DictionaryStrategy};
+use crate::names;
+#[cfg(feature = "http")]
+use crate::oracle::RaceOracle;
+use crate::oracle::{
+    ConservationOracle, ConservationRule, MagnitudeOracle, NumericFormatOracle, OracleRegistry,
+    RobustnessOracle, TurnStreakOracle, VolumeOracle,
+};
+use crate::pipeline::Phase;
+use crate::plateau::Escalation;
+#[cfg(feature = "plugins")]
+use crate::plugin::{self, Plugin};
+use crate::share::Prefer;
+use crate::storage;
+use crate::strategy::{self, bot::StrategyBot, BotSeats};
+use crate::wallclock::WallClock;
+
+use crate::report::storybook;
+
+pub enum Command {
+    Fuzz,
+    Simulate,
+    Batch {
+        seeds: Vec<u64>,
+        report: Option<PathBuf>,
+    },
+    Storybook {
+        path: PathBuf,
+        format: storybook::Format,
+    },
+    CheckRepro {
+        path: PathBuf,
+    },
+    Diverge {
+        path: PathBuf,
+    },
+    Triage {
+        path: PathBuf,
+    },
+    Daemon {
+        schedule: PathBuf,
+        dir: PathBuf,
+        keep_runs: usize,
+    },
+    ValidateConfig,
+    Pipeline {
+        phases: Vec<Phase>,
+    },
+    Eval {
+        games: usize,
+        bots: (String, String),
+    },
+    Compare {
+        strategies: (String, String),
+        report: Option<PathBuf>,
+    },
+    Export {
+        dir: PathBuf,
+        bundle: PathBuf,
+    },
+    Import {
+        bundle: PathBuf,
+        dir: PathBuf,
+        prefer: Prefer,
+    },
+    Dashboard {
+        tenants: Vec<Tenant>,
+        listen: String,
+        // Written instead of serving, with links under the URL.
+        feed: Option<(PathBuf, String)>,
+    },
+}
+
+pub struct Cli {
+    pub command: Command,
+    pub config: Config,
+    pub requester_args: Vec<String>,
+}
+
+pub const USAGE: &str = "Usage: brdgme-fuzz [--workers N] [--hot-reload] [--duration SECS]
+                   [--save-baseline PATH] [--baseline PATH] [--fail-on-regression]
+                   [--trace-games K] [--trace-rate P] [--trace-dir DIR]
+                   [--artifacts DIR] [--keep-going] [--suppressions PATH [--reverify-suppressions]]
+                   [--storage DIR|s3://BUCKET[/PREFIX]]
+                   [--stop time=D|games=N|commands=N|findings=N|plateau|file=PATH[&|...]]
+                   [--seed N] [--steps N] [--deterministic] [--session CHECKPOINT_EVERY] [--campaign-id ID]
+                   [--redact POINTER_OR_FIELD]... [--ignore-state POINTER]... [--unordered-state POINTER]...
+                   [--seeds A..B|A,B,C [--batch-report PATH]] [--eval GAMES STRATEGY:STRATEGY]
+                   [--compare STRATEGY:STRATEGY [--compare-report PATH]]
+                   [--phase STRATEGY:DURATION|replay]...
+                   [--autoscale MIN:MAX] [--autoscale-interval SECS] [--finish-reason REASON]...
+                   [--fairness-tolerance F] [--bundle] [--spectate P] [--render-sample P]
+                   [--max-rejections N] [--state-checkpoints K] [--check-player-refs]
+                   [--max-turn-attempts N[:skip|flag]]
+                   [--check-rejections] [--strict-rejections P] [--simultaneous] [--starvation STATES]
+                   [--max-findings-per-minute N]
+                   [--only-min-players|--only-max-players] [--warmup SECS]
+                   [--budget user-errors=PCT%|partial=N|violations=N|read-failures=N|render-ms=MS]...
+                   [--export-dictionary PATH] [--dictionary PATH] [--spec-stats PATH]
+                   [--heatmap PATH [--heatmap-bucket SECS]]
+                   [--webhook [slack:|discord:]URL [--webhook-template T] [--webhook-finished-template T]]...
+                   [--dedup-capacity N] [--dedup-bloom-bits N] [--dedup-state DIR]
+                   [--plateau SECS [--on-plateau strategy:NAME|reseed]...]
+                   [--game-phases LABEL:UNTIL_TURN,...,LABEL [--phase-strategy PHASE:STRATEGY]...]
+                   [--bot STRATEGY [--bot-seats SEAT,...|random]]
+                   [--resign-command COMMAND [--resign-rate P]] [--command-delay MS|MIN..MAX|exp:MEAN]
+                   [--virtual-clock START_SECS [--clock-tick SECS] [--clock-skip P:MAX_SECS]]
+                   [--strategy random|overflow|PLUGIN] [--names default|prefix|commands|A,B,C] [--jump-threshold N] [--robustness N] [--race URL]
+                   [--max-response-bytes N] [--max-logs N] [--numeric-format]
+                   [--conserve [up:|down:]POINTER,...]... [--plugin LIBRARY]...
+                   [--shadow-oracle NAME]... [--max-consecutive-turns K]
+                   [--log-file PATH] [--log-max-bytes N] [--log-max-age SECS] [--log-keep N]
+                   [--stats-file PATH] [--stats-interval SECS] [--artifacts-max-bytes N]
+                   REQUESTER_ARGS | --subprocess [--stderr-lines N] [--offline] [--sandbox]
+                                  [--cpu-limit SECS] [--memory-limit MB] [--fd-limit N]
+                                  [--protocol-log DIR [--protocol-log-max-bytes N]]
+                                  GAME_BINARY [ARGS...]
+       brdgme-fuzz storybook [--html] TRACE_OR_FINDING [REQUESTER_ARGS...]
+       brdgme-fuzz daemon SCHEDULE DIR [--keep-runs N] [FUZZ_FLAGS...]
+       brdgme-fuzz export TARGET_DIR BUNDLE
+       brdgme-fuzz import BUNDLE TARGET_DIR [--prefer-imported]
+       brdgme-fuzz dashboard [NAME=]DIR... [--listen ADDR | --feed PATH --feed-url URL]
+       brdgme-fuzz triage FINDING [ORACLE_FLAGS...] REQUESTER_ARGS...
+       brdgme-fuzz --check-repro FINDING [ORACLE_FLAGS...] REQUESTER_ARGS...
+       brdgme-fuzz --diverge TRACE REQUESTER_ARGS...
+       brdgme-fuzz --validate-config [FUZZ_FLAGS...] REQUESTER_ARGS...";
+
+pub fn parse(args: &[String]) -> Result<Cli, Error> {
+    let mut config = Config::default();
+    let mut command = Command::Fuzz;
+    let mut requester_args = vec![];
+    let mut hot_reload = false;
+    let mut jump_threshold = None;
+    let mut robustness = None;
+    let (mut max_response_bytes, mut max_logs) = (None, None);
+    let mut numeric_format = false;
+    let mut max_consecutive_turns = None;
+    let mut race: Option<String> = None;
+    let mut conserve: Vec<ConservationRule> = vec![];
+    let mut plugin_paths: Vec<PathBuf> = vec![];
+    let mut shadow: Vec<String> = vec![];
+    // A strategy that isn't built in, looked up in plugins once they're
+    // loaded.
+    let mut plugin_strategy: Option<String> = None;
+    let mut bot_seats: Option<BotSeats> = None;
+    let mut resign_rate: Option<f64> = None;
+    let mut clock_tick: Option<u64> = None;
+    let mut clock_skip: Option<(f64, u64)> = None;
+    let mut bundle = false;
+    let mut dictionary: Option<PathBuf> = None;
+    let mut on_plateau: Vec<Escalation> = vec![];
+    let args_for_bundle = args.to_vec();
+    let mut args = args.iter().peekable();
+    if let Some(program) = args.next() {
+        requester_args.push(program.to_owned());
+    }
+    if args.peek().map(|a| a.as_str()) == Some("storybook") {
+        args.next();
+        let mut format = storybook::Format::Markdown;
+        if args.peek().map(|a| a.as_str()) == Some("--html") {
+            args.next();
+            format = storybook::Format::Html;
+        }
+        command = Command::Storybook {
+            path: value("storybook", args.next())?,
+            format,
+        };
+    } else if args.peek().map(|a| a.as_str()) == Some("daemon") {
+        args.next();
+        command = Command::Daemon {
+            schedule: value("daemon", args.next())?,
+            dir: value("daemon", args.next())?,
+            keep_runs: 10,
+        };
+    } else if args.peek().map(|a| a.as_str()) == Some("export") {
+        args.next();
+        command = Command::Export {
+            dir: value("export", args.next())?,
+            bundle: value("export", args.next())?,
+        };
+    } else if args.peek().map(|a| a.as_str()) == Some("import") {
+        args.next();
+        command = Command::Import {
+            bundle: value("import", args.next())?,
+            dir: value("import", args.next())?,
+            prefer: Prefer::Local,
+        };
+    } else if args.peek().map(|a| a.as_str()) == Some("dashboard") {
+        args.next();
+        let mut tenants = vec![];
+        while let Some(dir) = args.next_if(|a| !a.starts_with("--")) {
+            tenants.push(value("dashboard", Some(dir))?);
+        }
+        if tenants.is_empty() {
+            bail!("dashboard requires at least one directory");
+        }
+        command = Command::Dashboard {
+            tenants,
+            listen: "127.0.0.1:8080".to_string(),
+            feed: None,
+        };
+    } else if args.peek().map(|a| a.as_str()) == Some("triage") {
+        args.next();
+        command = Command::Triage {
+            path: value("triage", args.next())?,
+        };
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--workers" => config.workers = value(arg, args.next())?,
+            "--hot-reload" => hot_reload = true,
+            "--duration" => config.duration = Some(Duration::from_secs(value(arg, args.next())?)),
+            "--save-baseline" => config.save_baseline = Some(value::<PathBuf>(arg, args.next())?),
+            "--baseline" => config.baseline = Some(value::<PathBuf>(arg, args.next())?),
+            "--fail-on-regression" => config.fail_on_regression = true,
+            "--trace-games" => config.trace.games = value(arg, args.next())?,
+            "--trace-rate" => config.trace.rate = value(arg, args.next())?,
+            "--trace-dir" => config.trace.dir = value(arg, args.next())?,
+            "--artifacts" => config.artifact_dir = Some(value::<PathBuf>(arg, args.next())?),
+            "--storage" => {
+                config.storage = Some(storage::parse(&value::<String>(arg, args.next())?)?)
+            }
+            "--keep-going" => config.keep_going = true,
+            "--suppressions" => config.suppressions = Some(value::<PathBuf>(arg, args.next())?),
+            "--reverify-suppressions" => config.reverify_suppressions = true,
+            "--campaign-id" => config.campaign_id = Some(value(arg, args.next())?),
+            "--seed" => config.seed = Some(value(arg, args.next())?),
+            "--steps" => config.max_steps = Some(value(arg, args.next())?),
+            "--stop" => config.stop = Some(value(arg, args.next())?),
+            "--deterministic" => command = Command::Simulate,
+            "--validate-config" => command = Command::ValidateConfig,
+            "--names" => {
+                let spec: String = value(arg, args.next())?;
+                names::by_name(&spec)?;
+                config.names = Arc::new(move || names::by_name(&spec).unwrap());
+            }
+            "--strategy" => {
+                let name: String = value(arg, args.next())?;
+                if strategy::by_name(&name).is_none() {
+                    plugin_strategy = Some(name);
+                    continue;
+                }
+                plugin_strategy = None;
+                if name == "overflow" && jump_threshold.is_none() {
+                    jump_threshold = Some(1_000_000.0);
+                }
+                config.strategy = Arc::new(move || strategy::by_name(&name).unwrap());
+            }
+            "--game-phases" => {
+                config.game_phase = Some(strategy::phase::by_turn(&value::<String>(
+                    arg,
+                    args.next(),
+                )?)?)
+            }
+            "--phase-strategy" => {
+                let spec: String = value(arg, args.next())?;
+                let (phase, name) = spec.split_once(':').ok_or_else(|| {
+                    format_err!("--phase-strategy expects PHASE:STRATEGY, got {}", spec)
+                })?;
+                if strategy::by_name(name).is_none() {
+                    bail!("unknown strategy {}", name);
+                }
+                let name = name.to_string();
+                config.phase_strategies.push((
+                    phase.to_string(),
+                    Arc::new(move || strategy::by_name(&name).unwrap()),
+                ));
+            }
+            "--bot" => {
+                let name: String = value(arg, args.next())?;
+                if strategy::by_name(&name).is_none() {
+                    bail!("unknown strategy {}", name);
+                }
+                let bot: BotFactory =
+                    Arc::new(move || Box::new(StrategyBot(strategy::by_name(&name).unwrap())));
+                config.bot = Some(BotConfig {
+                    bot,
+                    seats: BotSeats::Random,
+                });
+            }
+            "--resign-command" => {
+                config.resign = Some(ResignConfig {
+                    command: value(arg, args.next())?,
+                    rate: 0.01,
+                })
+            }
+            "--virtual-clock" => {
+                config.wallclock = Some(WallClock {
+                    start: value(arg, args.next())?,
+                    ..WallClock::default()
+                })
+            }
+            "--clock-tick" => clock_tick = Some(value(arg, args.next())?),
+            "--clock-skip" => {
+                let spec: String = value(arg, args.next())?;
+                let (rate, max) = spec
+                    .split_once(':')
+                    .ok_or_else(|| format_err!("--clock-skip expects P:MAX_SECS, got {}", spec))?;
+                clock_skip = Some((
+                    value(arg, Some(&rate.to_string()))?,
+                    value(arg, Some(&max.to_string()))?,
+                ));
+            }
+            "--command-delay" => config.command_delay = Some(value(arg, args.next())?),
+            "--resign-rate" => resign_rate = Some(value(arg, args.next())?),
+            "--bot-seats" => bot_seats = Some(value::<String>(arg, args.next())?.parse()?),
+            "--eval" => {
+                let games = value(arg, args.next())?;
+                let spec: String = value(arg, args.next())?;
+                let (a, b) = spec
+                    .split_once(':')
+                    .ok_or_else(|| format_err!("--eval expects STRATEGY:STRATEGY, got {}", spec))?;
+                for name in &[a, b] {
+                    if strategy::by_name(name).is_none() {
+                        bail!("unknown strategy {}", name);
+                    }
+                }
+                command = Command::Eval {
+                    games,
+                    bots: (a.to_string(), b.to_string()),
+                };
+            }
+            "--jump-threshold" => jump_threshold = Some(value(arg, args.next())?),
+            "--autoscale" => {
+                let range: String = value(arg, args.next())?;
+                let (min, max) = range
+                    .split_once(':')
+                    .ok_or_else(|| format_err!("--autoscale expects MIN:MAX, got {}", range))?;
+                let (min, max): (usize, usize) = (
+                    value(arg, Some(&min.to_string()))?,
+                    value(arg, Some(&max.to_string()))?,
+                );
+                if min == 0 || min > max {
+                    bail!("invalid --autoscale range {}", range);
+                }
+                config.autoscale = Some(AutoscaleConfig {
+                    min,
+                    max,
+                    interval: Duration::from_secs(10),
+                });
+            }
+            "--autoscale-interval" => match config.autoscale {
+                Some(ref mut a) => a.interval = Duration::from_secs(value(arg, args.next())?),
+                None => bail!("--autoscale-interval must come after --autoscale"),
+            },
+            "--fairness-tolerance" => config.fairness_tolerance = value(arg, args.next())?,
+            "--finish-reason" => config
+                .expected_finish_reasons
+                .push(value(arg, args.next())?),
+            "--check-repro" => {
+                command = Command::CheckRepro {
+                    path: value(arg, args.next())?,
+                }
+            }
+            "--bundle" => bundle = true,
+            "--export-dictionary" => {
+                config.dictionary_out = Some(value::<PathBuf>(arg, args.next())?)
+            }
+            "--spec-stats" => config.spec_stats_out = Some(value::<PathBuf>(arg, args.next())?),
+            "--heatmap" => config.heatmap_out = Some(value::<PathBuf>(arg, args.next())?),
+            "--heatmap-bucket" => {
+                config.heatmap_bucket = Duration::from_secs(value(arg, args.next())?)
+            }
+            "--dictionary" => dictionary = Some(value::<PathBuf>(arg, args.next())?),
+            "--keep-runs" => match command {
+                Command::Daemon {
+                    ref mut keep_runs, ..
+                } => *keep_runs = value(arg, args.next())?,
+                _ => bail!("--keep-runs only applies to daemon"),
+            },
+            "--listen" => match command {
+                Command::Dashboard { ref mut listen, .. } => *listen = value(arg, args.next())?,
+                _ => bail!("--listen only applies to dashboard"),
+            },
+            "--feed" => match command {
+                Command::Dashboard { ref mut feed, .. } => {
+                    *feed = Some((value(arg, args.next())?, String::new()))
+                }
+                _ => bail!("--feed only applies to dashboard"),
+            },
+            "--feed-url" => match command {
+                Command::Dashboard {
+                    feed: Some((_, ref mut url)),
+                    ..
+                } => *url = value(arg, args.next())?,
+                _ => bail!("--feed-url must come after --feed"),
+            },
+            "--prefer-imported" => match command {
+                Command::Import { ref mut prefer, .. } => *prefer = Prefer::Imported,
+                _ => bail!("--prefer-imported only applies to import"),
+            },
+            "--webhook" => config.webhooks.push(value(arg, args.next())?),
+            "--webhook-template" => match config.webhooks.last_mut() {
+                Some(w) => w.finding_template = value(arg, args.next())?,
+                None => bail!("--webhook-template must come after --webhook"),
+            },
+            "--webhook-finished-template" => match config.webhooks.last_mut() {
+                Some(w) => w.finished_template = value(arg, args.next())?,
+                None => bail!("--webhook-finished-template must come after --webhook"),
+            },
+            "--dedup-capacity" => config.dedup.capacity = value(arg, args.next())?,
+            "--dedup-bloom-bits" => config.dedup.bloom_bits = value(arg, args.next())?,
+            "--dedup-state" => config.dedup.state_dir = Some(value::<PathBuf>(arg, args.next())?),
+            "--budget" => config.budgets.push(value(arg, args.next())?),
+            "--warmup" => config.warmup = Some(Duration::from_secs(value(arg, args.next())?)),
+            "--only-min-players" | "--only-max-players" => {
+                let counts = if arg == "--only-min-players" {
+                    PlayerCounts::Min
+                } else {
+                    PlayerCounts::Max
+                };
+                if ![PlayerCounts::All, counts].contains(&config.player_counts) {
+                    bail!("--only-min-players and --only-max-players can't be combined");
+                }
+                config.player_counts = counts;
+            }
+            "--spectate" => config.spectate = value(arg, args.next())?,
+            "--max-rejections" => {
+                config.max_rejections = match value::<usize>(arg, args.next())? {
+                    0 => None,
+                    n => Some(n),
+                }
+            }
+            "--max-turn-attempts" => config.turn_attempts = Some(value(arg, args.next())?),
+            "--state-checkpoints" => {
+                config.state_checkpoints = match value::<usize>(arg, args.next())? {
+                    0 => None,
+                    n => Some(n),
+                }
+            }
+            "--max-findings-per-minute" => {
+                config.max_findings_per_minute = match value::<usize>(arg, args.next())? {
+                    0 => None,
+                    n => Some(n),
+                }
+            }
+            "--render-sample" => config.render_sample = value(arg, args.next())?,
+            "--check-player-refs" => config.check_player_refs = true,
+            "--check-rejections" => config.check_rejections = true,
+            "--simultaneous" => config.simultaneous = true,
+            "--starvation" => config.starvation = Some(value(arg, args.next())?),
+            "--strict-rejections" => config.strict_rejections = value(arg, args.next())?,
+            "--plateau" => {
+                config.plateau = Some(PlateauConfig {
+                    after: Duration::from_secs(value(arg, args.next())?),
+                    actions: vec![],
+                })
+            }
+            "--on-plateau" => on_plateau.push(value(arg, args.next())?),
+            "--diverge" => {
+                command = Command::Diverge {
+                    path: value(arg, args.next())?,
+                }
+            }
+            "--seeds" => {
+                command = Command::Batch {
+                    seeds: parse_seeds(&value::<String>(arg, args.next())?)?,
+                    report: None,
+                }
+            }
+            "--phase" => {
+                let phase = value(arg, args.next())?;
+                match command {
+                    Command::Pipeline { ref mut phases } => phases.push(phase),
+                    _ => {
+                        command = Command::Pipeline {
+                            phases: vec![phase],
+                        }
+                    }
+                }
+            }
+            "--compare" => {
+                let spec: String = value(arg, args.next())?;
+                let (a, b) = spec.split_once(':').ok_or_else(|| {
+                    format_err!("--compare expects STRATEGY:STRATEGY, got {}", spec)
+                })?;
+                for name in &[a, b] {
+                    if strategy::by_name(name).is_none() {
+                        bail!("unknown strategy {}", name);
+                    }
+                }
+                command = Command::Compare {
+                    strategies: (a.to_string(), b.to_string()),
+                    report: None,
+                };
+            }
+            "--compare-report" => match command {
+                Command::Compare { ref mut report, .. } => *report = Some(value(arg, args.next())?),
+                _ => bail!("--compare-report must come after --compare"),
+            },
+            "--batch-report" => match command {
+                Command::Batch { ref mut report, .. } => *report = Some(value(arg, args.next())?),
+                _ => bail!("--batch-report must come after --seeds"),
+            },
+            "--redact" => config.redact.add(value(arg, args.next())?),
+            "--ignore-state" => config.canonical.ignore(value(arg, args.next())?),
+            "--unordered-state" => config.canonical.unordered(value(arg, args.next())?),
+            "--session" => config.session_checkpoint = Some(value(arg, args.next())?),
+            "--conserve" => conserve.push(value(arg, args.next())?),
+            "--plugin" => plugin_paths.push(value(arg, args.next())?),
+            "--shadow-oracle" => shadow.push(value(arg, args.next())?),
+            "--robustness" => robustness = Some(value(arg, args.next())?),
+            "--max-consecutive-turns" => max_consecutive_turns = Some(value(arg, args.next())?),
+            "--max-response-bytes" => max_response_bytes = Some(value(arg, args.next())?),
+            "--max-logs" => max_logs = Some(value(arg, args.next())?),
+            "--numeric-format" => numeric_format = true,
+            "--race" => race = Some(value(arg, args.next())?),
+            "--log-file" => config.soak.log_file = Some(value::<PathBuf>(arg, args.next())?),
+            "--log-max-bytes" => config.soak.log_max_bytes = value(arg, args.next())?,
+            "--log-max-age" => {
+                config.soak.log_max_age = Some(Duration::from_secs(value(arg, args.next())?))
+            }
+            "--log-keep" => config.soak.log_keep = value(arg, args.next())?,
+            "--stats-file" => config.soak.stats_file = Some(value::<PathBuf>(arg, args.next())?),
+            "--stats-interval" => {
+                config.soak.stats_interval = Duration::from_secs(value(arg, args.next())?)
+            }
+            "--artifacts-max-bytes" => {
+                config.soak.artifact_max_bytes = Some(value(arg, args.next())?)
+            }
+            "--" => {
+                requester_args.extend(args.cloned());
+                break;
+            }
+            _ => requester_args.push(arg.to_owned()),
+        }
+    }
+    if let Command::Dashboard {
+        feed: Some((_, ref url)),
+        ..
+    } = command
+    {
+        if url.is_empty() {
+            bail!("--feed requires --feed-url");
+        }
+    }
+    if let Command::Simulate = command {
+        if config.seed.is_none() {
+            bail!("--deterministic requires --seed");
+        }
+    }
+    if race.is_some() && !cfg!(feature = "http") {
+        bail!("--race requires the http feature");
+    }
+    if !config.webhooks.is_empty() && !cfg!(feature = "http") {
+        bail!("--webhook requires the http feature");
+    }
+    if !plugin_paths.is_empty() && !cfg!(feature = "plugins") {
+        bail!("--plugin requires the plugins feature");
+    }
+    #[cfg(feature = "plugins")]
+    let plugins = plugin_paths
+        .iter()
+        .map(|path| Plugin::load(path).map(Arc::new))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(name) = plugin_strategy {
+        #[cfg(feature = "plugins")]
+        {
+            let plugin = plugins
+                .iter()
+                .find(|p| p.name() == name && p.has_strategy())
+                .cloned()
+                .ok_or_else(|| format_err!("unknown strategy {}", name))?;
+            config.strategy = Arc::new(move || Box::new(plugin::strategy(&plugin).unwrap()));
+        }
+        #[cfg(not(feature = "plugins"))]
+        bail!("unknown strategy {}", name);
+    }
+    #[cfg(feature = "plugins")]
+    let plugin_oracles: Vec<Arc<Plugin>> = plugins.into_iter().filter(|p| p.has_oracle()).collect();
+    #[cfg(not(feature = "plugins"))]
+    let plugin_oracles: Vec<()> = vec![];
+    if !config.phase_strategies.is_empty() && config.game_phase.is_none() {
+        bail!("--phase-strategy requires --game-phases");
+    }
+    if clock_tick.is_some() || clock_skip.is_some() {
+        match config.wallclock {
+            Some(ref mut clock) => {
+                clock.tick = clock_tick.unwrap_or(clock.tick);
+                if let Some((rate, max)) = clock_skip {
+                    clock.skip_rate = rate;
+                    clock.max_skip = max;
+                }
+            }
+            None => bail!("--clock-tick and --clock-skip require --virtual-clock"),
+        }
+    }
+    if let Some(rate) = resign_rate {
+        match config.resign {
+            Some(ref mut resign) => resign.rate = rate,
+            None => bail!("--resign-rate requires --resign-command"),
+        }
+    }
+    if let Some(seats) = bot_seats {
+        match config.bot {
+            Some(ref mut bot) => bot.seats = seats,
+            None => bail!("--bot-seats requires --bot"),
+        }
+    }
+    if let Command::Batch { .. } = command {
+        if config.max_steps.is_none() && config.duration.is_none() {
+            bail!("--seeds requires --steps or --duration to bound each campaign");
+        }
+    }
+    if let Command::Compare { .. } = command {
+        if config.max_steps.is_none() && config.duration.is_none() && config.stop.is_none() {
+            bail!("--compare requires --steps, --duration or --stop to bound each campaign");
+        }
+    }
+    if max_consecutive_turns == Some(0) {
+        bail!("--max-consecutive-turns must be at least 1");
+    }
+    let volume = max_response_bytes.is_some() || max_logs.is_some();
+    if jump_threshold.is_some()
+        || robustness.is_some()
+        || race.is_some()
+        || volume
+        || numeric_format
+        || max_consecutive_turns.is_some()
+        || !conserve.is_empty()
+        || !plugin_oracles.is_empty()
+    {
+        config.oracles = Arc::new(move || {
+            let mut oracles = OracleRegistry::with_defaults();
+            if let Some(threshold) = jump_threshold {
+                oracles.register(MagnitudeOracle::new(threshold));
+            }
+            if let Some(every) = robustness {
+                oracles.register(RobustnessOracle::new(every));
+            }
+            if volume {
+                oracles.register(VolumeOracle::new(max_response_bytes, max_logs));
+            }
+            if numeric_format {
+                oracles.register(NumericFormatOracle);
+            }
+            if let Some(k) = max_consecutive_turns {
+                oracles.register(TurnStreakOracle::new(k));
+            }
+            for rule in &conserve {
+                oracles.register(ConservationOracle::new(rule.clone()));
+            }
+            #[cfg(feature = "plugins")]
+            for p in &plugin_oracles {
+                oracles.register(plugin::oracle(p).unwrap());
+            }
+            #[cfg(feature = "http")]
+            {
+                if let Some(url) = race.clone() {
+                    oracles.register(RaceOracle::new(
+                        10,
+                        Box::new(move || Box::new(HttpRequester::new(url.clone()))),
+                    ));
+                }
+            }
+            oracles
+        });
+    }
+    if !shadow.is_empty() {
+        let registry = (config.oracles)();
+        if let Some(name) = shadow
+            .iter()
+            .find(|name| !registry.stats().any(|(n, _, _)| n == name.as_str()))
+        {
+            bail!("--shadow-oracle {} isn't a registered oracle", name);
+        }
+        let inner = config.oracles.clone();
+        config.oracles = Arc::new(move || {
+            let mut oracles = inner();
+            for name in &shadow {
+                oracles.set_shadow(name, true);
+            }
+            oracles
+        });
+    }
+    if hot_reload {
+        config.watch = Some(
+            requester_args
+                .iter()
+                .skip(1)
+                .map(Path::new)
+                .find(|p| p.is_file())
+                .ok_or_else(|| format_err!("--hot-reload requires a game binary path"))?
+                .to_path_buf(),
+        );
+    }
+    if let Some(path) = dictionary {
+        let dictionary = Arc::new(Dictionary::load(&path)?);
+        let inner = config.strategy.clone();
+        config.strategy =
+            Arc::new(move || Box::new(DictionaryStrategy::new(inner(), dictionary.clone(), 0.3)));
+    }
+    // Dedup state goes alongside the artifacts unless it has a directory of
+    // its own.
+    if let (Some(ref storage), None) = (&config.storage, &config.dedup.state_dir) {
+        config.dedup.storage = Some(storage::scoped(storage.clone(), "dedup"));
+    }
+    if config.plateau.is_none() && config.stop.as_ref().is_some_and(|s| s.uses_plateau()) {
+        bail!("--stop plateau requires --plateau");
+    }
+    match config.plateau {
+        Some(ref mut plateau) => plateau.actions = on_plateau,
+        None if !on_plateau.is_empty() => bail!("--on-plateau requires --plateau"),
+        None => {}
+    }
+    if bundle {
+        if config.artifact_dir.is_none() && config.storage.is_none() {
+            bail!("--bundle requires --artifacts or --storage");
+        }
+        let target = requester_args
+            .iter()
+            .skip(1)
+            .map(Path::new)
+            .find(|p| p.is_file());
+        config.bundle = Some(Environment::capture(&config, &args_for_bundle, target));
+    }
+    config.target = requester_args.iter().skip(1).cloned().collect();
+    Ok(Cli {
+        command,
+        config,
+        requester_args,
+    })
+}
+
+// Either a comma separated list or a half-open range such as 0..100.
+fn parse_seeds(s: &str) -> Result<Vec<u64>, Error> {
+    if let Some((from, to)) = s.split_once("..") {
+        let from: u64 = value("--seeds", Some(&from.to_string()))?;
+        let to: u64 = value("--seeds", Some(&to.to_string()))?;
+        if from >= to {
+            bail!("empty seed range {}", s);
+        }
+        return Ok((from..to).collect());
+    }
+    s.split(',')
+        .map(|seed| value("--seeds", Some(&seed.trim().to_string())))
+        .collect()
+}
+
+fn value<T>(flag: &str, arg: Option<&String>) -> Result<T, Error>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match arg {
+        Some(v) => v
+            .parse()
+            .map_err(|e| format_err!("invalid value for {}: {}", flag, e)),
+        None => bail!("{} requires a value", flag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("brdgme-fuzz")
+            .chain(args.iter().cloned())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn max_consecutive_turns_registers_the_oracle() {
+        let cli = parse(&args(&["--max-consecutive-turns", "3", "game"])).unwrap();
+        assert!((cli.config.oracles)()
+            .stats()
+            .any(|(name, enabled, _)| name == "turn_streak" && enabled));
+        assert!(parse(&args(&["--max-consecutive-turns", "0", "game"])).is_err());
+    }
+
+    #[test]
+    fn player_count_flags_conflict() {
+        let cli = parse(&args(&["--only-max-players", "--only-max-players", "game"])).unwrap();
+        assert_eq!(cli.config.player_counts, PlayerCounts::Max);
+        assert!(parse(&args(&["--only-min-players", "--only-max-players", "game"])).is_err());
+        assert!(parse(&args(&["--only-max-players", "--only-min-players", "game"])).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use failure::{bail, Error};
+use serde::Serialize;
+
+use brdgme_cmd::requester;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::campaign::{self, fuzz_with_config};
+use crate::config::Config;
+use crate::report::coverage::Coverage;
+use crate::report::Summary;
+use crate::strategy;
+
+// Branches listed per strategy when rendering, the rest are only saved.
+const MAX_RENDERED_BRANCHES: usize = 20;
+
+pub struct Side {
+    pub strategy: String,
+    pub summary: Box<Summary>,
+}
+
+impl Side {
+    pub fn coverage(&self) -> Coverage {
+        self.summary.coverage.clone().unwrap_or_default()
+    }
+}
+
+// Two strategies fuzzed under the same seed and limits, with what each
+// reached. Only has one side if cancelled before the second campaign.
+pub struct CompareReport {
+    pub seed: u64,
+    pub sides: Vec<Side>,
+}
+
+#[derive(Serialize)]
+struct ExportSide<'a> {
+    strategy: &'a str,
+    commands: usize,
+    games: usize,
+    reached: Coverage,
+    only: Coverage,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    seed: u64,
+    strategies: Vec<ExportSide<'a>>,
+}
+
+impl CompareReport {
+    pub fn failed(&self, fail_on_regression: bool) -> bool {
+        self.sides
+            .iter()
+            .any(|s| s.summary.failed(fail_on_regression))
+    }
+
+    // What the side reached that the other didn't.
+    pub fn only(&self, side: usize) -> Coverage {
+        let coverage = self.sides[side].coverage();
+        match self.sides.get(1 - side) {
+            Some(other) => coverage.difference(&other.coverage()),
+            None => coverage,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("Coverage comparison, seed {}\n", self.seed);
+        for side in &self.sides {
+            let coverage = side.coverage();
+            out.push_str(&format!(
+                "  {}: {} commands, {} games, {} states, {} branches\n",
+                side.strategy,
+                side.summary.tally.commands,
+                side.summary.tally.finished,
+                coverage.states.len(),
+                coverage.branches.len()
+            ));
+        }
+        if self.sides.len() < 2 {
+            return out;
+        }
+        let both = self.sides[0]
+            .coverage()
+            .intersection(&self.sides[1].coverage());
+        out.push_str(&format!(
+            "  Both reached {} states and {} branches\n",
+            both.states.len(),
+            both.branches.len()
+        ));
+        for (i, side) in self.sides.iter().enumerate() {
+            let only = self.only(i);
+            out.push_str(&format!(
+                "  Only {} reached {} states and {} branches\n",
+                side.strategy,
+                only.states.len(),
+                only.branches.len()
+            ));
+            for branch in only.branches.iter().take(MAX_RENDERED_BRANCHES) {
+                out.push_str(&format!("       {} = {}\n", branch.node, branch.choice));
+            }
+            if only.branches.len() > MAX_RENDERED_BRANCHES {
+                out.push_str(&format!(
+                    "       and {} more\n",
+                    only.branches.len() - MAX_RENDERED_BRANCHES
+                ));
+            }
+        }
+        out
+    }
+
+    // Everything each side reached, state hashes included, and what only it
+    // did.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let export = Export {
+            seed: self.seed,
+            strategies: self
+                .sides
+                .iter()
+                .enumerate()
+                .map(|(i, side)| ExportSide {
+                    strategy: &side.strategy,
+                    commands: side.summary.tally.commands,
+                    games: side.summary.tally.finished,
+                    reached: side.coverage(),
+                    only: self.only(i),
+                })
+                .collect(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(())
+    }
+}
+
+// Fuzzes with each strategy in turn under the config's limits, both from
+// the config's seed, or the same random one if it has none, and reports
+// which states and spec branches each reached that the other didn't. Set
+// --deterministic for the campaigns to differ only by strategy.
+pub fn compare<F, R>(
+    new_requester: F,
+    config: Config,
+    strategies: &(String, String),
+) -> Result<CompareReport, Error>
+where
+    F: Fn() -> R + Clone + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    for name in [&strategies.0, &strategies.1] {
+        if strategy::by_name(name).is_none() {
+            bail!("unknown strategy {}", name);
+        }
+    }
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let campaign_id = config
+        .campaign_id
+        .clone()
+        .unwrap_or_else(campaign::new_campaign_id);
+    let mut report = CompareReport {
+        seed,
+        sides: vec![],
+    };
+    for (i, name) in [&strategies.0, &strategies.1].iter().enumerate() {
+        if config.cancel.is_cancelled() {
+            break;
+        }
+        eprintln!("\nStrategy {} of 2: {}", i + 1, name);
+        let strategy_name = name.to_string();
+        let summary = fuzz_with_config(
+            new_requester.clone(),
+            Config {
+                strategy: Arc::new(move || {
+                    strategy::by_name(&strategy_name).expect("checked before fuzzing")
+                }),
+                seed: Some(seed),
+                collect_coverage: true,
+                campaign_id: Some(campaign_id.clone()),
+                phase: Some(format!("{}/2 {}", i + 1, name)),
+                ..config.clone()
+            },
+        );
+        report.sides.push(Side {
+            strategy: name.to_string(),
+            summary: Box::new(summary),
+        });
+    }
+    eprintln!("\n{}", report.render());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::ToyGame;
+
+    #[test]
+    fn unknown_strategies_are_rejected_before_fuzzing() {
+        let strategies = ("random".to_string(), "nope".to_string());
+        let error = compare(ToyGame::default, Config::default(), &strategies)
+            .err()
+            .expect("expected an unknown strategy error");
+        assert_eq!(error.to_string(), "unknown strategy nope");
+    }
+}
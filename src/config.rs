@@ -0,0 +1,425 @@
+use failure::{bail, Error};
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bundle::Environment;
+use crate::cancel::CancelToken;
+use crate::canonical::Canonicalizer;
+use crate::engine::{FuzzGame, MoveClock};
+use crate::names::{DefaultNames, NameProvider};
+use crate::oracle::OracleRegistry;
+use crate::pacing::Delay;
+use crate::plateau::Escalation;
+use crate::redact::Redactor;
+use crate::report::baseline::Thresholds;
+use crate::report::budget::Budget;
+use crate::report::finish;
+use crate::report::webhook::Webhook;
+use crate::seen::SeenSet;
+use crate::stop::StopCondition;
+use crate::storage::{self, Storage};
+use crate::strategy::{Bot, BotSeats, RandomStrategy, Strategy};
+use crate::wallclock::WallClock;
+
+#[derive(Clone)]
+pub struct Config {
+    pub workers: usize,
+    pub oracles: Arc<dyn Fn() -> OracleRegistry + Send + Sync>,
+    pub strategy: StrategyFactory,
+    // Labels the phase a game is in, such as opening or endgame, for
+    // phase_strategies. See strategy::phase::by_turn.
+    pub game_phase: Option<GamePhaseHook>,
+    // Used instead of `strategy` while a game is in the named phase.
+    pub phase_strategies: Vec<(String, StrategyFactory)>,
+    // Plays some seats instead of the strategy.
+    pub bot: Option<BotConfig>,
+    // Has players resign part way through games.
+    pub resign: Option<ResignConfig>,
+    // A pause before each command, see pacing::Delay.
+    pub command_delay: Option<Delay>,
+    pub wallclock: Option<WallClock>,
+    pub names: Arc<dyn Fn() -> Box<dyn NameProvider> + Send + Sync>,
+    pub watch: Option<PathBuf>,
+    pub duration: Option<Duration>,
+    pub save_baseline: Option<PathBuf>,
+    pub baseline: Option<PathBuf>,
+    pub baseline_thresholds: Thresholds,
+    pub fail_on_regression: bool,
+    // Known issues file, see report::suppress.
+    pub suppressions: Option<PathBuf>,
+    // Replays the suppressions' artifacts before fuzzing starts.
+    pub reverify_suppressions: bool,
+    pub trace: TraceConfig,
+    pub artifact_dir: Option<PathBuf>,
+    // Where artifacts are saved instead of artifact_dir, under the campaign's
+    // id, such as a bucket that outlives the instance fuzzing.
+    pub storage: Option<Arc<dyn Storage>>,
+    pub keep_going: bool,
+    pub soak: SoakConfig,
+    pub seed: Option<u64>,
+    pub max_steps: Option<usize>,
+    // Checkpoint interval for session mode, None keeps every Play stateless.
+    pub session_checkpoint: Option<usize>,
+    pub redact: Redactor,
+    // Applied to states before they're hashed, compared or diffed.
+    pub canonical: Canonicalizer,
+    // Spawns `max` workers and varies how many are active, `workers` being
+    // the starting point.
+    pub autoscale: Option<AutoscaleConfig>,
+    // Names why a finished game ended, for the finish reason tally.
+    pub finish_reason: FinishReasonHook,
+    pub expected_finish_reasons: Vec<String>,
+    // Relative deviation from an even share of turns a seat may show before
+    // it's reported, see report::fairness.
+    pub fairness_tolerance: f64,
+    // Saves each finding as a bundle with the environment it was found in.
+    pub bundle: Option<Environment>,
+    // Chance of a read-only request (renders, status, player counts) before
+    // each command.
+    pub spectate: f64,
+    // Chance of timing a parse of every player render after a command.
+    pub render_sample: f64,
+    // Checks every player tag in renders and logs resolves to a player.
+    pub check_player_refs: bool,
+    // Asks for the state again after each rejected command to make sure the
+    // rejection left it untouched.
+    pub check_rejections: bool,
+    // Chance of checking a rejected command had no side effects at all, by
+    // playing the next accepted command again with and without it.
+    pub strict_rejections: f64,
+    // When several players are in whose_turn, has each of them act once per
+    // round in a random order rather than picking one at random each time.
+    pub simultaneous: bool,
+    // Consecutive rejected commands after which a game is abandoned as a
+    // generator blind spot.
+    pub max_rejections: Option<usize>,
+    // Gives up on a single player's turn after this many rejected commands.
+    pub turn_attempts: Option<TurnAttempts>,
+    // Reports a player who had every command rejected in this many states
+    // in a row while the others kept playing.
+    pub starvation: Option<usize>,
+    // Keeps the state every this many commands of a game with its findings,
+    // so triage can jump into the middle of a long history.
+    pub state_checkpoints: Option<usize>,
+    // Findings printed and saved a minute before backing off, see
+    // throttle::FindingThrottle. Held back findings are still counted.
+    pub max_findings_per_minute: Option<usize>,
+    // Escalates when no new coverage fingerprints turn up for a while.
+    pub plateau: Option<PlateauConfig>,
+    pub player_counts: PlayerCounts,
+    // Left out of throughput and latency figures, which are then reported
+    // for warm-up and steady state separately.
+    pub warmup: Option<Duration>,
+    // Limits on user errors, partial parses and the like that fail the run
+    // even without a finding.
+    pub budgets: Vec<Budget>,
+    // Where to save the accepted commands seen, see dictionary::Dictionary.
+    pub dictionary_out: Option<PathBuf>,
+    // Where to save per spec node choice statistics, see
+    // report::spec_nodes.
+    pub spec_stats_out: Option<PathBuf>,
+    // Whether to collect the states and spec branches reached into
+    // Summary::coverage, as comparing strategies does.
+    pub collect_coverage: bool,
+    // Per command head generation and acceptance in buckets of
+    // `heatmap_bucket`, saved as CSV at the end of the campaign.
+    pub heatmap_out: Option<PathBuf>,
+    pub heatmap_bucket: Duration,
+    pub dedup: DedupConfig,
+    // Told about each new unique finding and the end of the campaign.
+    pub webhooks: Vec<Webhook>,
+    // Generated at the start of each campaign when not given.
+    pub campaign_id: Option<String>,
+    // Set by pipelines for progress reports, see report::eta.
+    pub phase: Option<String>,
+    // Checked by the coordinator on every tick.
+    pub stop: Option<StopCondition>,
+    // Stops the campaign, or whatever else the config is running, from
+    // outside as soon as it's cancelled.
+    pub cancel: CancelToken,
+    // The requester arguments without the program name, identifying what's
+    // being fuzzed in the run header.
+    pub target: Vec<String>,
+}
+
+pub type FinishReasonHook = Arc<dyn Fn(&FuzzGame) -> Option<String> + Send + Sync>;
+
+pub type GamePhaseHook = Arc<dyn Fn(&FuzzGame, &MoveClock) -> Option<String> + Send + Sync>;
+
+pub type StrategyFactory = Arc<dyn Fn() -> Box<dyn Strategy> + Send + Sync>;
+
+pub type BotFactory = Arc<dyn Fn() -> Box<dyn Bot> + Send + Sync>;
+
+// A command the game takes as a player resigning or forfeiting, played in
+// place of the strategy's command at `rate`. Once accepted the player must
+// drop out of whose_turn and be listed as eliminated, and can't place ahead
+// of anyone who stayed in.
+#[derive(Clone, Debug)]
+pub struct ResignConfig {
+    pub command: String,
+    pub rate: f64,
+}
+
+// How many commands a player's turn may have rejected before it's given up
+// on, and what's done then, given as `N`, `N:skip` or `N:flag`. Every attempt
+// is kept with why it was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TurnAttempts {
+    pub max: usize,
+    pub action: TurnAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurnAction {
+    // Saved like a blind spot and another player on turn tried instead, or
+    // the game abandoned if there's nobody else.
+    Skip,
+    // Reported as a warning finding and the game abandoned.
+    Flag,
+}
+
+impl FromStr for TurnAttempts {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (max, action) = s.split_once(':').unwrap_or((s, "skip"));
+        let max: usize = max.parse()?;
+        if max == 0 {
+            bail!("a turn needs at least one attempt");
+        }
+        let action = match action {
+            "skip" => TurnAction::Skip,
+            "flag" => TurnAction::Flag,
+            _ => bail!("unknown action {}, expected skip or flag", action),
+        };
+        Ok(TurnAttempts { max, action })
+    }
+}
+
+#[derive(Clone)]
+pub struct BotConfig {
+    pub bot: BotFactory,
+    pub seats: BotSeats,
+}
+
+#[derive(Clone, Debug)]
+pub struct AutoscaleConfig {
+    pub min: usize,
+    pub max: usize,
+    pub interval: Duration,
+}
+
+// Which of the game's supported player counts games are started with. The
+// boundaries hide most setup and scoring edge cases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerCounts {
+    All,
+    Min,
+    Max,
+}
+
+impl PlayerCounts {
+    pub fn select(self, counts: Vec<usize>) -> Vec<usize> {
+        let pick = match self {
+            PlayerCounts::All => return counts,
+            PlayerCounts::Min => counts.iter().min(),
+            PlayerCounts::Max => counts.iter().max(),
+        };
+        pick.map(|&c| vec![c]).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlateauConfig {
+    pub after: Duration,
+    pub actions: Vec<Escalation>,
+}
+
+// Sizes the plateau fingerprint and violation signature sets, see
+// seen::SeenSet. With a state directory or storage they're loaded at the
+// start of a campaign and saved at the end.
+#[derive(Clone, Debug)]
+pub struct DedupConfig {
+    pub capacity: usize,
+    pub bloom_bits: usize,
+    pub state_dir: Option<PathBuf>,
+    // Used instead of state_dir when set.
+    pub storage: Option<Arc<dyn Storage>>,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            capacity: 100_000,
+            bloom_bits: 1 << 23,
+            state_dir: None,
+            storage: None,
+        }
+    }
+}
+
+impl DedupConfig {
+    fn store(&self) -> Option<Arc<dyn Storage>> {
+        match self.storage {
+            Some(ref storage) => Some(storage.clone()),
+            None => self.state_dir.as_deref().map(storage::local),
+        }
+    }
+
+    pub fn open(&self, name: &str) -> SeenSet {
+        let fresh = || SeenSet::new(self.capacity, self.bloom_bits);
+        let store = match self.store() {
+            Some(store) => store,
+            None => return fresh(),
+        };
+        let key = format!("{}.json", name);
+        let loaded = store
+            .get(&key)
+            .and_then(|bytes| Ok(bytes.map(|b| serde_json::from_slice(&b)).transpose()?));
+        match loaded {
+            Ok(Some(seen)) => seen,
+            Ok(None) => fresh(),
+            Err(e) => {
+                eprintln!(
+                    "failed to load {}, starting empty: {}",
+                    store.locate(&key),
+                    e
+                );
+                fresh()
+            }
+        }
+    }
+
+    pub fn save(&self, name: &str, seen: &SeenSet) {
+        if let Some(store) = self.store() {
+            let key = format!("{}.json", name);
+            if let Err(e) = serde_json::to_vec(seen)
+                .map_err(Error::from)
+                .and_then(|bytes| store.put(&key, &bytes))
+            {
+                eprintln!("failed to save {}: {}", store.locate(&key), e);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SoakConfig {
+    pub log_file: Option<PathBuf>,
+    pub log_max_bytes: u64,
+    pub log_max_age: Option<Duration>,
+    pub log_keep: usize,
+    pub stats_file: Option<PathBuf>,
+    pub stats_interval: Duration,
+    pub artifact_max_bytes: Option<u64>,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        SoakConfig {
+            log_file: None,
+            log_max_bytes: 64 * 1024 * 1024,
+            log_max_age: None,
+            log_keep: 5,
+            stats_file: None,
+            stats_interval: Duration::from_secs(60),
+            artifact_max_bytes: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceConfig {
+    pub dir: PathBuf,
+    pub games: usize,
+    pub rate: f64,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        TraceConfig {
+            dir: PathBuf::from("traces"),
+            games: 0,
+            rate: 0.01,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            workers: num_cpus::get(),
+            oracles: Arc::new(OracleRegistry::with_defaults),
+            strategy: Arc::new(|| Box::new(RandomStrategy)),
+            game_phase: None,
+            phase_strategies: vec![],
+            bot: None,
+            resign: None,
+            command_delay: None,
+            wallclock: None,
+            names: Arc::new(|| Box::new(DefaultNames)),
+            watch: None,
+            duration: None,
+            save_baseline: None,
+            baseline: None,
+            baseline_thresholds: Thresholds::default(),
+            fail_on_regression: false,
+            suppressions: None,
+            reverify_suppressions: false,
+            trace: TraceConfig::default(),
+            artifact_dir: None,
+            storage: None,
+            keep_going: false,
+            soak: SoakConfig::default(),
+            seed: None,
+            max_steps: None,
+            session_checkpoint: None,
+            redact: Redactor::default(),
+            canonical: Canonicalizer::default(),
+            autoscale: None,
+            finish_reason: Arc::new(finish::last_log_reason),
+            expected_finish_reasons: vec![],
+            fairness_tolerance: 0.25,
+            bundle: None,
+            spectate: 0.0,
+            render_sample: 0.0,
+            check_player_refs: false,
+            check_rejections: false,
+            strict_rejections: 0.0,
+            simultaneous: false,
+            max_rejections: Some(1000),
+            turn_attempts: None,
+            starvation: None,
+            state_checkpoints: Some(100),
+            max_findings_per_minute: Some(100),
+            plateau: None,
+            player_counts: PlayerCounts::All,
+            warmup: None,
+            budgets: vec![],
+            dictionary_out: None,
+            spec_stats_out: None,
+            collect_coverage: false,
+            heatmap_out: None,
+            heatmap_bucket: Duration::from_secs(60),
+            dedup: DedupConfig::default(),
+            webhooks: vec![],
+            campaign_id: None,
+            phase: None,
+            stop: None,
+            cancel: CancelToken::default(),
+            target: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn worker_seed(&self, worker: usize) -> u64 {
+        match self.seed {
+            Some(seed) => crate::strategy::derive_seed(seed, worker as u64),
+            None => rand::random(),
+        }
+    }
+}
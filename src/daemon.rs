@@ -0,0 +1,217 @@
+use failure::{bail, format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use brdgme_cmd::requester;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::campaign;
+use crate::config::Config;
+use crate::stop::parse_duration;
+use crate::storage;
+
+// One line of a schedule file: `NAME every=6h for=30m REQUESTER_ARGS...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub name: String,
+    pub every: Duration,
+    pub duration: Duration,
+    pub args: Vec<String>,
+}
+
+impl Target {
+    fn parse(line: &str) -> Result<Self, Error> {
+        let mut words = line.split_whitespace();
+        let name = words
+            .next()
+            .ok_or_else(|| format_err!("missing target name"))?;
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            bail!("target name {} must be usable as a directory name", name);
+        }
+        let (mut every, mut duration) = (None, None);
+        let mut args = vec![];
+        for word in words {
+            match word.split_once('=') {
+                Some(("every", d)) if args.is_empty() => every = Some(parse_duration(d)?),
+                Some(("for", d)) if args.is_empty() => duration = Some(parse_duration(d)?),
+                _ => args.push(word.to_string()),
+            }
+        }
+        if args.is_empty() {
+            bail!("target {} has no requester arguments", name);
+        }
+        Ok(Target {
+            name: name.to_string(),
+            every: every.ok_or_else(|| format_err!("target {} is missing every=", name))?,
+            duration: duration.ok_or_else(|| format_err!("target {} is missing for=", name))?,
+            args,
+        })
+    }
+}
+
+// Blank lines and lines starting with # are skipped.
+pub fn parse_schedule(text: &str) -> Result<Vec<Target>, Error> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(i, l)| Target::parse(l).map_err(|e| format_err!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+// Written to `DIR/NAME/status.json` after every run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetStatus {
+    pub name: String,
+    pub runs: usize,
+    pub failed_runs: usize,
+    pub last_started: u64,
+    pub last_elapsed_secs: f64,
+    pub last_failed: bool,
+    pub last_unique_findings: usize,
+    pub last_commands: usize,
+    pub next_due_in_secs: u64,
+    #[serde(default)]
+    pub last_campaign_id: String,
+}
+
+impl TargetStatus {
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+// Runs each target's campaign whenever it's due, one at a time, until the
+// config's cancel token is cancelled.
+// Every run gets its own directory of artifacts and logs under
+// `DIR/NAME/runs`, of which the newest `keep_runs` are kept.
+pub fn run<F, R>(
+    targets: &[Target],
+    dir: &Path,
+    config: &Config,
+    keep_runs: usize,
+    connect: F,
+) -> Result<(), Error>
+where
+    F: Fn(&[String]) -> R + Clone + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    if targets.is_empty() {
+        bail!("the schedule has no targets");
+    }
+    let mut due: Vec<Instant> = targets.iter().map(|_| Instant::now()).collect();
+    loop {
+        let (i, at) = due
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, at)| **at)
+            .map(|(i, at)| (i, *at))
+            .expect("targets is non-empty");
+        if config
+            .cancel
+            .sleep(at.saturating_duration_since(Instant::now()))
+        {
+            return Ok(());
+        }
+        let target = &targets[i];
+        due[i] = Instant::now() + target.every;
+        let status = run_target(target, dir, config, keep_runs, connect.clone())?;
+        eprintln!(
+            "\n{}: run {} {} with {} unique findings, next in {:?}",
+            target.name,
+            status.runs,
+            if status.last_failed {
+                "failed"
+            } else {
+                "passed"
+            },
+            status.last_unique_findings,
+            due[i].saturating_duration_since(Instant::now())
+        );
+    }
+}
+
+fn run_target<F, R>(
+    target: &Target,
+    dir: &Path,
+    config: &Config,
+    keep_runs: usize,
+    connect: F,
+) -> Result<TargetStatus, Error>
+where
+    F: Fn(&[String]) -> R + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    let target_dir = dir.join(&target.name);
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let campaign_id = campaign::new_campaign_id();
+    let run_dir = target_dir
+        .join("runs")
+        .join(format!("{}-{}", started, campaign_id));
+    fs::create_dir_all(&run_dir)?;
+    let mut run_config = config.clone();
+    run_config.campaign_id = Some(campaign_id.clone());
+    run_config.target = target.args.clone();
+    run_config.duration = Some(target.duration);
+    run_config.artifact_dir = Some(run_dir.join("artifacts"));
+    run_config.trace.dir = run_dir.join("traces");
+    run_config.soak.log_file = Some(run_dir.join("fuzz.log"));
+    run_config.soak.stats_file = Some(run_dir.join("stats.jsonl"));
+    // Baselines and dedup state carry over between runs of a target.
+    run_config.baseline = Some(target_dir.join("baseline.json")).filter(|p| p.exists());
+    run_config.save_baseline = Some(target_dir.join("baseline.json"));
+    run_config.dedup.state_dir = Some(target_dir.join("dedup"));
+    if let Some(ref storage) = config.storage {
+        run_config.storage = Some(storage::scoped(storage.clone(), &target.name));
+        run_config.dedup.storage = Some(storage::scoped(
+            storage.clone(),
+            &format!("{}/dedup", target.name),
+        ));
+    }
+    if run_config.suppressions.is_none() {
+        run_config.suppressions = Some(target_dir.join("suppressions.json")).filter(|p| p.exists());
+    }
+    let args = target.args.clone();
+    let summary = campaign::fuzz_with_config(move || connect(&args), run_config);
+
+    let status_path = target_dir.join("status.json");
+    let mut status = TargetStatus::load(&status_path);
+    status.name = target.name.clone();
+    status.runs += 1;
+    status.last_started = started;
+    status.last_elapsed_secs = summary.elapsed.as_secs_f64();
+    status.last_failed = summary.failed(config.fail_on_regression);
+    status.failed_runs += status.last_failed as usize;
+    status.last_unique_findings = summary.unique_findings().len();
+    status.last_commands = summary.tally.commands;
+    status.next_due_in_secs = target.every.as_secs();
+    status.last_campaign_id = campaign_id;
+    fs::write(&status_path, serde_json::to_string_pretty(&status)?)?;
+    rotate_runs(&target_dir.join("runs"), keep_runs)?;
+    Ok(status)
+}
+
+// Run directories are named by start time and then campaign id, so the
+// oldest sort first.
+fn rotate_runs(runs: &Path, keep: usize) -> Result<(), Error> {
+    let mut dirs: Vec<(u64, PathBuf)> = fs::read_dir(runs)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_str()?.split('-').next()?.parse().ok()?;
+            Some((name, e.path()))
+        })
+        .collect();
+    dirs.sort();
+    let excess = dirs.len().saturating_sub(keep.max(1));
+    for (_, path) in dirs.into_iter().take(excess) {
+        fs::remove_dir_all(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,617 @@
+use failure::{bail, format_err, Error};
+use serde::Serialize;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::finding::{bytes_hash, Finding, Severity};
+use crate::report::storybook::escape;
+use crate::report::suppress::{Suppressions, Triage};
+use crate::share::SUPPRESSIONS;
+use crate::wallclock;
+
+// A campaign directory shown on the dashboard, given as `NAME=DIR` or just
+// `DIR` to be named after the directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tenant {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl FromStr for Tenant {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (name, dir) = match s.split_once('=') {
+            Some((name, dir)) => (name.to_string(), PathBuf::from(dir)),
+            None => {
+                let dir = PathBuf::from(s);
+                let name = dir
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .ok_or_else(|| format_err!("can't name {}, use NAME=DIR", s))?
+                    .to_string();
+                (name, dir)
+            }
+        };
+        if name.is_empty() || name.contains('/') {
+            bail!("tenant name {:?} must be usable in a URL path", name);
+        }
+        Ok(Tenant { name, dir })
+    }
+}
+
+// Whether a finding can be reproduced from what was saved: bundles carry
+// their environment with them, other findings need the game to be at hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Repro {
+    Bundle,
+    Replayable,
+    Unreplayable,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Entry {
+    pub tenant: String,
+    pub game: String,
+    pub id: String,
+    pub severity: Severity,
+    pub message: String,
+    pub signature: String,
+    pub age_secs: u64,
+    // When the finding was saved, in seconds since the epoch.
+    pub found_secs: u64,
+    // From the nearest suppressions file, None for findings not yet triaged.
+    pub triage: Option<Triage>,
+    pub repro: Repro,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+// Finds every saved finding under each tenant's directory. A directory with
+// a daemon's status.json names the game beneath it, otherwise the tenant
+// does, and suppressions apply to everything under the directory holding
+// them. Shadow findings are left out as they are in reports.
+pub fn scan(tenants: &[Tenant]) -> Vec<Entry> {
+    let now = SystemTime::now();
+    let mut entries = vec![];
+    for tenant in tenants {
+        walk(
+            tenant,
+            &tenant.dir,
+            &tenant.name,
+            &Suppressions::default(),
+            now,
+            &mut entries,
+        );
+    }
+    entries
+}
+
+fn walk(
+    tenant: &Tenant,
+    dir: &Path,
+    game: &str,
+    suppressions: &Suppressions,
+    now: SystemTime,
+    entries: &mut Vec<Entry>,
+) {
+    let game = match dir.file_name().and_then(|n| n.to_str()) {
+        Some(name) if dir.join("status.json").is_file() => name,
+        _ => game,
+    };
+    let loaded;
+    let suppressions = match Suppressions::load(&dir.join(SUPPRESSIONS)) {
+        Ok(s) => {
+            loaded = s;
+            &loaded
+        }
+        Err(_) => suppressions,
+    };
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(_) => return,
+    };
+    // File types don't follow symlinks, so a link back up the tree can't
+    // send this round in circles. Linked directories are skipped, linked
+    // findings are still read.
+    let mut paths: Vec<(PathBuf, fs::FileType)> = read
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Some((e.path(), e.file_type().ok()?)))
+        .collect();
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, file_type) in paths {
+        if file_type.is_dir() {
+            if path.file_name().is_some_and(|n| n != "shadow") {
+                walk(tenant, &path, game, suppressions, now, entries);
+            }
+            continue;
+        }
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        let is_finding = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+            n.starts_with("finding-") && (n.ends_with(".json") || n.ends_with(".tar"))
+        });
+        if !is_finding {
+            continue;
+        }
+        let finding = match Finding::load(&path) {
+            Ok(finding) => finding,
+            Err(_) => continue,
+        };
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let age = modified
+            .and_then(|m| now.duration_since(m).ok())
+            .unwrap_or_default();
+        let found = modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let repro = if path.extension().is_some_and(|e| e == "tar") {
+            Repro::Bundle
+        } else if finding.game.is_some() {
+            Repro::Replayable
+        } else {
+            Repro::Unreplayable
+        };
+        entries.push(Entry {
+            tenant: tenant.name.clone(),
+            game: game.to_string(),
+            id: finding.id.clone(),
+            severity: finding.severity,
+            message: finding.message.lines().next().unwrap_or("").to_string(),
+            triage: suppressions.get(&finding.signature).map(|s| s.state),
+            signature: finding.signature,
+            age_secs: age.as_secs(),
+            found_secs: found.as_secs(),
+            repro,
+            path,
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sort {
+    // Newest first.
+    #[default]
+    Age,
+    // Most severe first.
+    Severity,
+    Game,
+}
+
+// What the listing shows, taken from the query string.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub tenant: Option<String>,
+    pub game: Option<String>,
+    pub severity: Option<String>,
+    // `new` for findings without a suppression.
+    pub triage: Option<String>,
+    pub sort: Sort,
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Self, Error> {
+        let mut filter = Filter::default();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            if value.is_empty() {
+                continue;
+            }
+            match key {
+                "tenant" => filter.tenant = Some(value),
+                "game" => filter.game = Some(value),
+                "severity" => filter.severity = Some(value.to_lowercase()),
+                "triage" => filter.triage = Some(value.to_lowercase()),
+                "sort" => {
+                    filter.sort = match value.as_str() {
+                        "age" => Sort::Age,
+                        "severity" => Sort::Severity,
+                        "game" => Sort::Game,
+                        _ => bail!("unknown sort {}, expected age, severity or game", value),
+                    }
+                }
+                _ => bail!("unknown query parameter {}", key),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn apply(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        entries.retain(|e| {
+            self.tenant.as_ref().is_none_or(|t| *t == e.tenant)
+                && self.game.as_ref().is_none_or(|g| *g == e.game)
+                && self
+                    .severity
+                    .as_ref()
+                    .is_none_or(|s| *s == format!("{:?}", e.severity).to_lowercase())
+                && self
+                    .triage
+                    .as_ref()
+                    .is_none_or(|t| *t == triage_label(e.triage))
+        });
+        match self.sort {
+            Sort::Age => entries.sort_by_key(|e| e.age_secs),
+            Sort::Severity => entries.sort_by(|a, b| {
+                b.severity
+                    .cmp(&a.severity)
+                    .then(a.age_secs.cmp(&b.age_secs))
+            }),
+            Sort::Game => entries.sort_by(|a, b| {
+                (&a.tenant, &a.game, a.age_secs).cmp(&(&b.tenant, &b.game, b.age_secs))
+            }),
+        }
+        entries
+    }
+}
+
+fn triage_label(triage: Option<Triage>) -> String {
+    match triage {
+        Some(state) => format!("{:?}", state).to_lowercase(),
+        None => "new".to_string(),
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// Such as 40s, 12m, 5h or 3d.
+fn render_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+        s => format!("{}d", s / (24 * 60 * 60)),
+    }
+}
+
+pub fn render_html(tenants: &[Tenant], filter: &Filter, entries: &[Entry]) -> String {
+    let mut out = "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Findings</title><link rel=\"alternate\" type=\"application/atom+xml\" title=\"New findings\" href=\"/feed.atom\"></head><body>\n<h1>Findings</h1>\n".to_string();
+    out.push_str(
+        "<form method=\"get\">\n<select name=\"tenant\"><option value=\"\">all campaigns</option>",
+    );
+    for tenant in tenants {
+        out.push_str(&format!(
+            "<option{}>{}</option>",
+            if filter.tenant.as_ref() == Some(&tenant.name) {
+                " selected"
+            } else {
+                ""
+            },
+            escape(&tenant.name)
+        ));
+    }
+    out.push_str("</select>\n");
+    let selects: [(&str, &Option<String>, &[&str]); 2] = [
+        (
+            "severity",
+            &filter.severity,
+            &["security", "critical", "error", "warning", "info"],
+        ),
+        (
+            "triage",
+            &filter.triage,
+            &["new", "known", "wontfix", "fixed"],
+        ),
+    ];
+    for (name, selected, options) in selects {
+        out.push_str(&format!(
+            "<select name=\"{0}\"><option value=\"\">any {0}</option>",
+            name
+        ));
+        for option in options {
+            out.push_str(&format!(
+                "<option{}>{}</option>",
+                if selected.as_deref() == Some(*option) {
+                    " selected"
+                } else {
+                    ""
+                },
+                option
+            ));
+        }
+        out.push_str("</select>\n");
+    }
+    out.push_str(&format!(
+        "<input name=\"game\" placeholder=\"game\" value=\"{}\">\n<select name=\"sort\">",
+        escape(filter.game.as_deref().unwrap_or(""))
+    ));
+    for (sort, label) in [
+        (Sort::Age, "age"),
+        (Sort::Severity, "severity"),
+        (Sort::Game, "game"),
+    ] {
+        out.push_str(&format!(
+            "<option value=\"{0}\"{1}>by {0}</option>",
+            label,
+            if filter.sort == sort { " selected" } else { "" }
+        ));
+    }
+    out.push_str("</select>\n<button>Filter</button>\n</form>\n");
+    out.push_str(&format!("<p>{} findings</p>\n", entries.len()));
+    out.push_str("<table>\n<tr><th>Campaign</th><th>Game</th><th>Severity</th><th>Age</th><th>Triage</th><th>Reproduction</th><th>Finding</th></tr>\n");
+    for e in entries {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{:?}</td><td><a href=\"/artifact/{}/{}\" title=\"{}\">{}</a> {}</td></tr>\n",
+            escape(&e.tenant),
+            escape(&e.game),
+            e.severity,
+            render_age(e.age_secs),
+            triage_label(e.triage),
+            e.repro,
+            escape(&e.tenant),
+            escape(&e.id),
+            escape(&e.signature),
+            escape(&e.id),
+            escape(&e.message)
+        ));
+    }
+    out.push_str("</table>\n</body></html>\n");
+    out
+}
+
+// Findings in an Atom feed are at most this many of the newest.
+const FEED_ENTRIES: usize = 50;
+
+// An Atom feed of new unique findings, newest first, for subscribing from a
+// feed reader or a feed-to-chat bridge instead of a webhook. A finding seen
+// again under the same signature isn't new, so each signature is an entry
+// from when it was first saved, keeping its ID stable for readers. Links
+// are to artifacts under `base_url`, the dashboard's address.
+pub fn render_atom(base_url: &str, entries: &[Entry]) -> String {
+    let mut first: Vec<&Entry> = vec![];
+    for e in entries {
+        match first
+            .iter_mut()
+            .find(|f| f.tenant == e.tenant && f.signature == e.signature)
+        {
+            Some(f) if f.found_secs > e.found_secs => *f = e,
+            Some(_) => {}
+            None => first.push(e),
+        }
+    }
+    first.sort_by_key(|e| std::cmp::Reverse(e.found_secs));
+    first.truncate(FEED_ENTRIES);
+    let base_url = base_url.trim_end_matches('/');
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n<title>brdgme-fuzz findings</title>\n<id>{0}/feed.atom</id>\n<link rel=\"self\" href=\"{0}/feed.atom\"/>\n<link href=\"{0}/\"/>\n<updated>{1}</updated>\n",
+        escape(base_url),
+        wallclock::rfc3339(first.first().map_or(0, |e| e.found_secs))
+    );
+    for e in first {
+        out.push_str(&format!(
+            "<entry>\n<title>[{:?}] {}: {}</title>\n<id>urn:brdgme-fuzz:{}:{}</id>\n<link href=\"{}/artifact/{}/{}\"/>\n<updated>{}</updated>\n<author><name>{}</name></author>\n<category term=\"{}\"/>\n<summary>{:?} finding in {} ({}), triage {}, reproduction {:?}, signature {}</summary>\n</entry>\n",
+            e.severity,
+            escape(&e.game),
+            escape(&e.message),
+            escape(&e.tenant),
+            bytes_hash(e.signature.as_bytes()),
+            escape(base_url),
+            escape(&e.tenant),
+            escape(&e.id),
+            wallclock::rfc3339(e.found_secs),
+            escape(&e.tenant),
+            format!("{:?}", e.severity).to_lowercase(),
+            e.severity,
+            escape(&e.game),
+            escape(&e.tenant),
+            triage_label(e.triage),
+            e.repro,
+            escape(&e.signature)
+        ));
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+// Serves the dashboard until the process is stopped, scanning the tenants'
+// directories afresh for every page so new findings show up on reload.
+// Requests are handled one at a time, which is plenty for people looking.
+pub fn serve(listen: &str, tenants: &[Tenant]) -> Result<(), Error> {
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("Dashboard listening on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle(stream, tenants) {
+                    eprintln!("dashboard request failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("dashboard connection failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+fn handle(stream: TcpStream, tenants: &[Tenant]) -> Result<(), Error> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Only the host matters of the rest of the request, for the feed's
+    // links, but all of it is read so closing the connection doesn't reset
+    // it before the client sees the response.
+    let mut host = None;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_string());
+            }
+        }
+        header.clear();
+    }
+    let mut stream = reader.into_inner();
+    let mut words = request_line.split_whitespace();
+    let (method, target) = match (words.next(), words.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return respond(&mut stream, "400 Bad Request", "text/plain", b"bad request"),
+    };
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"only GET is supported",
+        );
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let filter = match Filter::parse(query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            return respond(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain",
+                e.to_string().as_bytes(),
+            )
+        }
+    };
+    match path {
+        "/" => {
+            let entries = filter.apply(scan(tenants));
+            let html = render_html(tenants, &filter, &entries);
+            respond(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                html.as_bytes(),
+            )
+        }
+        "/findings.json" => {
+            let json = serde_json::to_string_pretty(&filter.apply(scan(tenants)))?;
+            respond(&mut stream, "200 OK", "application/json", json.as_bytes())
+        }
+        "/feed.atom" => {
+            let base_url = format!(
+                "http://{}",
+                match host {
+                    Some(ref host) => host.clone(),
+                    None => stream.local_addr()?.to_string(),
+                }
+            );
+            let atom = render_atom(&base_url, &filter.apply(scan(tenants)));
+            respond(
+                &mut stream,
+                "200 OK",
+                "application/atom+xml; charset=utf-8",
+                atom.as_bytes(),
+            )
+        }
+        _ => {
+            // Only saved findings found by the scan are served, so the path
+            // can't reach anything else on disk.
+            let artifact = path.strip_prefix("/artifact/").and_then(|rest| {
+                let (tenant, id) = rest.split_once('/')?;
+                let (tenant, id) = (percent_decode(tenant), percent_decode(id));
+                scan(tenants)
+                    .into_iter()
+                    .find(|e| e.tenant == tenant && e.id == id)
+            });
+            match artifact {
+                Some(entry) => {
+                    let body = fs::read(&entry.path)?;
+                    let name = entry
+                        .path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("finding");
+                    let content_type = if name.ends_with(".tar") {
+                        "application/x-tar"
+                    } else {
+                        "application/json"
+                    };
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Disposition: attachment; filename=\"{}\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        content_type,
+                        name,
+                        body.len()
+                    )?;
+                    stream.write_all(&body)?;
+                    Ok(())
+                }
+                None => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+            }
+        }
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    use crate::finding::Category;
+
+    #[test]
+    fn scans_skip_linked_directories() {
+        let dir = std::env::temp_dir().join(format!("brdgme-fuzz-dashboard-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let run = dir.join("run");
+        fs::create_dir_all(&run).unwrap();
+        let mut finding = Finding::new(Category::Request, Severity::Critical, "boom");
+        finding.assign_id();
+        finding.save(&run).unwrap();
+        // A loop back up the tree, and a second way to the finding.
+        symlink(&dir, run.join("loop")).unwrap();
+        symlink(&run, dir.join("again")).unwrap();
+
+        let entries = scan(&[Tenant {
+            name: "toy".to_string(),
+            dir: dir.clone(),
+        }]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, finding.id);
+    }
+}
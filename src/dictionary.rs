@@ -0,0 +1,196 @@
+use failure::{format_err, Error};
+use rand::Rng;
+
+use brdgme_game::command;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::report::acceptance::command_head;
+use crate::strategy::{FuzzRng, Strategy};
+
+// Concrete commands a game has accepted, grouped by head. Saved with a
+// `.dict` extension it's written in AFL's dictionary format, otherwise one
+// command per line. Both are read back either way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dictionary {
+    pub commands: BTreeMap<String, BTreeSet<String>>,
+    // Caps how many commands are kept per head so long campaigns don't grow
+    // it without bound.
+    pub per_head: usize,
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Dictionary::new(1000)
+    }
+}
+
+impl Dictionary {
+    pub fn new(per_head: usize) -> Self {
+        Dictionary {
+            commands: BTreeMap::new(),
+            per_head,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.values().map(BTreeSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn insert(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        let entries = self.commands.entry(command_head(command)).or_default();
+        if entries.len() < self.per_head {
+            entries.insert(command.to_string());
+        }
+    }
+
+    pub fn merge(&mut self, other: Dictionary) {
+        for command in other.commands.into_values().flatten() {
+            self.insert(&command);
+        }
+    }
+
+    pub fn for_head(&self, head: &str) -> Option<&BTreeSet<String>> {
+        self.commands.get(head)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut dictionary = Dictionary::new(usize::MAX);
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let command = match afl_value(line) {
+                Some(value) => {
+                    unescape(value).ok_or_else(|| format_err!("bad escape in {:?}", line))?
+                }
+                None => line.to_string(),
+            };
+            dictionary.insert(&command);
+        }
+        Ok(dictionary)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let afl = path.extension().is_some_and(|e| e == "dict");
+        let mut out = String::new();
+        for (i, command) in self.commands.values().flatten().enumerate() {
+            if afl {
+                out.push_str(&format!("cmd_{}=\"{}\"\n", i, escape(command)));
+            } else {
+                out.push_str(command);
+                out.push('\n');
+            }
+        }
+        fs::write(path, out)?;
+        Ok(())
+    }
+}
+
+// The quoted part of an AFL entry such as `kw_1="value"`, names being
+// optional.
+fn afl_value(line: &str) -> Option<&str> {
+    let start = line.find('"')?;
+    let name = &line[..start];
+    let named = name.strip_suffix('=').is_some_and(|n| {
+        n.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+    });
+    if (name.is_empty() || named) && line.len() > start + 1 && line.ends_with('"') {
+        Some(&line[start + 1..line.len() - 1])
+    } else {
+        None
+    }
+}
+
+// AFL dictionaries escape quotes, backslashes and anything unprintable as
+// \xNN.
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match iter.next()? {
+            b'x' => {
+                let hex = [iter.next()?, iter.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            other => bytes.push(other),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// Swaps some of another strategy's commands for a known good command with
+// the same head, reaching states that need real commands faster.
+pub struct DictionaryStrategy {
+    name: String,
+    inner: Box<dyn Strategy>,
+    dictionary: Arc<Dictionary>,
+    rate: f64,
+}
+
+impl DictionaryStrategy {
+    pub fn new(inner: Box<dyn Strategy>, dictionary: Arc<Dictionary>, rate: f64) -> Self {
+        DictionaryStrategy {
+            name: format!("dictionary+{}", inner.name()),
+            inner,
+            dictionary,
+            rate,
+        }
+    }
+}
+
+impl Strategy for DictionaryStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) {
+        self.inner.new_game();
+    }
+
+    fn command(&mut self, spec: &command::Spec, names: &[String], rng: &mut FuzzRng) -> String {
+        let command = self.inner.command(spec, names, rng);
+        if rng.gen::<f64>() >= self.rate {
+            return command;
+        }
+        match self.dictionary.for_head(&command_head(&command)) {
+            Some(known) if !known.is_empty() => {
+                let i = rng.gen_range(0, known.len());
+                known.iter().nth(i).cloned().unwrap_or(command)
+            }
+            _ => command,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use failure::Error;
+
+use brdgme_cmd::requester::Requester;
+
+use crate::config::Config;
+use crate::engine::{FuzzStep, Fuzzer, Placings};
+use crate::finding::Finding;
+use crate::report::acceptance::Outcome;
+
+// What the fuzzer did on one step, leaving out what only campaign reports
+// use.
+#[derive(Debug)]
+pub enum Step {
+    Created,
+    Command {
+        head: String,
+        outcome: Outcome,
+    },
+    Finished {
+        turns: usize,
+        reason: String,
+        placings: Option<Placings>,
+    },
+    // `stops` is whether a campaign would have stopped on it, false for
+    // protocol violations and oracles in shadow mode.
+    Finding {
+        finding: Box<Finding>,
+        stops: bool,
+    },
+}
+
+// The fuzzer without a campaign around it, playing one game at a time on
+// the caller's thread when asked to. This is all the embedded build has, for
+// running inside something like a browser game debugger where there are no
+// threads or processes, but works the same in the full build.
+pub struct EmbeddedFuzzer {
+    fuzzer: Fuzzer,
+}
+
+impl EmbeddedFuzzer {
+    pub fn new(client: Box<dyn Requester>, seed: u64, config: &Config) -> Result<Self, Error> {
+        Ok(EmbeddedFuzzer {
+            fuzzer: Fuzzer::new(0, seed, client, config)?,
+        })
+    }
+
+    pub fn step(&mut self) -> Step {
+        loop {
+            match self.fuzzer.advance() {
+                FuzzStep::Created => return Step::Created,
+                FuzzStep::Command { head, outcome, .. } => return Step::Command { head, outcome },
+                FuzzStep::Finished {
+                    turns,
+                    reason,
+                    placings,
+                    ..
+                } => {
+                    return Step::Finished {
+                        turns,
+                        reason,
+                        placings,
+                    }
+                }
+                FuzzStep::Finding(finding) => {
+                    return Step::Finding {
+                        finding,
+                        stops: true,
+                    }
+                }
+                FuzzStep::Violation(finding) | FuzzStep::Shadow(finding) => {
+                    return Step::Finding {
+                        finding,
+                        stops: false,
+                    }
+                }
+                FuzzStep::Read { .. }
+                | FuzzStep::RenderParse { .. }
+                | FuzzStep::SpecNodes { .. }
+                | FuzzStep::Reached(_)
+                | FuzzStep::BlindSpot(_) => {}
+            }
+        }
+    }
+
+    // Steps until a game finishes or there's a finding a campaign would stop
+    // on, returning every step along the way.
+    pub fn play_game(&mut self) -> Vec<Step> {
+        let mut steps = vec![];
+        loop {
+            let step = self.step();
+            let done = matches!(
+                step,
+                Step::Finished { .. } | Step::Finding { stops: true, .. }
+            );
+            steps.push(step);
+            if done {
+                return steps;
+            }
+        }
+    }
+}
@@ -0,0 +1,2039 @@
+use failure::{bail, format_err, Error};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::{self, Requester};
+use brdgme_game::command;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+#[cfg(not(feature = "embedded"))]
+use std::thread;
+use std::time::Duration;
+
+#[cfg(not(feature = "embedded"))]
+use crate::backend::process;
+use crate::backend::session::{self, Session};
+use crate::backend::trace::TraceRequester;
+use crate::canonical::Canonicalizer;
+use crate::config::{
+    Config, FinishReasonHook, GamePhaseHook, PlayerCounts, ResignConfig, TraceConfig, TurnAction,
+    TurnAttempts,
+};
+use crate::finding::{Category, Finding, GameMeta, HistoryEntry, Severity};
+use crate::markup;
+use crate::names::NameProvider;
+use crate::oracle::OracleRegistry;
+use crate::pacing::Delay;
+use crate::report::acceptance::{self, Outcome};
+use crate::report::blind_spot::BlindSpot;
+use crate::report::finish;
+use crate::report::overhead::Overhead;
+use crate::report::spec_nodes::{self, Choice};
+use crate::report::user_errors::UserErrorSample;
+use crate::strategy::{self, Bot, BotSeats, FuzzRng, Strategy};
+use crate::timing::Instant;
+use crate::wallclock::{self, WallClock};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameId {
+    pub worker: usize,
+    pub game: usize,
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.worker, self.game)
+    }
+}
+
+// Turns and commands so far in a game. A turn is over once the player who
+// acted is no longer to move or the players to move change, so a turn made
+// of several commands, such as a few buys and then done, counts once.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MoveClock {
+    pub turn: usize,
+    // Commands accepted from each player.
+    pub moves: Vec<usize>,
+    // Who took the last turn, and how many in a row they've taken.
+    pub streak_player: Option<usize>,
+    pub streak: usize,
+    // Times each player appeared in whose_turn, counted once per state.
+    #[serde(default)]
+    pub offered: Vec<usize>,
+    // The players to move in the state last offered.
+    #[serde(default)]
+    pub to_move: Vec<usize>,
+}
+
+impl MoveClock {
+    pub fn new(players: usize) -> Self {
+        MoveClock {
+            moves: vec![0; players],
+            offered: vec![0; players],
+            ..MoveClock::default()
+        }
+    }
+
+    pub fn offer(&mut self, whose_turn: &[usize]) {
+        for &player in whose_turn {
+            if player >= self.offered.len() {
+                self.offered.resize(player + 1, 0);
+            }
+            self.offered[player] += 1;
+        }
+        self.to_move = unique_players(whose_turn);
+    }
+
+    // A command accepted from `player`, leaving `whose_turn` to move. Call
+    // before offering the state it led to.
+    pub fn record(&mut self, player: usize, whose_turn: &[usize]) {
+        if player >= self.moves.len() {
+            self.moves.resize(player + 1, 0);
+        }
+        self.moves[player] += 1;
+        let to_move = unique_players(whose_turn);
+        let changed = to_move.len() != self.to_move.len()
+            || to_move.iter().any(|p| !self.to_move.contains(p));
+        if to_move.contains(&player) && !changed {
+            return;
+        }
+        self.turn += 1;
+        if self.streak_player == Some(player) {
+            self.streak += 1;
+        } else {
+            self.streak_player = Some(player);
+            self.streak = 1;
+        }
+    }
+}
+
+pub struct StepContext<'a> {
+    pub game_id: GameId,
+    pub clock: &'a MoveClock,
+    pub player: Option<usize>,
+    pub command: Option<&'a str>,
+    pub names: &'a [String],
+    pub before: Option<&'a FuzzGame>,
+    pub after: &'a FuzzGame,
+    pub latency: Duration,
+    pub(crate) client: RefCell<&'a mut dyn Requester>,
+}
+
+impl<'a> StepContext<'a> {
+    pub fn logs(&self) -> &'a [api::CliLog] {
+        &self.after.logs
+    }
+
+    // Requests made by oracles go straight to the target, bypassing game
+    // tracing so they don't show up as moves in the trace.
+    pub fn request(&self, req: &api::Request) -> Result<api::Response, Error> {
+        self.client.borrow_mut().request(req)
+    }
+
+    pub fn play(
+        &self,
+        state: &str,
+        player: usize,
+        command: &str,
+    ) -> Result<CommandResponse, Error> {
+        exec_command(
+            &mut **self.client.borrow_mut(),
+            command.to_string(),
+            state.to_string(),
+            player,
+            self.names.to_vec(),
+        )
+    }
+}
+
+pub(crate) struct TraceSampler {
+    pub(crate) config: TraceConfig,
+    pub(crate) budget: Arc<AtomicUsize>,
+}
+
+impl TraceSampler {
+    fn sample(&self, rng: &mut impl Rng) -> bool {
+        self.budget.load(Ordering::SeqCst) > 0
+            && rng.gen::<f64>() < self.config.rate
+            && self
+                .budget
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |b| b.checked_sub(1))
+                .is_ok()
+    }
+}
+
+// A player's run of rejected commands since they last had one accepted.
+#[derive(Default)]
+struct Starving {
+    // States moved on from by someone else's command, each after this
+    // player tried and failed in it.
+    states: usize,
+    tried: bool,
+    reported: bool,
+    last: Option<(String, String)>,
+}
+
+pub(crate) struct Fuzzer {
+    client: TraceRequester<Box<dyn requester::Requester>>,
+    next_client: Option<Box<dyn requester::Requester>>,
+    player_counts: Vec<usize>,
+    player_count_preset: PlayerCounts,
+    names: Vec<String>,
+    // Games and histories are shared rather than copied: a step only
+    // allocates the new game it produced, and history snapshots handed out
+    // alongside findings are copied on the next write instead of per step.
+    game: Option<Arc<FuzzGame>>,
+    game_id: GameId,
+    clock: MoveClock,
+    seed: u64,
+    initial_state: String,
+    history: Arc<Vec<HistoryEntry>>,
+    checkpoints: Arc<BTreeMap<usize, String>>,
+    seeds: FuzzRng,
+    rng: FuzzRng,
+    strategy: Box<dyn Strategy>,
+    game_phase: Option<GamePhaseHook>,
+    phase_strategies: Vec<(String, Box<dyn Strategy>)>,
+    bot: Option<(Box<dyn Bot>, BotSeats)>,
+    // The seats the bot has in the current game.
+    bot_seats: Vec<usize>,
+    resign: Option<ResignConfig>,
+    // Drawn from an rng of its own so pacing doesn't change the commands.
+    delay: Option<(Delay, FuzzRng)>,
+    wallclock: Option<(WallClock, FuzzRng)>,
+    // The current game's virtual time. It's this fuzzer's own and only
+    // installed on the thread while advancing, so fuzzers sharing a thread
+    // don't move each other's clocks.
+    now: Option<u64>,
+    // The current game's stderr lines, installed on the thread while
+    // advancing in the same way.
+    #[cfg(not(feature = "embedded"))]
+    stderr: VecDeque<String>,
+    // The virtual time of each command played in the current game.
+    times: Vec<u64>,
+    // Players who have resigned from the current game.
+    resigned: Vec<usize>,
+    name_provider: Box<dyn NameProvider>,
+    oracles: OracleRegistry,
+    pending: VecDeque<FuzzStep>,
+    pub(crate) trace_sampler: Option<TraceSampler>,
+    session_every: Option<usize>,
+    // Negotiated once per client, None until the first game on it.
+    sessions_supported: Option<bool>,
+    session: Option<Session>,
+    finish_reason: FinishReasonHook,
+    // Chance of a read-only request before each command.
+    spectate: f64,
+    // Whether each kind of read request is supported by the current client,
+    // decided by its first response.
+    reads_supported: HashMap<&'static str, bool>,
+    collect_commands: bool,
+    collect_spec_nodes: bool,
+    collect_coverage: bool,
+    render_sample: f64,
+    check_player_refs: bool,
+    check_rejections: bool,
+    // Whether the client answers the Status requests made after rejected
+    // commands, decided by its first response.
+    rejection_status_supported: Option<bool>,
+    strict_rejections: f64,
+    strict_pending: Option<HistoryEntry>,
+    canonical: Canonicalizer,
+    max_rejections: Option<usize>,
+    state_checkpoints: Option<usize>,
+    // Commands rejected since the last accepted one, the latest few kept
+    // for the blind spot report.
+    rejections: usize,
+    recent_rejections: VecDeque<(String, String)>,
+    turn_attempts: Option<TurnAttempts>,
+    // Each player's rejected commands in the current state, and the players
+    // whose turn in it was skipped.
+    attempts: HashMap<usize, Vec<(String, String)>>,
+    skipped_turns: Vec<usize>,
+    starvation: Option<usize>,
+    starving: HashMap<usize, Starving>,
+    simultaneous: bool,
+    // Players yet to act in the current simultaneous round.
+    round: VecDeque<usize>,
+    // Time spent since the last take_overhead.
+    overhead: Overhead,
+}
+
+impl Fuzzer {
+    pub(crate) fn new(
+        worker: usize,
+        seed: u64,
+        mut client: Box<dyn requester::Requester>,
+        config: &Config,
+    ) -> Result<Self, Error> {
+        let player_counts = match client.request(&api::Request::PlayerCounts)? {
+            api::Response::PlayerCounts { player_counts } => {
+                config.player_counts.select(player_counts)
+            }
+            v => bail!("invalid response to player counts request: {:?}", v),
+        };
+        let mut client = TraceRequester::new(client);
+        client.redactor = config.redact.clone();
+        Ok(Fuzzer {
+            client,
+            next_client: None,
+            player_counts,
+            player_count_preset: config.player_counts,
+            names: vec![],
+            game: None,
+            game_id: GameId { worker, game: 0 },
+            clock: MoveClock::default(),
+            seed: 0,
+            initial_state: String::new(),
+            history: Arc::default(),
+            checkpoints: Arc::default(),
+            seeds: strategy::seeded_rng(seed),
+            rng: strategy::seeded_rng(0),
+            strategy: (config.strategy)(),
+            game_phase: config.game_phase.clone(),
+            phase_strategies: config
+                .phase_strategies
+                .iter()
+                .map(|(phase, strategy)| (phase.clone(), strategy()))
+                .collect(),
+            bot: config.bot.as_ref().map(|b| ((b.bot)(), b.seats.clone())),
+            bot_seats: vec![],
+            resign: config.resign.clone(),
+            wallclock: config
+                .wallclock
+                .clone()
+                .map(|c| (c, strategy::seeded_rng(0))),
+            now: None,
+            #[cfg(not(feature = "embedded"))]
+            stderr: VecDeque::new(),
+            times: vec![],
+            delay: config.command_delay.map(|d| {
+                (
+                    d,
+                    strategy::seeded_rng(strategy::derive_seed(seed, u64::MAX)),
+                )
+            }),
+            resigned: vec![],
+            name_provider: (config.names)(),
+            oracles: (config.oracles)(),
+            pending: VecDeque::new(),
+            trace_sampler: None,
+            session_every: config.session_checkpoint,
+            sessions_supported: None,
+            session: None,
+            finish_reason: config.finish_reason.clone(),
+            spectate: config.spectate,
+            reads_supported: HashMap::new(),
+            collect_commands: config.dictionary_out.is_some(),
+            collect_spec_nodes: config.spec_stats_out.is_some() || config.collect_coverage,
+            collect_coverage: config.collect_coverage,
+            render_sample: config.render_sample,
+            check_player_refs: config.check_player_refs,
+            check_rejections: config.check_rejections,
+            rejection_status_supported: None,
+            strict_rejections: config.strict_rejections,
+            strict_pending: None,
+            canonical: config.canonical.clone(),
+            max_rejections: config.max_rejections,
+            state_checkpoints: config.state_checkpoints,
+            rejections: 0,
+            recent_rejections: VecDeque::new(),
+            turn_attempts: config.turn_attempts,
+            attempts: HashMap::new(),
+            skipped_turns: vec![],
+            starvation: config.starvation,
+            starving: HashMap::new(),
+            simultaneous: config.simultaneous,
+            round: VecDeque::new(),
+            overhead: Overhead::default(),
+        })
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    pub(crate) fn take_overhead(&mut self) -> Overhead {
+        std::mem::take(&mut self.overhead)
+    }
+
+    // A new strategy is used from the next command, new seeds from the next
+    // game.
+    #[cfg(not(feature = "embedded"))]
+    pub(crate) fn set_strategy(&mut self, strategy: Box<dyn Strategy>) {
+        self.strategy = strategy;
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    pub(crate) fn reseed(&mut self, seed: u64) {
+        self.seeds = strategy::seeded_rng(seed);
+    }
+
+    // Recycled clients are swapped in at the next game boundary so in-flight
+    // games finish against the binary that created them.
+    #[cfg(not(feature = "embedded"))]
+    pub(crate) fn recycle(&mut self, client: Box<dyn requester::Requester>) {
+        self.next_client = Some(client);
+    }
+
+    fn new_game(&mut self) -> Result<Duration, Error> {
+        if let Some(mut client) = self.next_client.take() {
+            self.player_counts = match client.request(&api::Request::PlayerCounts)? {
+                api::Response::PlayerCounts { player_counts } => {
+                    self.player_count_preset.select(player_counts)
+                }
+                v => bail!("invalid response to player counts request: {:?}", v),
+            };
+            let redactor = self.client.redactor.clone();
+            self.client = TraceRequester::new(client);
+            self.client.redactor = redactor;
+            self.sessions_supported = None;
+            self.reads_supported.clear();
+        }
+        self.seed = self.seeds.gen();
+        self.rng = strategy::seeded_rng(self.seed);
+        self.strategy.new_game();
+        for (_, strategy) in &mut self.phase_strategies {
+            strategy.new_game();
+        }
+        self.game_id.game += 1;
+        #[cfg(not(feature = "embedded"))]
+        process::swap_stderr(VecDeque::new());
+        self.history = Arc::default();
+        self.checkpoints = Arc::default();
+        self.rejections = 0;
+        self.recent_rejections.clear();
+        self.attempts.clear();
+        self.skipped_turns.clear();
+        self.resigned.clear();
+        self.strict_pending = None;
+        self.round.clear();
+        self.starving.clear();
+        self.times.clear();
+        if let Some((ref clock, ref mut rng)) = self.wallclock {
+            *rng = strategy::seeded_rng(strategy::derive_seed(self.seed, 1));
+            self.now = Some(clock.start);
+            wallclock::set_virtual_now(self.now);
+        }
+        let players = *self.rng.choose(&self.player_counts).ok_or_else(|| {
+            format_err!("could not get player counts from {:?}", self.player_counts)
+        })?;
+        self.clock = MoveClock::new(players);
+        self.bot_seats = match self.bot {
+            Some((ref mut bot, ref seats)) => {
+                bot.new_game();
+                seats.pick(players, &mut self.rng)
+            }
+            None => vec![],
+        };
+        self.client.stop();
+        // Drawn from an rng of its own so tracing doesn't change the games.
+        if let Some(ref sampler) = self.trace_sampler {
+            let mut rng = strategy::seeded_rng(strategy::derive_seed(self.seed, 2));
+            if sampler.sample(&mut rng) {
+                let path = sampler
+                    .config
+                    .dir
+                    .join(format!("game-{}.jsonl", self.game_id));
+                if let Err(e) = self.client.start(&path) {
+                    eprintln!("\nfailed to start trace {}: {}", path.display(), e);
+                }
+            }
+        }
+        self.names = self.name_provider.names(players, &mut self.rng);
+        self.session = match self.session_every {
+            Some(every) => {
+                let client = self.client.inner_mut();
+                if *self
+                    .sessions_supported
+                    .get_or_insert_with(|| session::negotiate(client))
+                {
+                    Some(Session::new(self.game_id.to_string(), every))
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+        let start = Instant::now();
+        let response = self.client.request(&api::Request::New { players });
+        self.overhead.transport += start.elapsed();
+        match response? {
+            api::Response::New {
+                game,
+                logs,
+                player_renders,
+                ..
+            } => {
+                self.initial_state = game.state.clone();
+                self.game = Some(Arc::new(FuzzGame::new(game, player_renders, logs)));
+                self.offer_turns();
+                self.check_renders();
+                Ok(start.elapsed())
+            }
+            v => bail!("invalid response for new game: {:?}", v),
+        }
+    }
+
+    fn offer_turns(&mut self) {
+        if let Some(FuzzGame {
+            game:
+                api::GameResponse {
+                    status: brdgme_game::Status::Active { ref whose_turn, .. },
+                    ..
+                },
+            ..
+        }) = self.game.as_deref()
+        {
+            self.clock.offer(&unique_players(whose_turn));
+        }
+    }
+
+    // Picks among the players whose turn it is that were given a command
+    // spec, so one missing render doesn't stop the others from playing.
+    fn next_command(&mut self) -> Result<(usize, command::Spec, String), Error> {
+        let (player, command_spec) = match self.game.as_deref() {
+            Some(FuzzGame {
+                game:
+                    api::GameResponse {
+                        status: brdgme_game::Status::Active { ref whose_turn, .. },
+                        ..
+                    },
+                ref player_renders,
+                ..
+            }) => {
+                let playable: Vec<usize> = unique_players(whose_turn)
+                    .into_iter()
+                    .filter(|&p| {
+                        !self.skipped_turns.contains(&p)
+                            && player_command_spec(player_renders, p).is_ok()
+                    })
+                    .collect();
+                let picked = if self.simultaneous && playable.len() > 1 {
+                    self.round.retain(|p| playable.contains(p));
+                    if self.round.is_empty() {
+                        let mut order = playable.clone();
+                        self.rng.shuffle(&mut order);
+                        self.round.extend(order);
+                    }
+                    self.round.pop_front()
+                } else {
+                    self.round.clear();
+                    self.rng.choose(&playable).copied()
+                };
+                let player = match picked {
+                    Some(player) => player,
+                    None => match whose_turn.first() {
+                        Some(&player) => player,
+                        None => bail!("unable to pick active turn player from: {:?}", whose_turn),
+                    },
+                };
+                (player, player_command_spec(player_renders, player)?)
+            }
+            _ => bail!("there isn't an active game"),
+        };
+        if !players_satisfy(&command_spec, self.names.len()) {
+            bail!(
+                "player {}'s command_spec requires a player, but the game has {} players",
+                player,
+                self.names.len()
+            );
+        }
+        if let Some(ref resign) = self.resign {
+            if !self.resigned.contains(&player) && self.rng.gen::<f64>() < resign.rate {
+                return Ok((player, command_spec, resign.command.clone()));
+            }
+        }
+        if let (Some((bot, _)), Some(game)) = (self.bot.as_mut(), self.game.as_deref()) {
+            if self.bot_seats.contains(&player) {
+                let command = bot.command(player, game, &command_spec, &self.names, &mut self.rng);
+                return Ok((player, command_spec, command));
+            }
+        }
+        let phase = match (&self.game_phase, self.game.as_deref()) {
+            (Some(hook), Some(game)) => hook(game, &self.clock),
+            _ => None,
+        };
+        let phase_strategies = &mut self.phase_strategies;
+        let strategy =
+            match phase.and_then(|phase| phase_strategies.iter_mut().find(|(p, _)| *p == phase)) {
+                Some((_, strategy)) => strategy,
+                None => &mut self.strategy,
+            };
+        let command = strategy.command(&command_spec, &self.names, &mut self.rng);
+        Ok((player, command_spec, command))
+    }
+
+    // Engines with intermittent render bugs are still worth fuzzing for
+    // everything else, so a short player_renders is reported without ending
+    // the run.
+    fn check_renders(&mut self) {
+        let start = Instant::now();
+        let violations: Vec<String> = match self.game.as_deref() {
+            Some(game) => render_violation(game, self.names.len())
+                .into_iter()
+                .chain(whose_turn_violation(game))
+                .collect(),
+            None => vec![],
+        };
+        for message in violations {
+            let step = self.violation(message);
+            self.pending.push_back(step);
+        }
+        if self.check_player_refs {
+            self.check_player_refs();
+        }
+        self.sample_render_parse();
+        self.overhead.checking += start.elapsed();
+    }
+
+    // Every player who resigned so far is checked after each command, since
+    // a game can bring a resigned player back any time after.
+    fn check_resignations(&mut self, player: usize, command: &str) {
+        let game = match self.game {
+            Some(ref game) if !self.resigned.is_empty() => game.clone(),
+            _ => return,
+        };
+        let players = self.names.len();
+        let mut problems = vec![];
+        match game.game.status {
+            brdgme_game::Status::Active {
+                ref whose_turn,
+                ref eliminated,
+            } => {
+                for &p in &self.resigned {
+                    if whose_turn.contains(&p) {
+                        problems.push(format!("player {} resigned but is in whose_turn", p));
+                    } else if !eliminated.contains(&p) {
+                        problems.push(format!("player {} resigned but isn't eliminated", p));
+                    }
+                }
+                if self.resigned.len() + 1 >= players {
+                    problems.push(format!(
+                        "{} of {} players resigned but the game is still active",
+                        self.resigned.len(),
+                        players
+                    ));
+                }
+            }
+            brdgme_game::Status::Finished { ref placings, .. } => {
+                for &r in &self.resigned {
+                    for p in (0..players).filter(|p| !self.resigned.contains(p)) {
+                        if let (Some(a), Some(b)) = (placings.get(r), placings.get(p)) {
+                            if a < b {
+                                problems.push(format!(
+                                    "player {} resigned but placed ahead of player {}",
+                                    r, p
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for message in problems {
+            let finding =
+                Finding::new(Category::Oracle, Severity::Error, message).with_oracle("resign");
+            let step = self.finding(finding, Some((player, command)));
+            self.pending.push_back(step);
+        }
+    }
+
+    // A rejected command must leave the game as it was. The backend is asked
+    // for the state the command was played on, or for its own copy in
+    // session mode, and anything else coming back means the command was
+    // partly applied before being rejected: corruption passing itself off as
+    // a harmless user error, so it's reported as critical.
+    fn check_rejection(&mut self, player: usize, command: &str, message: &str) {
+        if !self.check_rejections || self.rejection_status_supported == Some(false) {
+            return;
+        }
+        let game = match self.game {
+            Some(ref game) => game.clone(),
+            None => return,
+        };
+        let start = Instant::now();
+        let field = self
+            .session
+            .as_ref()
+            .and_then(Session::resume_field)
+            .unwrap_or_else(|| game.game.state.clone());
+        let response = self
+            .client
+            .inner_mut()
+            .request(&api::Request::Status { game: field });
+        self.overhead.checking += start.elapsed();
+        let status = match response {
+            Ok(api::Response::Status { game: status, .. }) => status,
+            _ => {
+                self.rejection_status_supported.get_or_insert(false);
+                return;
+            }
+        };
+        self.rejection_status_supported = Some(true);
+        if self.canonical.same(&status.state, &game.game.state) {
+            return;
+        }
+        let finding = Finding::new(
+            Category::Oracle,
+            Severity::Critical,
+            format!(
+                "command was rejected with {:?} but the state changed",
+                message
+            ),
+        )
+        .with_oracle("rejection")
+        .with_artifact("state_after", status.state);
+        let step = self.finding(finding, Some((player, command)));
+        self.pending.push_back(step);
+    }
+
+    // Goes further than check_rejection for a sample of rejections: the next
+    // command accepted on the same state is played again statelessly on
+    // that state, once on its own and once after sending the rejected
+    // command again. Both must come out as the live game did, and the
+    // rejected command must be rejected again, or the rejection wasn't free
+    // of side effects.
+    fn check_strict_rejection(
+        &mut self,
+        rejected: HistoryEntry,
+        player: usize,
+        command: &str,
+        before: &FuzzGame,
+    ) {
+        let after = match self.game {
+            Some(ref game) => game.clone(),
+            None => return,
+        };
+        let start = Instant::now();
+        let state = &before.game.state;
+        let names = &self.names;
+        let client = self.client.inner_mut();
+        let mut play = |player: usize, command: &str| {
+            exec_command(
+                &mut **client,
+                command.to_string(),
+                state.clone(),
+                player,
+                names.clone(),
+            )
+        };
+        let alone = play(player, command);
+        let again = play(rejected.player, &rejected.command);
+        let following = play(player, command);
+        self.overhead.checking += start.elapsed();
+        let mut problems = vec![];
+        if let Ok(CommandResponse::Ok(_)) = again {
+            problems.push("the rejected command was accepted when sent again".to_string());
+        }
+        for (when, result) in [
+            ("on its own", alone),
+            ("after the rejected command", following),
+        ] {
+            match result {
+                Ok(CommandResponse::Ok(ref game))
+                    if self.canonical.same(&game.game.state, &after.game.state) => {}
+                Ok(CommandResponse::Ok(_)) => problems.push(format!(
+                    "the command accepted next gave a different state {}",
+                    when
+                )),
+                Ok(_) => problems.push(format!("the command accepted next was rejected {}", when)),
+                // Transport failures say nothing about the rejection.
+                Err(_) => {}
+            }
+        }
+        for message in problems {
+            let finding = Finding::new(Category::Oracle, Severity::Critical, message)
+                .with_oracle("strict_rejection")
+                .with_artifact(
+                    "rejected",
+                    format!("player {}: {}", rejected.player, rejected.command),
+                );
+            let step = self.finding(finding, Some((player, command)));
+            self.pending.push_back(step);
+        }
+    }
+
+    // One player acting in a simultaneous round shouldn't take the turn away
+    // from those still to act in it. Players who drop out are reported and
+    // left out of the rest of the round.
+    fn check_round(&mut self, player: usize, command: &str) {
+        if self.round.is_empty() {
+            return;
+        }
+        let dropped: Vec<usize> = match self.game.as_deref().map(|g| &g.game.status) {
+            Some(brdgme_game::Status::Active { whose_turn, .. }) => self
+                .round
+                .iter()
+                .copied()
+                .filter(|p| !whose_turn.contains(p))
+                .collect(),
+            _ => {
+                self.round.clear();
+                return;
+            }
+        };
+        self.round.retain(|p| !dropped.contains(p));
+        for p in dropped {
+            let finding = Finding::new(
+                Category::Oracle,
+                Severity::Warning,
+                format!(
+                    "player {} was still to act in a simultaneous round but left whose_turn after player {} did",
+                    p, player
+                ),
+            )
+            .with_oracle("simultaneous");
+            let step = self.finding(finding, Some((player, command)));
+            self.pending.push_back(step);
+        }
+    }
+
+    // A player who's offered turn after turn but can't get a command accepted
+    // while everyone else plays on is either facing a spec that doesn't
+    // describe their legal moves or genuinely stuck by the rules. The game
+    // as a whole isn't stuck, so it never becomes a blind spot.
+    fn check_starvation(&mut self, player: usize, command: &str) {
+        let threshold = match self.starvation {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        self.starving.remove(&player);
+        let mut starved = vec![];
+        for (&p, starving) in &mut self.starving {
+            if !starving.tried {
+                continue;
+            }
+            starving.tried = false;
+            starving.states += 1;
+            if starving.states >= threshold && !starving.reported {
+                starving.reported = true;
+                starved.push((p, starving.states, starving.last.clone()));
+            }
+        }
+        starved.sort_by_key(|s| s.0);
+        for (p, states, last) in starved {
+            let mut finding = Finding::new(
+                Category::Oracle,
+                Severity::Warning,
+                format!(
+                    "player {} had every command rejected in {} states in a row while others played",
+                    p, states
+                ),
+            )
+            .with_oracle("starvation");
+            if let Some((rejected, message)) = last {
+                finding =
+                    finding.with_artifact("last_rejected", format!("{}: {}", rejected, message));
+            }
+            let step = self.finding(finding, Some((player, command)));
+            self.pending.push_back(step);
+        }
+    }
+
+    // Player tags are resolved against the names by the renderer, so an
+    // off-by-one index there only shows up once the markup is rendered.
+    fn check_player_refs(&mut self) {
+        let players = self.names.len();
+        let problems: Vec<String> = match self.game {
+            Some(ref game) => game
+                .player_renders
+                .iter()
+                .enumerate()
+                .map(|(p, r)| (format!("player {}'s render", p), &r.player_render))
+                .chain(game.logs.iter().map(|l| ("a log".to_string(), &l.content)))
+                .flat_map(|(source, render)| {
+                    markup::unresolved_players(render, players)
+                        .into_iter()
+                        .map(move |i| format!("{} refers to player {} of {}", source, i, players))
+                })
+                .collect(),
+            None => return,
+        };
+        for message in problems {
+            let step = self.violation(message);
+            self.pending.push_back(step);
+        }
+    }
+
+    fn sample_render_parse(&mut self) {
+        if self.render_sample <= 0.0 || self.rng.gen::<f64>() >= self.render_sample {
+            return;
+        }
+        let renders: Vec<String> = match self.game {
+            Some(ref game) => game
+                .player_renders
+                .iter()
+                .map(|r| r.player_render.clone())
+                .collect(),
+            None => return,
+        };
+        for (player, render) in renders.iter().enumerate() {
+            let start = Instant::now();
+            let parsed = markup::parse(render);
+            self.pending.push_back(FuzzStep::RenderParse {
+                latency: start.elapsed(),
+                bytes: render.len(),
+                ok: parsed.is_ok(),
+            });
+            if let Err(e) = parsed {
+                let step = self.violation(format!(
+                    "player {}'s render isn't valid markup: {}",
+                    player, e
+                ));
+                self.pending.push_back(step);
+            }
+        }
+    }
+
+    fn play(&mut self, player: usize, command: &str) -> Result<Play, Error> {
+        let state = match self.game {
+            Some(ref game) => game.game.state.as_str(),
+            None => bail!("there isn't a game"),
+        };
+        let start = Instant::now();
+        if let Some(ref mut session) = self.session {
+            let game = session.game_field(state);
+            match exec_command(
+                &mut self.client,
+                command.to_string(),
+                game,
+                player,
+                self.names.clone(),
+            ) {
+                Ok(response) => {
+                    if let CommandResponse::Ok(_) = response {
+                        session.accepted();
+                    }
+                    return Ok(Play {
+                        response,
+                        latency: start.elapsed(),
+                    });
+                }
+                Err(ref e) if session::lost(e) => {
+                    eprintln!("\nsession lost, falling back to stateless: {}", e);
+                    self.session = None;
+                    self.sessions_supported = Some(false);
+                }
+                // The target failed the command, which resending it
+                // statelessly wouldn't make any less of a finding.
+                Err(e) => return Err(e),
+            }
+        }
+        let start = Instant::now();
+        let response = exec_command(
+            &mut self.client,
+            command.to_string(),
+            state.to_string(),
+            player,
+            self.names.clone(),
+        )?;
+        Ok(Play {
+            response,
+            latency: start.elapsed(),
+        })
+    }
+
+    // Makes a read-only request like a spectator or the server would, and
+    // checks the response against the game it was made on. A kind of request
+    // failing the first time it's tried is taken as unsupported and not
+    // tried again on this client.
+    fn spectate(&mut self) {
+        if self.spectate <= 0.0 || self.rng.gen::<f64>() >= self.spectate {
+            return;
+        }
+        let game = match self.game {
+            Some(ref game) => game.clone(),
+            None => return,
+        };
+        let players = self.names.len();
+        let state = game.game.state.clone();
+        let (kind, request) = match self.rng.gen_range(0, 4) {
+            0 => (
+                "render",
+                api::Request::Render {
+                    player: None,
+                    game: state,
+                },
+            ),
+            1 => (
+                "player_render",
+                api::Request::Render {
+                    player: Some(self.rng.gen_range(0, players.max(1))),
+                    game: state,
+                },
+            ),
+            2 => ("status", api::Request::Status { game: state }),
+            _ => ("player_counts", api::Request::PlayerCounts),
+        };
+        if self.reads_supported.get(kind) == Some(&false) {
+            return;
+        }
+        let start = Instant::now();
+        let response = self.client.inner_mut().request(&request);
+        let latency = start.elapsed();
+        self.overhead.transport += latency;
+        let problem = match response {
+            Ok(ref response) => read_problem(&request, response, &game, players, &self.canonical),
+            Err(ref e) => Some(format!("failed: {}", e)),
+        };
+        if !*self
+            .reads_supported
+            .entry(kind)
+            .or_insert_with(|| problem.is_none())
+        {
+            return;
+        }
+        self.pending.push_back(FuzzStep::Read {
+            kind,
+            latency,
+            ok: problem.is_none(),
+        });
+        if let Some(problem) = problem {
+            let finding = Finding::new(
+                Category::Protocol,
+                Severity::Error,
+                format!("{} request {}", kind, problem),
+            );
+            let step = self.finding(finding, None);
+            self.pending.push_back(step);
+        }
+    }
+
+    fn check(&mut self, before: Option<&FuzzGame>, play: Option<(usize, &str)>, latency: Duration) {
+        let after = match self.game.as_deref() {
+            Some(game) => game,
+            None => return,
+        };
+        let start = Instant::now();
+        let findings = self.oracles.run(&StepContext {
+            game_id: self.game_id,
+            clock: &self.clock,
+            player: play.map(|(p, _)| p),
+            command: play.map(|(_, c)| c),
+            names: &self.names,
+            before,
+            after,
+            latency,
+            client: RefCell::new(self.client.inner_mut()),
+        });
+        self.overhead.checking += start.elapsed();
+        for finding in findings {
+            let step = match self.finding(finding, play) {
+                FuzzStep::Finding(finding) if finding.shadow => FuzzStep::Shadow(finding),
+                step => step,
+            };
+            self.pending.push_back(step);
+        }
+    }
+
+    fn finding(&self, mut finding: Finding, play: Option<(usize, &str)>) -> FuzzStep {
+        finding.player = play.map(|(p, _)| p);
+        finding.command = play.map(|(_, c)| c.to_string());
+        finding.seed = Some(self.seed);
+        finding.history = self.history.clone();
+        if let Some(game) = self.game.as_deref() {
+            finding.game = Some(GameMeta {
+                id: self.game_id,
+                players: self.names.len(),
+                names: self.names.clone(),
+                turn: self.clock.turn,
+                initial_state: self.initial_state.clone(),
+                checkpoints: self.checkpoints.clone(),
+                bots: match self.bot {
+                    Some((ref bot, _)) => self
+                        .bot_seats
+                        .iter()
+                        .map(|&seat| (seat, bot.name().to_string()))
+                        .collect(),
+                    None => BTreeMap::new(),
+                },
+                times: match self.wallclock {
+                    Some(_) => self.times.iter().copied().chain(self.now).collect(),
+                    None => vec![],
+                },
+            });
+            finding.artifacts.insert(
+                "game".to_string(),
+                serde_json::to_string_pretty(game).unwrap_or_else(|_| format!("{:?}", game)),
+            );
+        }
+        #[cfg(not(feature = "embedded"))]
+        {
+            let stderr = process::stderr_excerpt();
+            if !stderr.is_empty() {
+                finding
+                    .artifacts
+                    .insert("stderr".to_string(), stderr.join("\n"));
+            }
+        }
+        finding.assign_id();
+        FuzzStep::Finding(Box::new(finding))
+    }
+
+    fn violation(&self, message: String) -> FuzzStep {
+        match self.finding(
+            Finding::new(Category::Protocol, Severity::Error, message),
+            None,
+        ) {
+            FuzzStep::Finding(finding) => FuzzStep::Violation(finding),
+            step => step,
+        }
+    }
+
+    fn error(&self, error: &Error, play: Option<(usize, &str)>) -> FuzzStep {
+        self.finding(error_finding(error), play)
+    }
+}
+
+// The finding for a failed request, which replays rebuild to compare
+// signatures with.
+#[cfg(not(feature = "embedded"))]
+pub(crate) fn error_finding(error: &Error) -> Finding {
+    let (category, severity) = if error.downcast_ref::<process::LimitExceeded>().is_some() {
+        (Category::Resource, Severity::Critical)
+    } else if error.downcast_ref::<process::SandboxViolation>().is_some() {
+        (Category::Request, Severity::Security)
+    } else {
+        (Category::Request, Severity::Critical)
+    };
+    Finding::new(category, severity, error.to_string())
+}
+
+// Without subprocesses, every failed request is the game's.
+#[cfg(feature = "embedded")]
+pub(crate) fn error_finding(error: &Error) -> Finding {
+    Finding::new(Category::Request, Severity::Critical, error.to_string())
+}
+
+struct Play {
+    response: CommandResponse,
+    latency: Duration,
+}
+
+// The embedded build has no campaign reports to read most of this.
+#[cfg_attr(feature = "embedded", allow(dead_code))]
+#[derive(Debug)]
+pub(crate) enum FuzzStep {
+    Created,
+    Command {
+        head: String,
+        outcome: Outcome,
+        latency: Duration,
+        user_error: Option<UserErrorSample>,
+        // Only kept when collecting a command dictionary.
+        accepted: Option<String>,
+    },
+    Finished {
+        turns: usize,
+        reason: String,
+        players: usize,
+        offered: Vec<usize>,
+        moves: Vec<usize>,
+        placings: Option<Placings>,
+    },
+    Read {
+        kind: &'static str,
+        latency: Duration,
+        ok: bool,
+    },
+    RenderParse {
+        latency: Duration,
+        bytes: usize,
+        ok: bool,
+    },
+    // The spec choices behind the command just played, only sent when
+    // collecting spec node statistics.
+    SpecNodes {
+        choices: Option<Vec<Choice>>,
+        outcome: Outcome,
+    },
+    // The canonical hash of a state just reached, only sent when collecting
+    // coverage.
+    Reached(String),
+    // A game abandoned because no generated command was accepted.
+    BlindSpot(Box<BlindSpot>),
+    Finding(Box<Finding>),
+    // A finding that doesn't stop the campaign, counted on every occurrence
+    // but only reported once per signature.
+    Violation(Box<Finding>),
+    // A finding from an oracle in shadow mode, logged but never failing the
+    // run.
+    Shadow(Box<Finding>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Active,
+    Finished,
+}
+
+impl Fuzzer {
+    fn phase(&self) -> Phase {
+        match self.game.as_deref() {
+            None => Phase::Idle,
+            Some(FuzzGame {
+                game:
+                    api::GameResponse {
+                        status: brdgme_game::Status::Active { .. },
+                        ..
+                    },
+                ..
+            }) => Phase::Active,
+            Some(FuzzGame {
+                game:
+                    api::GameResponse {
+                        status: brdgme_game::Status::Finished { .. },
+                        ..
+                    },
+                ..
+            }) => Phase::Finished,
+        }
+    }
+
+    // Each call makes exactly one transition:
+    //
+    //   Idle -> Active | Finished  (Created)
+    //   Idle -> Idle  (a finding if creation failed)
+    //   Active -> Active | Finished  (Command, or a finding)
+    //   Active -> Idle  (a finding, the game abandoned)
+    //   Finished -> Idle  (Finished)
+    //
+    // A game that ends is only cleared by the Finished transition, so one
+    // reaching Finished by any route is always reported the same way. One
+    // that can't go on, because a request failed, nobody can move or a turn
+    // ran out of attempts, is abandoned without being reported as finished.
+    pub(crate) fn advance(&mut self) -> FuzzStep {
+        let start = Instant::now();
+        let _clock = wallclock::scoped(self.now);
+        #[cfg(not(feature = "embedded"))]
+        let theirs = process::swap_stderr(std::mem::take(&mut self.stderr));
+        let step = match self.pending.pop_front() {
+            Some(step) => step,
+            None => match self.phase() {
+                Phase::Idle => self.start_game(),
+                Phase::Active => self.step_game(),
+                Phase::Finished => self.finish_game(),
+            },
+        };
+        #[cfg(not(feature = "embedded"))]
+        {
+            self.stderr = process::swap_stderr(theirs);
+        }
+        self.overhead.total += start.elapsed();
+        step
+    }
+
+    fn start_game(&mut self) -> FuzzStep {
+        match self.new_game() {
+            Ok(latency) => {
+                self.check(None, None, latency);
+                self.reached();
+                FuzzStep::Created
+            }
+            Err(e) => self.error(&e, None),
+        }
+    }
+
+    fn finish_game(&mut self) -> FuzzStep {
+        let game = self.game.take();
+        let placings = game.as_deref().and_then(FuzzGame::placings);
+        let reason = game
+            .and_then(|game| (self.finish_reason)(&game))
+            .unwrap_or_else(|| finish::UNKNOWN.to_string());
+        self.client.stop();
+        FuzzStep::Finished {
+            turns: self.clock.turn,
+            reason,
+            players: self.names.len(),
+            offered: self.clock.offered.clone(),
+            moves: self.clock.moves.clone(),
+            placings,
+        }
+    }
+
+    fn step_game(&mut self) -> FuzzStep {
+        self.spectate();
+        let start = Instant::now();
+        let next = self.next_command();
+        self.overhead.generating += start.elapsed();
+        let (player, spec, command) = match next {
+            Ok(c) => c,
+            Err(e) => {
+                // Nobody can move, so the game is dropped and the run carries
+                // on with a new one.
+                let step = self.violation(e.to_string());
+                self.abandon_game();
+                return step;
+            }
+        };
+        let head = acceptance::command_head(&command);
+        let start = Instant::now();
+        let choices = if self.collect_spec_nodes {
+            Some(spec_nodes::attribute(&spec, &command, &self.names))
+        } else {
+            None
+        };
+        self.overhead.generating += start.elapsed();
+        if let Some((ref delay, ref mut rng)) = self.delay {
+            let pause = delay.sample(rng);
+            // There's no sleeping on wasm32, so the embedded build only
+            // counts the pause.
+            #[cfg(not(feature = "embedded"))]
+            thread::sleep(pause);
+            self.overhead.pacing += pause;
+        }
+        let step = self.play_command(player, command, head);
+        if let (Some(choices), FuzzStep::Command { outcome, .. }) = (choices, &step) {
+            self.pending.push_back(FuzzStep::SpecNodes {
+                choices,
+                outcome: *outcome,
+            });
+        }
+        step
+    }
+
+    fn reached(&mut self) {
+        if !self.collect_coverage {
+            return;
+        }
+        if let Some(ref game) = self.game {
+            let state = self.canonical.hash(&game.game.state);
+            self.pending.push_back(FuzzStep::Reached(state));
+        }
+    }
+
+    fn play_command(&mut self, player: usize, command: String, head: String) -> FuzzStep {
+        let start = Instant::now();
+        let play = self.play(player, &command);
+        self.overhead.transport += start.elapsed();
+        match play {
+            Ok(Play {
+                response: CommandResponse::Ok(game),
+                latency,
+            }) => {
+                self.clock.record(player, game.whose_turn());
+                if let Some((ref clock, ref mut rng)) = self.wallclock {
+                    let now = self.now.unwrap_or(clock.start);
+                    self.times.push(now);
+                    self.now = Some(clock.advance(now, rng));
+                    wallclock::set_virtual_now(self.now);
+                }
+                Arc::make_mut(&mut self.history).push(HistoryEntry {
+                    player,
+                    command: command.clone(),
+                });
+                let played = self.history.len();
+                if self
+                    .state_checkpoints
+                    .is_some_and(|k| played.is_multiple_of(k))
+                {
+                    Arc::make_mut(&mut self.checkpoints).insert(played, game.game.state.clone());
+                }
+                self.rejections = 0;
+                self.recent_rejections.clear();
+                self.attempts.clear();
+                self.skipped_turns.clear();
+                let before = self.game.replace(Arc::new(game));
+                if let (Some(rejected), Some(before)) = (self.strict_pending.take(), &before) {
+                    self.check_strict_rejection(rejected, player, &command, before);
+                }
+                if self.resign.as_ref().is_some_and(|r| r.command == command) {
+                    self.resigned.push(player);
+                }
+                self.offer_turns();
+                self.check_renders();
+                self.check_resignations(player, &command);
+                self.check_round(player, &command);
+                self.check_starvation(player, &command);
+                self.check(before.as_deref(), Some((player, &command)), latency);
+                self.reached();
+                FuzzStep::Command {
+                    head,
+                    outcome: Outcome::Accepted,
+                    latency,
+                    user_error: None,
+                    accepted: if self.collect_commands {
+                        Some(command)
+                    } else {
+                        None
+                    },
+                }
+            }
+            Ok(Play {
+                response: CommandResponse::UserError { message },
+                latency,
+            }) => {
+                self.check_rejection(player, &command, &message);
+                if self.starvation.is_some() {
+                    let starving = self.starving.entry(player).or_default();
+                    starving.tried = true;
+                    starving.last = Some((command.clone(), message.clone()));
+                }
+                if self.strict_pending.is_none()
+                    && self.strict_rejections > 0.0
+                    && self.rng.gen::<f64>() < self.strict_rejections
+                {
+                    self.strict_pending = Some(HistoryEntry {
+                        player,
+                        command: command.clone(),
+                    });
+                }
+                self.rejected(player, &command, &message);
+                FuzzStep::Command {
+                    head,
+                    outcome: Outcome::Rejected,
+                    latency,
+                    accepted: None,
+                    user_error: Some(UserErrorSample {
+                        state_hash: self
+                            .game
+                            .as_ref()
+                            .map(|g| self.canonical.hash(&g.game.state))
+                            .unwrap_or_default(),
+                        command,
+                        message,
+                    }),
+                }
+            }
+            Ok(Play {
+                response: CommandResponse::PartialParse,
+                latency,
+            }) => {
+                self.rejected(player, &command, "partial parse");
+                FuzzStep::Command {
+                    head,
+                    outcome: Outcome::Partial,
+                    latency,
+                    user_error: None,
+                    accepted: None,
+                }
+            }
+            Err(e) => {
+                let step = self.error(&e, Some((player, &command)));
+                self.abandon_game();
+                step
+            }
+        }
+    }
+
+    // Once too many commands in a row have been rejected the generator
+    // evidently can't find a way forward, so the game is given up on rather
+    // than retried forever.
+    fn rejected(&mut self, player: usize, command: &str, message: &str) {
+        self.rejections += 1;
+        self.recent_rejections
+            .push_back((command.to_string(), message.to_string()));
+        if self.recent_rejections.len() > 10 {
+            self.recent_rejections.pop_front();
+        }
+        self.turn_attempt(player, command, message);
+        if self.game.is_none() {
+            return;
+        }
+        if self.max_rejections.is_none_or(|max| self.rejections < max) {
+            return;
+        }
+        if let Some(game) = self.game.as_deref() {
+            let blind_spot = BlindSpot {
+                game_id: self.game_id,
+                seed: self.seed,
+                names: self.names.clone(),
+                state: game.game.state.clone(),
+                player_renders: game.player_renders.clone(),
+                rejected: self.recent_rejections.iter().cloned().collect(),
+                attempts: self.rejections,
+                player: None,
+            };
+            self.pending
+                .push_back(FuzzStep::BlindSpot(Box::new(blind_spot)));
+        }
+        self.abandon_game();
+    }
+
+    // A single hard to satisfy spec would otherwise have the generator keep
+    // trying the same turn until max_rejections, so turns are given up on
+    // sooner when turn_attempts is set.
+    fn turn_attempt(&mut self, player: usize, command: &str, message: &str) {
+        let limit = match self.turn_attempts {
+            Some(limit) => limit,
+            None => return,
+        };
+        let attempts = self.attempts.entry(player).or_default();
+        attempts.push((command.to_string(), message.to_string()));
+        if attempts.len() < limit.max {
+            return;
+        }
+        let attempts = self.attempts.remove(&player).unwrap_or_default();
+        let game = match self.game {
+            Some(ref game) => game.clone(),
+            None => return,
+        };
+        match limit.action {
+            TurnAction::Skip => {
+                self.skipped_turns.push(player);
+                self.pending
+                    .push_back(FuzzStep::BlindSpot(Box::new(BlindSpot {
+                        game_id: self.game_id,
+                        seed: self.seed,
+                        names: self.names.clone(),
+                        state: game.game.state.clone(),
+                        player_renders: game.player_renders.clone(),
+                        attempts: attempts.len(),
+                        rejected: attempts,
+                        player: Some(player),
+                    })));
+                let others = match game.game.status {
+                    brdgme_game::Status::Active { ref whose_turn, .. } => {
+                        unique_players(whose_turn).into_iter().any(|p| {
+                            !self.skipped_turns.contains(&p)
+                                && player_command_spec(&game.player_renders, p).is_ok()
+                        })
+                    }
+                    brdgme_game::Status::Finished { .. } => false,
+                };
+                if !others {
+                    self.abandon_game();
+                }
+            }
+            TurnAction::Flag => {
+                let finding = Finding::new(
+                    Category::Oracle,
+                    Severity::Warning,
+                    format!(
+                        "player {} had {} commands rejected on one turn",
+                        player,
+                        attempts.len()
+                    ),
+                )
+                .with_oracle("turn_attempts")
+                .with_artifact(
+                    "attempts",
+                    attempts
+                        .iter()
+                        .map(|(command, message)| format!("{}: {}", command, message))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                );
+                let step = self.finding(finding, Some((player, command)));
+                self.pending.push_back(step);
+                self.abandon_game();
+            }
+        }
+    }
+
+    // After a request error the game can't be trusted to make progress, so
+    // it's dropped rather than reported as finished.
+    fn abandon_game(&mut self) {
+        self.game = None;
+        self.client.stop();
+    }
+}
+
+impl Iterator for Fuzzer {
+    type Item = FuzzStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.advance())
+    }
+}
+
+// How a finished game came out, typed so reports and oracles needn't match
+// it out of the status again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Placings {
+    // Each player's place, 1 being first. Tied players share a place.
+    pub placings: Vec<usize>,
+    pub stats: Vec<HashMap<String, brdgme_game::Stat>>,
+}
+
+impl Placings {
+    // Everyone sharing the best place.
+    pub fn winners(&self) -> Vec<usize> {
+        let best = match self.placings.iter().min() {
+            Some(&best) => best,
+            None => return vec![],
+        };
+        (0..self.placings.len())
+            .filter(|&p| self.placings[p] == best)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FuzzGame {
+    pub game: api::GameResponse,
+    pub player_renders: Vec<api::PlayerRender>,
+    pub logs: Vec<api::CliLog>,
+    // Parsed on first use. A game is the `after` of one step and the `before`
+    // of the next, so each state is parsed at most once however many oracles
+    // look at it.
+    #[serde(skip)]
+    state_json: OnceLock<Result<Value, String>>,
+}
+
+impl FuzzGame {
+    pub fn new(
+        game: api::GameResponse,
+        player_renders: Vec<api::PlayerRender>,
+        logs: Vec<api::CliLog>,
+    ) -> Self {
+        FuzzGame {
+            game,
+            player_renders,
+            logs,
+            state_json: OnceLock::new(),
+        }
+    }
+
+    // Nobody once the game is finished.
+    pub fn whose_turn(&self) -> &[usize] {
+        match self.game.status {
+            brdgme_game::Status::Active { ref whose_turn, .. } => whose_turn,
+            brdgme_game::Status::Finished { .. } => &[],
+        }
+    }
+
+    pub fn placings(&self) -> Option<Placings> {
+        match self.game.status {
+            brdgme_game::Status::Finished {
+                ref placings,
+                ref stats,
+            } => Some(Placings {
+                placings: placings.clone(),
+                stats: stats.clone(),
+            }),
+            brdgme_game::Status::Active { .. } => None,
+        }
+    }
+
+    pub fn state_json(&self) -> Result<&Value, &str> {
+        self.state_json
+            .get_or_init(|| serde_json::from_str(&self.game.state).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(String::as_str)
+    }
+}
+
+// How a game took a command, see exec_command.
+#[derive(Clone, Debug)]
+pub enum CommandResponse {
+    Ok(FuzzGame),
+    UserError { message: String },
+    PartialParse,
+}
+
+pub(crate) fn render_violation(game: &FuzzGame, players: usize) -> Option<String> {
+    let renders = game.player_renders.len();
+    if renders == players {
+        return None;
+    }
+    Some(render_violation_message(renders, players))
+}
+
+pub(crate) fn render_violation_message(renders: usize, players: usize) -> String {
+    format!(
+        "returned {} player renders for {} players",
+        renders, players
+    )
+}
+
+// A player listed twice in whose_turn would be picked twice as often, so
+// it's reported and the engine picks from each player once.
+pub(crate) fn whose_turn_violation(game: &FuzzGame) -> Option<String> {
+    let whose_turn = match game.game.status {
+        brdgme_game::Status::Active { ref whose_turn, .. } => whose_turn,
+        brdgme_game::Status::Finished { .. } => return None,
+    };
+    unique_players(whose_turn).into_iter().find_map(|p| {
+        match whose_turn.iter().filter(|&&q| q == p).count() {
+            1 => None,
+            times => Some(whose_turn_violation_message(p, times)),
+        }
+    })
+}
+
+pub(crate) fn whose_turn_violation_message(player: usize, times: usize) -> String {
+    format!("whose_turn lists player {} {} times", player, times)
+}
+
+// The players in whose_turn once each, in the order they first appear.
+pub(crate) fn unique_players(whose_turn: &[usize]) -> Vec<usize> {
+    let mut unique = Vec::with_capacity(whose_turn.len());
+    for &p in whose_turn {
+        if !unique.contains(&p) {
+            unique.push(p);
+        }
+    }
+    unique
+}
+
+fn read_problem(
+    request: &api::Request,
+    response: &api::Response,
+    game: &FuzzGame,
+    players: usize,
+    canonical: &Canonicalizer,
+) -> Option<String> {
+    match (request, response) {
+        (_, api::Response::UserError { message }) | (_, api::Response::SystemError { message }) => {
+            Some(format!("failed: {}", message))
+        }
+        (
+            api::Request::Render { player: None, .. },
+            api::Response::Render {
+                render: api::Render::Pub(_),
+            },
+        ) => None,
+        (
+            api::Request::Render {
+                player: Some(player),
+                ..
+            },
+            api::Response::Render {
+                render: api::Render::Player(render),
+            },
+        ) => match game.player_renders.get(*player) {
+            Some(expected) if expected.player_render != render.player_render => Some(format!(
+                "for player {} differs from the render returned with the game",
+                player
+            )),
+            _ => None,
+        },
+        (api::Request::Status { .. }, api::Response::Status { game: status, .. })
+            if !canonical.same(&status.state, &game.game.state) =>
+        {
+            Some("returned a different state".to_string())
+        }
+        (api::Request::Status { .. }, api::Response::Status { player_renders, .. })
+            if player_renders.len() != players =>
+        {
+            Some(format!(
+                "returned {} player renders for {} players",
+                player_renders.len(),
+                players
+            ))
+        }
+        (api::Request::Status { .. }, api::Response::Status { .. }) => None,
+        (api::Request::PlayerCounts, api::Response::PlayerCounts { player_counts }) => {
+            if player_counts.contains(&players) {
+                None
+            } else {
+                Some(format!("no longer lists {} players", players))
+            }
+        }
+        _ => Some("returned a response of the wrong kind".to_string()),
+    }
+}
+
+// Whether every Player a command needs can be picked from `players` names,
+// Opt, OneOf and Many with no minimum all having a way around one.
+fn players_satisfy(spec: &command::Spec, players: usize) -> bool {
+    match *spec {
+        command::Spec::Player => players > 0,
+        command::Spec::Chain(ref specs) => specs.iter().all(|s| players_satisfy(s, players)),
+        command::Spec::OneOf(ref specs) => {
+            specs.is_empty() || specs.iter().any(|s| players_satisfy(s, players))
+        }
+        command::Spec::Many { ref spec, min, .. } => {
+            min.unwrap_or(0) == 0 || players_satisfy(spec, players)
+        }
+        command::Spec::Doc { ref spec, .. } => players_satisfy(spec, players),
+        _ => true,
+    }
+}
+
+pub(crate) fn player_command_spec(
+    player_renders: &[api::PlayerRender],
+    player: usize,
+) -> Result<command::Spec, Error> {
+    match player_renders.get(player) {
+        Some(api::PlayerRender {
+            command_spec: Some(ref command_spec),
+            ..
+        }) => Ok(command_spec.clone()),
+        Some(_) => bail!("player {}'s command_spec is None", player),
+        // The renders themselves are in the finding's game artifact.
+        None => bail!(
+            "there is no player_render for player {}, only {} were returned",
+            player,
+            player_renders.len()
+        ),
+    }
+}
+
+// Plays one command as `player` on a serialized game state against any
+// requester, outside of the fuzz loop. Anything other than a Play or
+// UserError response, including the requester failing, is an error.
+pub fn exec_command(
+    client: &mut (impl requester::Requester + ?Sized),
+    command: String,
+    game: String,
+    player: usize,
+    names: Vec<String>,
+) -> Result<CommandResponse, Error> {
+    match client.request(&api::Request::Play {
+        command,
+        game,
+        names,
+        player,
+    })? {
+        api::Response::Play {
+            ref remaining_input,
+            ..
+        } if !remaining_input.trim().is_empty() => Ok(CommandResponse::PartialParse),
+        api::Response::Play {
+            game,
+            logs,
+            player_renders,
+            ..
+        } => Ok(CommandResponse::Ok(FuzzGame::new(
+            game,
+            player_renders,
+            logs,
+        ))),
+        api::Response::UserError { message } => Ok(CommandResponse::UserError { message }),
+        v => bail!(format!("{:?}", v)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use crate::mock::{MockRequester, ToyGame};
+
+    fn fuzzer(client: impl Requester + Send + 'static) -> Fuzzer {
+        Fuzzer::new(0, 1, Box::new(client), &Config::default()).unwrap()
+    }
+
+    // Advances until the fuzzer is in `phase`, returning the step that took
+    // it there.
+    fn advance_to(fuzzer: &mut Fuzzer, phase: Phase) -> FuzzStep {
+        for _ in 0..1000 {
+            let step = fuzzer.advance();
+            if fuzzer.pending.is_empty() && fuzzer.phase() == phase {
+                return step;
+            }
+        }
+        panic!("never reached {:?}", phase);
+    }
+
+    fn finding(step: FuzzStep) -> Finding {
+        match step {
+            FuzzStep::Finding(f) => *f,
+            _ => panic!("expected a finding"),
+        }
+    }
+
+    // A toy game whose requests of `kind` fail.
+    fn failing(kind: &'static str) -> MockRequester {
+        let mut toy = ToyGame::default();
+        MockRequester::new().fallback(move |req| match (kind, req) {
+            ("new", api::Request::New { .. }) | ("play", api::Request::Play { .. }) => {
+                Err(format_err!("{} failed", kind))
+            }
+            _ => toy.request(req),
+        })
+    }
+
+    #[test]
+    fn games_go_from_idle_to_finished_and_back() {
+        let mut fuzzer = fuzzer(ToyGame::default());
+        assert_eq!(fuzzer.phase(), Phase::Idle);
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+        assert_eq!(fuzzer.phase(), Phase::Active);
+
+        // The winning command leaves the finished game in place until the
+        // next advance reports it.
+        assert!(matches!(
+            advance_to(&mut fuzzer, Phase::Finished),
+            FuzzStep::Command {
+                outcome: Outcome::Accepted,
+                ..
+            }
+        ));
+        match fuzzer.advance() {
+            FuzzStep::Finished {
+                players, placings, ..
+            } => {
+                let placings = placings.unwrap();
+                assert_eq!(placings.placings.len(), players);
+                assert_eq!(placings.winners().len(), 1);
+            }
+            _ => panic!("expected the game to finish"),
+        }
+        assert_eq!(fuzzer.phase(), Phase::Idle);
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+    }
+
+    #[test]
+    fn failed_creation_stays_idle() {
+        let mut fuzzer = fuzzer(failing("new"));
+        let finding = finding(fuzzer.advance());
+        assert_eq!(finding.category, Category::Request);
+        assert_eq!(finding.message, "new failed");
+        assert_eq!(fuzzer.phase(), Phase::Idle);
+    }
+
+    #[test]
+    fn failed_commands_abandon_the_game() {
+        let mut fuzzer = fuzzer(failing("play"));
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+        let finding = finding(advance_to(&mut fuzzer, Phase::Idle));
+        assert_eq!(finding.category, Category::Request);
+        assert!(finding.command.is_some());
+        assert!(fuzzer.game.is_none());
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+    }
+
+    // The virtual time each of a toy game's requests was made at.
+    fn clocked(seen: &Arc<Mutex<Vec<Option<u64>>>>) -> MockRequester {
+        let seen = seen.clone();
+        let mut toy = ToyGame::default();
+        MockRequester::new().fallback(move |req| {
+            seen.lock().unwrap().push(wallclock::virtual_now());
+            toy.request(req)
+        })
+    }
+
+    #[test]
+    fn fuzzers_sharing_a_thread_keep_their_own_clocks() {
+        let config = Config {
+            wallclock: Some(WallClock::default()),
+            ..Config::default()
+        };
+        let new = |seed, seen: &Arc<Mutex<Vec<Option<u64>>>>| {
+            Fuzzer::new(0, seed, Box::new(clocked(seen)), &config).unwrap()
+        };
+        let alone = Arc::new(Mutex::new(vec![]));
+        let mut fuzzer = new(1, &alone);
+        for _ in 0..100 {
+            fuzzer.advance();
+        }
+
+        let (shared, other) = (Arc::new(Mutex::new(vec![])), Arc::new(Mutex::new(vec![])));
+        let (mut fuzzer, mut neighbour) = (new(1, &shared), new(2, &other));
+        for _ in 0..100 {
+            fuzzer.advance();
+            neighbour.advance();
+            neighbour.advance();
+        }
+        assert_eq!(*alone.lock().unwrap(), *shared.lock().unwrap());
+        assert_eq!(wallclock::virtual_now(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fuzzers_sharing_a_thread_keep_their_own_stderr() {
+        use crate::backend::process::ProcessRequester;
+
+        let failing = |name: &str| {
+            let script = format!("cat >/dev/null; echo {} >&2; exit 1", name);
+            let mut game = ProcessRequester::new("sh", vec!["-c".to_string(), script], 10);
+            let client = MockRequester::new()
+                .respond(api::Response::PlayerCounts {
+                    player_counts: vec![2],
+                })
+                .fallback(move |req| game.request(req));
+            fuzzer(client)
+        };
+        let stderr = |step| finding(step).artifacts.get("stderr").cloned();
+        let (mut a, mut b) = (failing("a"), failing("b"));
+        assert_eq!(stderr(a.advance()), Some("a".to_string()));
+        assert_eq!(stderr(b.advance()), Some("b".to_string()));
+        // Each game starts with none of the last one's.
+        assert_eq!(stderr(a.advance()), Some("a".to_string()));
+    }
+
+    #[test]
+    fn findings_share_the_history() {
+        let mut toy = ToyGame::default();
+        let mut plays = 0;
+        let mut fuzzer = fuzzer(MockRequester::new().fallback(move |req| {
+            if let api::Request::Play { .. } = *req {
+                plays += 1;
+                if plays == 3 {
+                    bail!("boom");
+                }
+            }
+            toy.request(req)
+        }));
+        let finding = loop {
+            if let FuzzStep::Finding(finding) = fuzzer.advance() {
+                break finding;
+            }
+        };
+        assert_eq!(finding.message, "boom");
+        assert!(Arc::ptr_eq(&finding.history, &fuzzer.history));
+    }
+
+    #[test]
+    fn tracing_leaves_the_games_alone() {
+        // The seed of each game played.
+        let seeds = |sampler: Option<TraceSampler>| {
+            let mut fuzzer = fuzzer(ToyGame::default());
+            fuzzer.trace_sampler = sampler;
+            let mut seeds = vec![];
+            for _ in 0..200 {
+                if let FuzzStep::Created = fuzzer.advance() {
+                    seeds.push(fuzzer.seed);
+                }
+            }
+            fuzzer.client.stop();
+            seeds
+        };
+        let dir = std::env::temp_dir().join(format!("brdgme-fuzz-traces-{}", std::process::id()));
+        let traced = seeds(Some(TraceSampler {
+            config: TraceConfig {
+                dir: dir.clone(),
+                games: 3,
+                rate: 0.5,
+            },
+            budget: Arc::new(AtomicUsize::new(3)),
+        }));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(traced, seeds(None));
+    }
+
+    #[test]
+    fn turns_end_when_the_player_stops_moving() {
+        let mut clock = MoveClock::new(2);
+        clock.offer(&[0]);
+        // Two buys and done make up one turn.
+        for _ in 0..2 {
+            clock.record(0, &[0]);
+            clock.offer(&[0]);
+        }
+        assert_eq!((clock.turn, clock.streak), (0, 0));
+        clock.record(0, &[1]);
+        clock.offer(&[1]);
+        assert_eq!(
+            (clock.turn, clock.streak_player, clock.streak),
+            (1, Some(0), 1)
+        );
+        // Another player joining those to move ends the turn too.
+        clock.record(1, &[0, 1]);
+        clock.offer(&[0, 1]);
+        assert_eq!((clock.turn, clock.streak_player), (2, Some(1)));
+        clock.record(1, &[0]);
+        clock.offer(&[0]);
+        assert_eq!((clock.turn, clock.streak), (3, 2));
+        assert_eq!(clock.moves, vec![3, 2]);
+    }
+
+    #[test]
+    fn abandoned_games_are_not_finished() {
+        let mut fuzzer = fuzzer(ToyGame::default());
+        fuzzer.advance();
+        fuzzer.abandon_game();
+        assert_eq!(fuzzer.phase(), Phase::Idle);
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+    }
+}
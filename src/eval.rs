@@ -0,0 +1,276 @@
+use failure::{bail, Error};
+use rand::Rng;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use crate::backend::trace::TraceEntry;
+use crate::cancel::CancelToken;
+use crate::config::PlayerCounts;
+use crate::engine::{exec_command, player_command_spec, unique_players, CommandResponse, FuzzGame};
+use crate::finding::HistoryEntry;
+use crate::names::NameProvider;
+use crate::replay;
+use crate::strategy::{self, Bot};
+
+#[derive(Clone, Debug)]
+pub struct EvalConfig {
+    pub games: usize,
+    pub seed: u64,
+    pub player_counts: PlayerCounts,
+    // Commands a game may take before it's given up on as unfinished.
+    pub max_commands: usize,
+    // Commands rejected in a row before a game is given up on.
+    pub max_rejections: usize,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        EvalConfig {
+            games: 100,
+            seed: 0,
+            player_counts: PlayerCounts::All,
+            max_commands: 10_000,
+            max_rejections: 1000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalResult {
+    // The index of the bot that won.
+    Won(usize),
+    // Both bots shared first place.
+    Drawn,
+    Unfinished(String),
+}
+
+// One evaluation game, with what it takes to replay it.
+#[derive(Clone, Debug)]
+pub struct EvalGame {
+    pub seed: u64,
+    // Which bot played each seat.
+    pub seats: Vec<usize>,
+    pub names: Vec<String>,
+    pub initial_state: String,
+    pub history: Vec<HistoryEntry>,
+    pub result: EvalResult,
+}
+
+impl EvalGame {
+    pub fn replay(
+        &self,
+        client: &mut (impl Requester + ?Sized),
+        cancel: &CancelToken,
+    ) -> Result<Vec<TraceEntry>, Error> {
+        replay::replay(
+            client,
+            &self.initial_state,
+            &self.names,
+            &self.history,
+            cancel,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EvalReport {
+    pub bots: [String; 2],
+    pub seed: u64,
+    pub games: Vec<EvalGame>,
+}
+
+impl EvalReport {
+    pub fn wins(&self, bot: usize) -> usize {
+        self.count(|r| *r == EvalResult::Won(bot))
+    }
+
+    pub fn draws(&self) -> usize {
+        self.count(|r| *r == EvalResult::Drawn)
+    }
+
+    pub fn unfinished(&self) -> usize {
+        self.count(|r| matches!(r, EvalResult::Unfinished(_)))
+    }
+
+    fn count(&self, f: impl Fn(&EvalResult) -> bool) -> usize {
+        self.games.iter().filter(|g| f(&g.result)).count()
+    }
+
+    // The share of finished games the bot won, with a 95% Wilson score
+    // interval, which stays sensible for small samples and lopsided results.
+    pub fn win_rate(&self, bot: usize) -> (f64, f64, f64) {
+        let n = (self.games.len() - self.unfinished()) as f64;
+        if n == 0.0 {
+            return (0.0, 0.0, 1.0);
+        }
+        let p = self.wins(bot) as f64 / n;
+        let z: f64 = 1.96;
+        let denom = 1.0 + z * z / n;
+        let center = (p + z * z / (2.0 * n)) / denom;
+        let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denom;
+        (p, (center - half).max(0.0), (center + half).min(1.0))
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Evaluation: {} vs {} over {} games, seed {} ({} drawn, {} unfinished)\n",
+            self.bots[0],
+            self.bots[1],
+            self.games.len(),
+            self.seed,
+            self.draws(),
+            self.unfinished()
+        );
+        for (bot, name) in self.bots.iter().enumerate() {
+            let (rate, low, high) = self.win_rate(bot);
+            out.push_str(&format!(
+                "  {:<20} {:>6} wins  {:.1}% (95% CI {:.1}%-{:.1}%)\n",
+                name,
+                self.wins(bot),
+                rate * 100.0,
+                low * 100.0,
+                high * 100.0
+            ));
+        }
+        for (i, game) in self.games.iter().enumerate() {
+            if let EvalResult::Unfinished(ref reason) = game.result {
+                out.push_str(&format!(
+                    "  game {} (seed {}) unfinished: {}\n",
+                    i, game.seed, reason
+                ));
+            }
+        }
+        out
+    }
+}
+
+// Pits two bots against each other over seeded games, alternating which
+// takes the first seat so neither gains from going first. Game i is seeded
+// from the config's seed alone, so any one of them can be played again.
+pub fn evaluate(
+    client: &mut dyn Requester,
+    mut bots: [&mut dyn Bot; 2],
+    names: &mut dyn NameProvider,
+    config: &EvalConfig,
+    cancel: &CancelToken,
+) -> Result<EvalReport, Error> {
+    let player_counts = match client.request(&api::Request::PlayerCounts)? {
+        api::Response::PlayerCounts { player_counts } => config.player_counts.select(player_counts),
+        v => bail!("invalid response to player counts request: {:?}", v),
+    };
+    let mut report = EvalReport {
+        bots: [bots[0].name().to_string(), bots[1].name().to_string()],
+        seed: config.seed,
+        games: vec![],
+    };
+    for i in 0..config.games {
+        let seed = strategy::derive_seed(config.seed, i as u64);
+        let mut rng = strategy::seeded_rng(seed);
+        let players = match rng.choose(&player_counts) {
+            Some(&players) => players,
+            None => bail!("could not get player counts from {:?}", player_counts),
+        };
+        let seats: Vec<usize> = (0..players).map(|seat| (seat + i) % 2).collect();
+        let names = names.names(players, &mut rng);
+        for bot in bots.iter_mut() {
+            bot.new_game();
+        }
+        let mut game = match client.request(&api::Request::New { players })? {
+            api::Response::New {
+                game,
+                logs,
+                player_renders,
+                ..
+            } => FuzzGame::new(game, player_renders, logs),
+            v => bail!("invalid response for new game: {:?}", v),
+        };
+        let mut played = EvalGame {
+            seed,
+            seats,
+            names,
+            initial_state: game.game.state.clone(),
+            history: vec![],
+            result: EvalResult::Drawn,
+        };
+        played.result = play_game(
+            client,
+            &mut bots,
+            &mut game,
+            &mut played,
+            &mut rng,
+            config,
+            cancel,
+        )?;
+        report.games.push(played);
+    }
+    Ok(report)
+}
+
+fn play_game(
+    client: &mut dyn Requester,
+    bots: &mut [&mut dyn Bot; 2],
+    game: &mut FuzzGame,
+    played: &mut EvalGame,
+    rng: &mut strategy::FuzzRng,
+    config: &EvalConfig,
+    cancel: &CancelToken,
+) -> Result<EvalResult, Error> {
+    let mut rejections = 0;
+    loop {
+        cancel.check()?;
+        if let Some(placings) = game.placings() {
+            let mut winners = placings.winners().into_iter().map(|p| played.seats[p]);
+            return Ok(match winners.next() {
+                Some(bot) if winners.all(|b| b == bot) => EvalResult::Won(bot),
+                Some(_) => EvalResult::Drawn,
+                None => EvalResult::Unfinished("finished without placings".to_string()),
+            });
+        }
+        if played.history.len() >= config.max_commands {
+            return Ok(EvalResult::Unfinished(format!(
+                "no result after {} commands",
+                config.max_commands
+            )));
+        }
+        let player = match game.game.status {
+            brdgme_game::Status::Active { ref whose_turn, .. } => {
+                match rng.choose(&unique_players(whose_turn)) {
+                    Some(&player) => player,
+                    None => return Ok(EvalResult::Unfinished("whose_turn is empty".to_string())),
+                }
+            }
+            brdgme_game::Status::Finished { .. } => unreachable!(),
+        };
+        let spec = match player_command_spec(&game.player_renders, player) {
+            Ok(spec) => spec,
+            Err(e) => return Ok(EvalResult::Unfinished(e.to_string())),
+        };
+        let bot = &mut bots[played.seats[player]];
+        let command = bot.command(player, game, &spec, &played.names, rng);
+        match exec_command(
+            client,
+            command.clone(),
+            game.game.state.clone(),
+            player,
+            played.names.clone(),
+        ) {
+            Ok(CommandResponse::Ok(next)) => {
+                played.history.push(HistoryEntry { player, command });
+                *game = next;
+                rejections = 0;
+            }
+            Ok(_) => {
+                rejections += 1;
+                if rejections >= config.max_rejections {
+                    return Ok(EvalResult::Unfinished(format!(
+                        "{} had {} commands in a row rejected",
+                        bot.name(),
+                        rejections
+                    )));
+                }
+            }
+            Err(e) => return Ok(EvalResult::Unfinished(e.to_string())),
+        }
+    }
+}
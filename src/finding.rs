@@ -0,0 +1,328 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::bundle;
+use crate::engine::GameId;
+use crate::storage::{LocalStorage, Storage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+    // A sandboxed target tried to do something it was denied.
+    Security,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Request,
+    Protocol,
+    Oracle,
+    // The target hit a resource limit, see backend::process::Limits.
+    Resource,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub player: usize,
+    pub command: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameMeta {
+    pub id: GameId,
+    pub players: usize,
+    pub names: Vec<String>,
+    pub turn: usize,
+    pub initial_state: String,
+    // States after every so many commands of the history, keyed by how many
+    // had been played, see Config::state_checkpoints.
+    #[serde(default)]
+    pub checkpoints: Arc<BTreeMap<usize, String>>,
+    // Seats played by a bot rather than the strategy, with the bot's name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bots: BTreeMap<usize, String>,
+    // The virtual time each history command was played at, and last the
+    // time of the command after, see wallclock::WallClock.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub times: Vec<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub id: String,
+    pub severity: Severity,
+    pub category: Category,
+    pub signature: String,
+    pub message: String,
+    pub oracle: Option<String>,
+    pub player: Option<usize>,
+    pub command: Option<String>,
+    pub game: Option<GameMeta>,
+    pub seed: Option<u64>,
+    // Shared with the fuzzer that found it until either side changes it.
+    pub history: Arc<Vec<HistoryEntry>>,
+    pub artifacts: BTreeMap<String, String>,
+    #[serde(default)]
+    pub panic: Option<PanicSite>,
+    // The campaign that found it, see campaign::new_campaign_id.
+    #[serde(default)]
+    pub campaign_id: Option<String>,
+    // Found by an oracle in shadow mode, see OracleRegistry::set_shadow.
+    #[serde(default)]
+    pub shadow: bool,
+}
+
+// Where a target panicked, parsed out of its error message.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PanicSite {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl PanicSite {
+    // Understands both `panicked at 'MSG', FILE:LINE:COL` and the newer
+    // `panicked at FILE:LINE:COL:` followed by the message on the next line.
+    pub fn parse(text: &str) -> Option<Self> {
+        let start = text.find("panicked at ")? + "panicked at ".len();
+        let rest = &text[start..];
+        if let Some(quoted) = rest.strip_prefix('\'') {
+            return quoted.match_indices("', ").find_map(|(i, _)| {
+                let (file, line, column) = parse_location(&quoted[i + 3..])?;
+                Some(PanicSite {
+                    file,
+                    line,
+                    column,
+                    message: quoted[..i].to_string(),
+                })
+            });
+        }
+        let (location, message) = rest.split_once('\n')?;
+        let (file, line, column) = parse_location(location.trim_end().trim_end_matches(':'))?;
+        Some(PanicSite {
+            file,
+            line,
+            column,
+            message: message.lines().next().unwrap_or("").trim().to_string(),
+        })
+    }
+
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+fn parse_location(s: &str) -> Option<(String, u32, Option<u32>)> {
+    let token = s.split_whitespace().next()?.trim_end_matches([':', ',']);
+    let mut parts = token.rsplitn(3, ':');
+    let last: u32 = parts.next()?.parse().ok()?;
+    match (parts.next(), parts.next()) {
+        (Some(line), Some(file)) if line.parse::<u32>().is_ok() => {
+            Some((file.to_string(), line.parse().ok()?, Some(last)))
+        }
+        (Some(_), Some(_)) => None,
+        (Some(file), None) => Some((file.to_string(), last, None)),
+        _ => None,
+    }
+}
+
+// Masks anything that varies between occurrences of the same bug: hex
+// addresses first, then any other digits.
+fn mask_numbers(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find("0x") {
+        let hex = rest[i + 2..]
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(rest.len() - i - 2);
+        out.push_str(&rest[..i]);
+        if hex == 0 {
+            out.push_str("0x");
+        } else {
+            out.push_str("ADDR");
+        }
+        rest = &rest[i + 2 + hex..];
+    }
+    out.push_str(rest);
+    out.chars()
+        .map(|c| if c.is_ascii_digit() { 'N' } else { c })
+        .collect()
+}
+
+impl Finding {
+    pub fn new(category: Category, severity: Severity, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let mut finding = Finding {
+            id: String::new(),
+            severity,
+            category,
+            signature: String::new(),
+            message,
+            oracle: None,
+            player: None,
+            command: None,
+            game: None,
+            seed: None,
+            history: Arc::default(),
+            artifacts: BTreeMap::new(),
+            panic: None,
+            campaign_id: None,
+            shadow: false,
+        };
+        finding.panic = PanicSite::parse(&finding.message);
+        finding.sign();
+        finding
+    }
+
+    pub fn with_oracle(mut self, oracle: impl Into<String>) -> Self {
+        self.oracle = Some(oracle.into());
+        self.sign();
+        self
+    }
+
+    pub fn with_artifact(mut self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.artifacts.insert(name.into(), content.into());
+        self
+    }
+
+    // Signatures ignore numbers so the same bug at different turns or player
+    // indices collapses into one. Panics are keyed on where they happened
+    // instead, as the rest of the output varies with the state.
+    fn sign(&mut self) {
+        let message = match self.panic {
+            Some(ref panic) => format!(
+                "panic at {}: {}",
+                panic.location(),
+                mask_numbers(&panic.message)
+            ),
+            None => mask_numbers(&self.message),
+        };
+        self.signature = format!(
+            "{:?}/{}/{}",
+            self.category,
+            self.oracle.as_deref().unwrap_or("-"),
+            message
+        )
+        .to_lowercase();
+        self.assign_id();
+    }
+
+    // Accepts either a saved finding or a bundle holding one.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if path.extension().is_some_and(|e| e == "tar") {
+            return Ok(bundle::load(path)?.finding);
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, Error> {
+        self.store(&LocalStorage::new(dir)).map(PathBuf::from)
+    }
+
+    // Returns where it was saved.
+    pub fn store(&self, storage: &dyn Storage) -> Result<String, Error> {
+        let key = format!("finding-{}.json", self.id);
+        storage.put(&key, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(storage.locate(&key))
+    }
+
+    pub(crate) fn assign_id(&mut self) {
+        let mut hash = fnv1a(FNV_OFFSET, self.signature.as_bytes());
+        if let Some(seed) = self.seed {
+            hash = fnv1a(hash, &seed.to_le_bytes());
+        }
+        if let Some(ref game) = self.game {
+            hash = fnv1a(hash, &(game.turn as u64).to_le_bytes());
+        }
+        self.id = format!("{:016x}", hash);
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Error detected: {}", self.message)?;
+        writeln!(
+            f,
+            "\nFinding: {} ({:?}, {:?})",
+            self.id, self.severity, self.category
+        )?;
+        if let Some(seed) = self.seed {
+            writeln!(f, "Seed: {}", seed)?;
+        }
+        if let Some(ref panic) = self.panic {
+            writeln!(f, "Panicked at: {}", panic.location())?;
+        }
+        writeln!(
+            f,
+            "\nCommand: {}",
+            self.command.as_deref().unwrap_or("none")
+        )?;
+        match self.artifacts.get("game") {
+            Some(game) => write!(f, "\nGame: {}", game),
+            None => write!(f, "\nGame: none"),
+        }
+    }
+}
+
+pub(crate) fn state_hash(state: &str) -> String {
+    bytes_hash(state.as_bytes())
+}
+
+pub(crate) fn bytes_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(FNV_OFFSET, bytes))
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// Removes saved findings until the directory fits in max_bytes, evicting the
+// oldest duplicate of a signature before touching any unique finding.
+pub fn evict(dir: &Path, max_bytes: u64) -> Result<Vec<PathBuf>, Error> {
+    let mut saved = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_finding = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+            n.starts_with("finding-") && (n.ends_with(".json") || n.ends_with(".tar"))
+        });
+        if !is_finding {
+            continue;
+        }
+        let meta = fs::metadata(&path)?;
+        let signature = Finding::load(&path)
+            .map(|f| f.signature)
+            .unwrap_or_default();
+        saved.push((meta.modified()?, meta.len(), signature, path));
+    }
+    saved.sort();
+    let mut total: u64 = saved.iter().map(|s| s.1).sum();
+    let mut evicted = vec![];
+    while total > max_bytes && !saved.is_empty() {
+        let victim = (0..saved.len())
+            .find(|&i| saved[i + 1..].iter().any(|s| s.2 == saved[i].2))
+            .unwrap_or(0);
+        let (_, len, _, path) = saved.remove(victim);
+        fs::remove_file(&path)?;
+        total -= len;
+        evicted.push(path);
+    }
+    Ok(evicted)
+}
@@ -1,11 +1,372 @@
 extern crate brdgme_cmd;
 extern crate brdgme_fuzz;
 
-use brdgme_cmd::requester;
+#[cfg(not(feature = "embedded"))]
+use failure::{format_err, Error};
 
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::backend::{self, trace};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::bundle::{self, Environment};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::cancel::CancelToken;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::cli::{self, Command};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::daemon;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::dashboard;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::eval::{self, EvalConfig};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::redact::Redactor;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::replay::{self, Reproduction};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::report::storybook;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::share::{self, Share};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::strategy::{self, bot::StrategyBot};
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::triage::Triage;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::validate;
+#[cfg(not(feature = "embedded"))]
+use brdgme_fuzz::{Config, Finding};
+
+#[cfg(not(feature = "embedded"))]
 use std::env;
+#[cfg(not(feature = "embedded"))]
+use std::fs;
+#[cfg(not(feature = "embedded"))]
+use std::io;
+#[cfg(not(feature = "embedded"))]
+use std::path::Path;
+#[cfg(not(feature = "embedded"))]
+use std::process;
+
+#[cfg(not(feature = "embedded"))]
+fn storybook(
+    path: &Path,
+    format: storybook::Format,
+    requester_args: &[String],
+    redactor: &Redactor,
+    cancel: &CancelToken,
+) -> Result<(), Error> {
+    let entries = if path.extension().is_some_and(|e| e == "jsonl") {
+        trace::read_trace(path)?
+    } else {
+        let finding = Finding::load(path)?;
+        let mut client = backend::from_args(requester_args)?;
+        replay::replay_finding(&mut client, &finding, cancel)?
+    };
+    let entries: Vec<_> = entries.iter().map(|e| redactor.redact_trace(e)).collect();
+    print!(
+        "{}",
+        storybook::render(
+            &path.display().to_string(),
+            &storybook::chapters(&entries),
+            format
+        )
+    );
+    Ok(())
+}
+
+// Exits 1 when the finding reproduces, 0 when it doesn't and 125 when it
+// can't be told, so this can be used directly with `git bisect run`.
+#[cfg(not(feature = "embedded"))]
+fn check_repro(path: &Path, config: &Config, requester_args: &[String]) -> ! {
+    if path.extension().is_some_and(|e| e == "tar") {
+        if let Ok(bundle) = bundle::load(path) {
+            let target = requester_args
+                .iter()
+                .skip(1)
+                .map(Path::new)
+                .find(|p| p.is_file());
+            let args: Vec<String> = env::args().collect();
+            let current = Environment::capture(config, &args, target);
+            for difference in bundle.environment.differences(&current) {
+                eprintln!("warning: {}", difference);
+            }
+        }
+    }
+    let result = Finding::load(path).and_then(|finding| {
+        let mut client = backend::from_args(requester_args)?;
+        replay::check_repro(
+            &mut client,
+            &finding,
+            &mut (config.oracles)(),
+            &config.cancel,
+        )
+    });
+    let code = match result {
+        Ok(reproduction) => {
+            println!("{:?}", reproduction);
+            reproduction.exit_code()
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Reproduction::Inconclusive.exit_code()
+        }
+    };
+    process::exit(code)
+}
+
+// Replays a recorded trace and reports the first command whose state no
+// longer matches, exiting 1 if there is one.
+#[cfg(not(feature = "embedded"))]
+fn diverge(path: &Path, config: &Config, requester_args: &[String]) -> Result<bool, Error> {
+    let recorded = replay::RecordedGame::from_trace(&trace::read_trace(path)?)
+        .ok_or_else(|| format_err!("{} doesn't record a new game", path.display()))?;
+    let mut client = backend::from_args(requester_args)?;
+    Ok(
+        match replay::localize_divergence(
+            &mut client,
+            &recorded,
+            &config.canonical,
+            &config.cancel,
+        )? {
+            Some(d) => {
+                println!(
+                    "diverged at command {} of {}: {:?} by player {}",
+                    d.index,
+                    recorded.steps.len(),
+                    d.step.command,
+                    d.step.player
+                );
+                for line in &d.diff {
+                    println!("  {}", line);
+                }
+                true
+            }
+            None => {
+                println!("all {} commands replayed identically", recorded.steps.len());
+                false
+            }
+        },
+    )
+}
+
+#[cfg(not(feature = "embedded"))]
+fn triage(path: &Path, config: &Config, requester_args: &[String]) -> Result<(), Error> {
+    let finding = Finding::load(path)?;
+    let mut client = backend::from_args(requester_args)?;
+    let stdin = io::stdin();
+    Triage::new(&mut client, &finding, (config.oracles)(), &config.cancel)?
+        .run(stdin.lock(), io::stdout())
+}
+
+#[cfg(not(feature = "embedded"))]
+fn eval(
+    games: usize,
+    bots: &(String, String),
+    config: &Config,
+    requester_args: &[String],
+) -> Result<(), Error> {
+    let mut client = backend::from_args(requester_args)?;
+    let bot = |name: &str| {
+        strategy::by_name(name)
+            .map(StrategyBot)
+            .ok_or_else(|| format_err!("unknown strategy {}", name))
+    };
+    let (mut a, mut b) = (bot(&bots.0)?, bot(&bots.1)?);
+    let report = eval::evaluate(
+        &mut client,
+        [&mut a, &mut b],
+        &mut *(config.names)(),
+        &EvalConfig {
+            games,
+            seed: config.seed.unwrap_or_else(rand::random),
+            player_counts: config.player_counts,
+            max_rejections: config.max_rejections.unwrap_or(usize::MAX),
+            ..EvalConfig::default()
+        },
+        &config.cancel,
+    )?;
+    print!("{}", report.render());
+    Ok(())
+}
+
+// Target arguments are given without the program name the requester
+// expects first.
+#[cfg(not(feature = "embedded"))]
+fn daemon(
+    schedule: &Path,
+    dir: &Path,
+    keep_runs: usize,
+    config: &Config,
+    program: String,
+) -> Result<(), Error> {
+    let targets = daemon::parse_schedule(&fs::read_to_string(schedule)?)?;
+    daemon::run(
+        &targets,
+        dir,
+        config,
+        keep_runs,
+        move |args: &[String]| {
+            let mut full = vec![program.clone()];
+            full.extend_from_slice(args);
+            backend::from_args(&full).unwrap()
+        },
+    )
+}
 
+#[cfg(not(feature = "embedded"))]
 fn main() {
     let args: Vec<String> = env::args().collect();
-    brdgme_fuzz::fuzz(move || requester::parse_args(&args).unwrap());
+    let cli = cli::parse(&args).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, cli::USAGE);
+        process::exit(2);
+    });
+    let requester_args = cli.requester_args;
+    if let Command::Storybook { ref path, format } = cli.command {
+        if let Err(e) = storybook(
+            path,
+            format,
+            &requester_args,
+            &cli.config.redact,
+            &cli.config.cancel,
+        ) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::CheckRepro { ref path } = cli.command {
+        check_repro(path, &cli.config, &requester_args);
+    }
+    if let Command::Triage { ref path } = cli.command {
+        if let Err(e) = triage(path, &cli.config, &requester_args) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::Diverge { ref path } = cli.command {
+        match diverge(path, &cli.config, &requester_args) {
+            Ok(diverged) => process::exit(diverged as i32),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
+    if let Command::Export {
+        ref dir,
+        ref bundle,
+    } = cli.command
+    {
+        if let Err(e) = share::export(dir).and_then(|s| s.save(bundle)) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::Import {
+        ref bundle,
+        ref dir,
+        prefer,
+    } = cli.command
+    {
+        match Share::load(bundle).and_then(|s| s.import(dir, prefer)) {
+            Ok(imported) => print!("{}", imported.render()),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Command::Dashboard {
+        ref tenants,
+        ref listen,
+        ref feed,
+    } = cli.command
+    {
+        let served = match *feed {
+            Some((ref path, ref url)) => {
+                let atom = dashboard::render_atom(url, &dashboard::scan(tenants));
+                fs::write(path, atom).map_err(Error::from)
+            }
+            None => dashboard::serve(listen, tenants),
+        };
+        if let Err(e) = served {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::Eval { games, ref bots } = cli.command {
+        if let Err(e) = eval(games, bots, &cli.config, &requester_args) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::ValidateConfig = cli.command {
+        let validation = validate::validate(&cli.config, &requester_args);
+        print!("{}", validation.render());
+        process::exit(!validation.problems.is_empty() as i32);
+    }
+    if let Command::Daemon {
+        ref schedule,
+        ref dir,
+        keep_runs,
+    } = cli.command
+    {
+        let program = requester_args.first().cloned().unwrap_or_default();
+        if let Err(e) = daemon(schedule, dir, keep_runs, &cli.config, program) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let fail_on_regression = cli.config.fail_on_regression;
+    let new_requester = move || backend::from_args(&requester_args).unwrap();
+    let summary = match cli.command {
+        Command::Batch { seeds, report } => {
+            let batch = brdgme_fuzz::batch(new_requester, cli.config, &seeds);
+            if let Some(path) = report {
+                if let Err(e) = batch.save(&path) {
+                    eprintln!("failed to save batch report to {}: {}", path.display(), e);
+                }
+            }
+            if !batch.columns.is_empty() {
+                process::exit(1);
+            }
+            return;
+        }
+        Command::Compare { strategies, report } => {
+            let compared = match brdgme_fuzz::compare(new_requester, cli.config, &strategies) {
+                Ok(compared) => compared,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                }
+            };
+            if let Some(path) = report {
+                if let Err(e) = compared.save(&path) {
+                    eprintln!("failed to save comparison to {}: {}", path.display(), e);
+                }
+            }
+            process::exit(compared.failed(fail_on_regression) as i32);
+        }
+        Command::Pipeline { phases } => {
+            let report = brdgme_fuzz::pipeline(new_requester, cli.config, &phases);
+            process::exit(report.failed(fail_on_regression) as i32);
+        }
+        Command::Simulate => brdgme_fuzz::simulate(new_requester, cli.config),
+        _ => brdgme_fuzz::fuzz_with_config(new_requester, cli.config),
+    };
+    if summary.failed(fail_on_regression) {
+        process::exit(1);
+    }
+}
+
+// The embedded build is only the library.
+#[cfg(feature = "embedded")]
+fn main() {
+    eprintln!("brdgme-fuzz was built with the embedded feature, which has no command line");
+    std::process::exit(1);
 }
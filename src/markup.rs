@@ -0,0 +1,58 @@
+// Parses a render the way clients do. Without the markup feature only the
+// tag structure is checked, which is cheaper than a real parse but still
+// catches unterminated and nested tags.
+#[cfg(feature = "markup")]
+pub fn parse(render: &str) -> Result<(), String> {
+    brdgme_markup::from_string(render)
+        .map(|_| ())
+        .map_err(|e| format!("{:?}", e))
+}
+
+#[cfg(not(feature = "markup"))]
+pub fn parse(render: &str) -> Result<(), String> {
+    let mut rest = render;
+    while let Some(open) = rest.find("{{") {
+        let tag = &rest[open + 2..];
+        let close = tag.find("}}").ok_or_else(|| {
+            format!(
+                "unterminated tag at byte {}",
+                render.len() - rest.len() + open
+            )
+        })?;
+        if tag[..close].contains("{{") {
+            return Err(format!(
+                "nested tag at byte {}",
+                render.len() - rest.len() + open
+            ));
+        }
+        rest = &tag[close + 2..];
+    }
+    match rest.find("}}") {
+        Some(i) => Err(format!(
+            "unopened tag end at byte {}",
+            render.len() - rest.len() + i
+        )),
+        None => Ok(()),
+    }
+}
+
+// The `{{player N}}` tags in a render or log that don't resolve to one of
+// `players` names, such as an index one past the last player. Tags with
+// an index that isn't a number are returned as written.
+pub fn unresolved_players(render: &str, players: usize) -> Vec<String> {
+    let mut unresolved = vec![];
+    let mut rest = render;
+    while let Some(open) = rest.find("{{player ") {
+        let tag = &rest[open + "{{player ".len()..];
+        let close = match tag.find("}}") {
+            Some(close) => close,
+            None => break,
+        };
+        let index = tag[..close].trim();
+        if index.parse::<usize>().map_or(true, |i| i >= players) {
+            unresolved.push(index.to_string());
+        }
+        rest = &tag[close + 2..];
+    }
+    unresolved
+}
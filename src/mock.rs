@@ -0,0 +1,474 @@
+use failure::{bail, format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+use brdgme_game::command::Spec;
+use brdgme_game::Status;
+
+use std::collections::{HashMap, VecDeque};
+#[cfg(not(feature = "embedded"))]
+use std::path::PathBuf;
+#[cfg(not(feature = "embedded"))]
+use std::thread;
+#[cfg(not(feature = "embedded"))]
+use std::time::Duration;
+
+#[cfg(not(feature = "embedded"))]
+use crate::backend::process::{Limit, LimitExceeded};
+use crate::backend::session::{self, SessionRef};
+
+pub enum Scripted {
+    Respond(api::Response),
+    Fail(String),
+    #[cfg(not(feature = "embedded"))]
+    Hang(Duration),
+}
+
+type Fallback = Box<dyn FnMut(&api::Request) -> Result<api::Response, Error> + Send>;
+
+// Replays scripted responses in order, then defers to the fallback. Every
+// request is recorded so tests can assert on what the engine sent.
+#[derive(Default)]
+pub struct MockRequester {
+    script: VecDeque<Scripted>,
+    fallback: Option<Fallback>,
+    #[cfg(not(feature = "embedded"))]
+    cpu_limit: Option<Duration>,
+    pub requests: Vec<api::Request>,
+}
+
+impl MockRequester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn wrapping(inner: impl Requester + Send + 'static) -> Self {
+        let mut inner = inner;
+        Self::new().fallback(move |req| inner.request(req))
+    }
+
+    pub fn respond(mut self, response: api::Response) -> Self {
+        self.script.push_back(Scripted::Respond(response));
+        self
+    }
+
+    pub fn fail(mut self, message: impl Into<String>) -> Self {
+        self.script.push_back(Scripted::Fail(message.into()));
+        self
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    pub fn hang(mut self, duration: Duration) -> Self {
+        self.script.push_back(Scripted::Hang(duration));
+        self
+    }
+
+    // Hangs reaching the limit fail the way a game killed for spinning past
+    // its CPU limit does, see backend::process::Limits.
+    #[cfg(not(feature = "embedded"))]
+    pub fn cpu_limit(mut self, limit: Duration) -> Self {
+        self.cpu_limit = Some(limit);
+        self
+    }
+
+    pub fn fallback(
+        mut self,
+        f: impl FnMut(&api::Request) -> Result<api::Response, Error> + Send + 'static,
+    ) -> Self {
+        self.fallback = Some(Box::new(f));
+        self
+    }
+}
+
+impl Requester for MockRequester {
+    // Only hanging loops, which the embedded build can't do.
+    #[cfg_attr(feature = "embedded", allow(clippy::never_loop))]
+    fn request(&mut self, req: &api::Request) -> Result<api::Response, Error> {
+        self.requests.push(req.clone());
+        loop {
+            match self.script.pop_front() {
+                Some(Scripted::Respond(response)) => return Ok(response),
+                Some(Scripted::Fail(message)) => bail!(message),
+                #[cfg(not(feature = "embedded"))]
+                Some(Scripted::Hang(duration)) => match self.cpu_limit {
+                    Some(limit) if duration >= limit => {
+                        thread::sleep(limit);
+                        return Err(LimitExceeded {
+                            limit: Limit::Cpu,
+                            binary: PathBuf::from("mock"),
+                        }
+                        .into());
+                    }
+                    _ => thread::sleep(duration),
+                },
+                None => {
+                    return match self.fallback {
+                        Some(ref mut f) => f(req),
+                        None => bail!("mock requester has no response for {:?}", req),
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A race to a target total: players take turns to `add` 1 to 3, and whoever
+// reaches the target wins.
+#[derive(Clone, Debug)]
+pub struct ToyGame {
+    pub target: u32,
+    pub player_counts: Vec<usize>,
+    // States stored per session id, None disables session mode. See
+    // backend::session.
+    pub sessions: Option<HashMap<String, String>>,
+}
+
+impl Default for ToyGame {
+    fn default() -> Self {
+        ToyGame {
+            target: 10,
+            player_counts: vec![2, 3, 4],
+            sessions: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ToyState {
+    total: u32,
+    current: usize,
+    players: usize,
+    winner: Option<usize>,
+}
+
+impl ToyGame {
+    fn command_spec() -> Spec {
+        Spec::Chain(vec![
+            Spec::Token("add".to_string()),
+            Spec::Space,
+            Spec::Int {
+                min: Some(1),
+                max: Some(3),
+            },
+        ])
+    }
+
+    fn game_response(state: &ToyState) -> Result<api::GameResponse, Error> {
+        let status = match state.winner {
+            Some(winner) => Status::Finished {
+                placings: (0..state.players)
+                    .map(|p| if p == winner { 1 } else { 2 })
+                    .collect(),
+                stats: vec![HashMap::new(); state.players],
+            },
+            None => Status::Active {
+                whose_turn: vec![state.current],
+                eliminated: vec![],
+            },
+        };
+        Ok(api::GameResponse {
+            state: serde_json::to_string(state)?,
+            points: vec![0.0; state.players],
+            status,
+        })
+    }
+
+    fn renders(state: &ToyState) -> (api::PubRender, Vec<api::PlayerRender>) {
+        let board = format!("Total: {}", state.total);
+        let player_renders = (0..state.players)
+            .map(|p| api::PlayerRender {
+                player_render: board.clone(),
+                command_spec: if state.winner.is_none() && p == state.current {
+                    Some(Self::command_spec())
+                } else {
+                    None
+                },
+            })
+            .collect();
+        (api::PubRender { pub_render: board }, player_renders)
+    }
+
+    fn play(&self, player: usize, command: &str, game: &str) -> Result<api::Response, Error> {
+        let mut state: ToyState = serde_json::from_str(game)?;
+        if state.winner.is_some() {
+            return Ok(user_error("the game is over"));
+        }
+        if player != state.current {
+            return Ok(user_error("it's not your turn"));
+        }
+        let mut words = command.split_whitespace();
+        let amount = match (words.next(), words.next().map(str::parse::<u32>)) {
+            (Some("add"), Some(Ok(n))) if (1..=3).contains(&n) => n,
+            _ => return Ok(user_error("expected add 1-3")),
+        };
+        state.total += amount;
+        if state.total >= self.target {
+            state.winner = Some(player);
+        } else {
+            state.current = (state.current + 1) % state.players;
+        }
+        let (public_render, player_renders) = Self::renders(&state);
+        Ok(api::Response::Play {
+            game: Self::game_response(&state)?,
+            logs: vec![],
+            can_undo: false,
+            remaining_input: words.collect::<Vec<&str>>().join(" "),
+            public_render,
+            player_renders,
+        })
+    }
+}
+
+impl Requester for ToyGame {
+    fn request(&mut self, req: &api::Request) -> Result<api::Response, Error> {
+        match *req {
+            api::Request::PlayerCounts => Ok(api::Response::PlayerCounts {
+                player_counts: self.player_counts.clone(),
+            }),
+            api::Request::New { players } => {
+                let state = ToyState {
+                    total: 0,
+                    current: 0,
+                    players,
+                    winner: None,
+                };
+                let (public_render, player_renders) = Self::renders(&state);
+                Ok(api::Response::New {
+                    game: Self::game_response(&state)?,
+                    logs: vec![],
+                    public_render,
+                    player_renders,
+                })
+            }
+            api::Request::Status { ref game } => {
+                let game = match (session::parse(game), &self.sessions) {
+                    (Some(SessionRef::Resume { id }), Some(sessions)) => sessions
+                        .get(id)
+                        .ok_or_else(|| format_err!("no session {}", id))?,
+                    _ => game,
+                };
+                let state: ToyState = serde_json::from_str(game)?;
+                let (public_render, player_renders) = Self::renders(&state);
+                Ok(api::Response::Status {
+                    game: Self::game_response(&state)?,
+                    public_render,
+                    player_renders,
+                })
+            }
+            api::Request::Render { ref game, .. }
+                if self.sessions.is_some() && game == session::PROBE =>
+            {
+                Ok(api::Response::Render {
+                    render: api::Render::Pub(api::PubRender {
+                        pub_render: String::new(),
+                    }),
+                })
+            }
+            api::Request::Play {
+                player,
+                ref command,
+                ref game,
+                ..
+            } => match (session::parse(game), self.sessions.is_some()) {
+                (Some(session_ref), true) => {
+                    let (id, state) = match session_ref {
+                        SessionRef::Resume { id } => (
+                            id,
+                            self.sessions
+                                .as_ref()
+                                .and_then(|s| s.get(id))
+                                .cloned()
+                                .ok_or_else(|| format_err!("no session {}", id))?,
+                        ),
+                        SessionRef::Checkpoint { id, state } => (id, state.to_string()),
+                    };
+                    let res = self.play(player, command, &state)?;
+                    // Partial parses aren't stored, see backend::session.
+                    if let (
+                        api::Response::Play {
+                            ref game,
+                            ref remaining_input,
+                            ..
+                        },
+                        Some(ref mut sessions),
+                    ) = (&res, &mut self.sessions)
+                    {
+                        if remaining_input.trim().is_empty() {
+                            sessions.insert(id.to_string(), game.state.clone());
+                        }
+                    }
+                    Ok(res)
+                }
+                _ => self.play(player, command, game),
+            },
+            ref other => Err(format_err!("toy game doesn't support {:?}", other)),
+        }
+    }
+}
+
+fn user_error(message: &str) -> api::Response {
+    api::Response::UserError {
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::engine::{FuzzStep, Fuzzer};
+    use crate::finding::{Category, Finding, Severity};
+
+    // Scripts go first, so the player counts request the fuzzer starts with
+    // is answered before the scripted failure.
+    fn scripted(script: impl FnOnce(MockRequester) -> MockRequester) -> Fuzzer {
+        let client = script(MockRequester::new().respond(api::Response::PlayerCounts {
+            player_counts: vec![2],
+        }))
+        .fallback({
+            let mut toy = ToyGame::default();
+            move |req| toy.request(req)
+        });
+        Fuzzer::new(0, 1, Box::new(client), &Config::default()).unwrap()
+    }
+
+    fn finding(step: FuzzStep) -> Finding {
+        match step {
+            FuzzStep::Finding(f) => *f,
+            other => panic!("expected a finding, got {:?}", other),
+        }
+    }
+
+    fn sessions() -> ToyGame {
+        ToyGame {
+            sessions: Some(HashMap::new()),
+            ..ToyGame::default()
+        }
+    }
+
+    fn new_state(toy: &mut ToyGame) -> String {
+        match toy.request(&api::Request::New { players: 2 }).unwrap() {
+            api::Response::New { game, .. } => game.state,
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    fn play(toy: &mut ToyGame, player: usize, command: &str, game: String) -> api::Response {
+        toy.request(&api::Request::Play {
+            player,
+            command: command.to_string(),
+            game,
+            names: vec!["a".to_string(), "b".to_string()],
+        })
+        .unwrap()
+    }
+
+    fn stored(toy: &ToyGame, id: &str) -> Option<ToyState> {
+        toy.sessions
+            .as_ref()
+            .unwrap()
+            .get(id)
+            .map(|s| serde_json::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn failures_are_request_findings() {
+        let mut fuzzer = scripted(|m| m.fail("boom"));
+        let finding = finding(fuzzer.advance());
+        assert_eq!(finding.category, Category::Request);
+        assert_eq!(finding.severity, Severity::Critical);
+        assert_eq!(finding.message, "boom");
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    #[test]
+    fn hangs_past_the_cpu_limit_are_resource_findings() {
+        let mut fuzzer = scripted(|m| {
+            m.cpu_limit(Duration::from_millis(10))
+                .hang(Duration::from_secs(60))
+        });
+        let finding = finding(fuzzer.advance());
+        assert_eq!(finding.category, Category::Resource);
+        assert_eq!(finding.severity, Severity::Critical);
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    #[test]
+    fn hangs_within_the_cpu_limit_only_delay() {
+        let mut fuzzer = scripted(|m| {
+            m.cpu_limit(Duration::from_secs(60))
+                .hang(Duration::from_millis(10))
+        });
+        assert!(matches!(fuzzer.advance(), FuzzStep::Created));
+    }
+
+    #[test]
+    fn sessions_store_accepted_plays() {
+        let mut toy = sessions();
+        let state = new_state(&mut toy);
+        play(
+            &mut toy,
+            0,
+            "add 2",
+            format!("{}s\n{}", session::PREFIX, state),
+        );
+        assert_eq!(stored(&toy, "s").unwrap().total, 2);
+        play(&mut toy, 1, "add 3", format!("{}s", session::PREFIX));
+        assert_eq!(stored(&toy, "s").unwrap().total, 5);
+        match toy
+            .request(&api::Request::Status {
+                game: format!("{}s", session::PREFIX),
+            })
+            .unwrap()
+        {
+            api::Response::Status { game, .. } => {
+                let state: ToyState = serde_json::from_str(&game.state).unwrap();
+                assert_eq!(state.total, 5);
+            }
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sessions_ignore_rejected_and_partial_plays() {
+        let mut toy = sessions();
+        let state = new_state(&mut toy);
+        play(
+            &mut toy,
+            0,
+            "add 1",
+            format!("{}s\n{}", session::PREFIX, state),
+        );
+        assert!(matches!(
+            play(&mut toy, 0, "add 1", format!("{}s", session::PREFIX)),
+            api::Response::UserError { .. }
+        ));
+        match play(&mut toy, 1, "add 1 more", format!("{}s", session::PREFIX)) {
+            api::Response::Play {
+                remaining_input, ..
+            } => assert_eq!(remaining_input, "more"),
+            other => panic!("unexpected response {:?}", other),
+        }
+        let stored = stored(&toy, "s").unwrap();
+        assert_eq!(stored.total, 1);
+        assert_eq!(stored.current, 1);
+    }
+
+    #[test]
+    fn unknown_sessions_are_lost() {
+        let mut toy = sessions();
+        let err = toy
+            .request(&api::Request::Play {
+                player: 0,
+                command: "add 1".to_string(),
+                game: format!("{}missing", session::PREFIX),
+                names: vec![],
+            })
+            .unwrap_err();
+        assert!(session::lost(&err));
+        assert!(session::negotiate(&mut toy));
+        assert!(!session::negotiate(&mut ToyGame::default()));
+    }
+}
@@ -0,0 +1,102 @@
+use failure::{bail, Error};
+use rand::Rng;
+
+use crate::strategy::FuzzRng;
+
+// Chooses player names for each new game. Names end up in commands that
+// mention other players, so lists built to confuse a game's parser, such as
+// names sharing a prefix or spelling out a command, catch bugs the default
+// names never will. The names used are stored with each finding since a
+// reproduction depends on them.
+pub trait NameProvider: Send {
+    fn name(&self) -> &str;
+    fn names(&mut self, players: usize, rng: &mut FuzzRng) -> Vec<String>;
+}
+
+pub struct DefaultNames;
+
+impl NameProvider for DefaultNames {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    fn names(&mut self, players: usize, _rng: &mut FuzzRng) -> Vec<String> {
+        (0..players).map(|p| format!("player{}", p)).collect()
+    }
+}
+
+// Picks distinct names from a list at random, numbering repeats once the
+// list runs out.
+pub struct ListNames {
+    label: String,
+    list: Vec<String>,
+}
+
+impl ListNames {
+    pub fn new(label: impl Into<String>, list: Vec<String>) -> Self {
+        ListNames {
+            label: label.into(),
+            list,
+        }
+    }
+}
+
+impl NameProvider for ListNames {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    fn names(&mut self, players: usize, rng: &mut FuzzRng) -> Vec<String> {
+        let mut pool = self.list.clone();
+        rng.shuffle(&mut pool);
+        let len = pool.len();
+        (0..players)
+            .map(|p| match len {
+                0 => format!("player{}", p),
+                _ if p < len => pool[p].clone(),
+                _ => format!("{}{}", pool[p % len], p / len),
+            })
+            .collect()
+    }
+}
+
+fn list(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+// Names where one is a prefix of another, for parsers that match players by
+// the shortest unambiguous prefix.
+pub fn prefix() -> ListNames {
+    ListNames::new(
+        "prefix",
+        list(&["al", "ali", "alice", "alicia", "bo", "bob", "bobby"]),
+    )
+}
+
+// Names that read as common command words.
+pub fn commands() -> ListNames {
+    ListNames::new(
+        "commands",
+        list(&["pass", "play", "take", "done", "yes", "no", "all", "1"]),
+    )
+}
+
+// `default`, `prefix`, `commands` or a comma separated list of names.
+pub fn by_name(spec: &str) -> Result<Box<dyn NameProvider>, Error> {
+    Ok(match spec {
+        "default" => Box::new(DefaultNames),
+        "prefix" => Box::new(prefix()),
+        "commands" => Box::new(commands()),
+        _ => {
+            let names: Vec<String> = spec
+                .split(',')
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty())
+                .collect();
+            if names.is_empty() {
+                bail!("no names in {:?}", spec);
+            }
+            Box::new(ListNames::new(spec, names))
+        }
+    })
+}
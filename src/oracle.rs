@@ -0,0 +1,354 @@
+use serde_json::Value;
+
+use brdgme_game::Status;
+
+use std::collections::BTreeMap;
+
+use crate::engine::{GameId, StepContext};
+use crate::finding::{Category, Finding, Severity};
+
+pub mod conservation;
+pub mod magnitude;
+pub mod numeric;
+#[cfg(not(feature = "embedded"))]
+pub mod race;
+pub mod robustness;
+pub mod volume;
+
+pub use self::conservation::{ConservationOracle, ConservationRule};
+pub use self::magnitude::MagnitudeOracle;
+pub use self::numeric::NumericFormatOracle;
+#[cfg(not(feature = "embedded"))]
+pub use self::race::RaceOracle;
+pub use self::robustness::RobustnessOracle;
+pub use self::volume::VolumeOracle;
+
+pub trait Oracle: Send {
+    fn name(&self) -> &str;
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding>;
+
+    fn finding(&self, message: String) -> Finding {
+        Finding::new(Category::Oracle, Severity::Error, message).with_oracle(self.name())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OracleStats {
+    pub checks: usize,
+    pub findings: usize,
+}
+
+struct Entry {
+    oracle: Box<dyn Oracle>,
+    enabled: bool,
+    // Findings are marked as shadow findings, which are logged but never
+    // fail the run, for vetting a new oracle before enforcing it.
+    shadow: bool,
+    stats: OracleStats,
+}
+
+#[derive(Default)]
+pub struct OracleRegistry {
+    entries: Vec<Entry>,
+}
+
+impl OracleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(RendersOracle);
+        registry.register(PointsOracle);
+        registry.register(WhoseTurnOracle);
+        registry.register(SerializationOracle);
+        registry
+    }
+
+    pub fn register(&mut self, oracle: impl Oracle + 'static) -> &mut Self {
+        self.entries.push(Entry {
+            oracle: Box::new(oracle),
+            enabled: true,
+            shadow: false,
+            stats: OracleStats::default(),
+        });
+        self
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> &mut Self {
+        for e in self.entries.iter_mut().filter(|e| e.oracle.name() == name) {
+            e.enabled = enabled;
+        }
+        self
+    }
+
+    pub fn set_shadow(&mut self, name: &str, shadow: bool) -> &mut Self {
+        for e in self.entries.iter_mut().filter(|e| e.oracle.name() == name) {
+            e.shadow = shadow;
+        }
+        self
+    }
+
+    pub fn is_shadow(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.shadow && e.oracle.name() == name)
+    }
+
+    pub fn run(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let mut findings = vec![];
+        for e in self.entries.iter_mut().filter(|e| e.enabled) {
+            let mut found = e.oracle.check(ctx);
+            e.stats.checks += 1;
+            e.stats.findings += found.len();
+            for f in &mut found {
+                f.shadow = e.shadow;
+            }
+            findings.extend(found);
+        }
+        findings
+    }
+
+    pub fn stats(&self) -> impl Iterator<Item = (&str, bool, &OracleStats)> {
+        self.entries
+            .iter()
+            .map(|e| (e.oracle.name(), e.enabled, &e.stats))
+    }
+}
+
+pub struct RendersOracle;
+
+impl Oracle for RendersOracle {
+    fn name(&self) -> &str {
+        "renders"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        // A player_renders of the wrong length is counted by the engine as a
+        // protocol violation instead, so it doesn't end the run.
+        let player_renders = &ctx.after.player_renders;
+        match ctx.after.game.status {
+            Status::Active { ref whose_turn, .. } => whose_turn
+                .iter()
+                .filter(|&&p| p < player_renders.len() && player_renders[p].command_spec.is_none())
+                .map(|p| self.finding(format!("active player {}'s command_spec is None", p)))
+                .collect(),
+            Status::Finished { .. } => vec![],
+        }
+    }
+}
+
+pub struct PointsOracle;
+
+impl Oracle for PointsOracle {
+    fn name(&self) -> &str {
+        "points"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let points = &ctx.after.game.points;
+        let mut findings = vec![];
+        if !points.is_empty() && points.len() != ctx.names.len() {
+            findings.push(self.finding(format!(
+                "expected points for {} players, got {:?}",
+                ctx.names.len(),
+                points
+            )));
+        }
+        if points.iter().any(|p| !p.is_finite()) {
+            findings.push(self.finding(format!("non-finite points: {:?}", points)));
+        }
+        findings
+    }
+}
+
+pub struct WhoseTurnOracle;
+
+impl Oracle for WhoseTurnOracle {
+    fn name(&self) -> &str {
+        "whose_turn"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let players = ctx.names.len();
+        match ctx.after.game.status {
+            Status::Active { ref whose_turn, .. } if whose_turn.is_empty() => {
+                vec![self.finding("game is active but whose_turn is empty".to_string())]
+            }
+            Status::Active { ref whose_turn, .. } => whose_turn
+                .iter()
+                .filter(|&&p| p >= players)
+                .map(|p| {
+                    self.finding(format!(
+                        "whose_turn contains player {} but there are only {} players",
+                        p, players
+                    ))
+                })
+                .collect(),
+            Status::Finished { ref placings, .. } if placings.len() != players => {
+                vec![self.finding(format!(
+                    "expected placings for {} players, got {:?}",
+                    players, placings
+                ))]
+            }
+            Status::Finished { .. } => vec![],
+        }
+    }
+}
+
+pub struct SerializationOracle;
+
+impl Oracle for SerializationOracle {
+    fn name(&self) -> &str {
+        "serialization"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        match ctx.after.state_json() {
+            Ok(_) => vec![],
+            Err(e) => vec![self.finding(format!("state is not valid JSON: {}", e))],
+        }
+    }
+}
+
+// Flags a player taking more than `max_consecutive` turns in a row. Turns
+// are counted by the move clock, so several commands in one turn don't
+// count against anybody.
+pub struct TurnStreakOracle {
+    pub max_consecutive: usize,
+    // The game and turn last reported, as the streak stays over the limit
+    // for every command until the next turn ends.
+    reported: Option<(GameId, usize)>,
+}
+
+impl TurnStreakOracle {
+    pub fn new(max_consecutive: usize) -> Self {
+        TurnStreakOracle {
+            max_consecutive,
+            reported: None,
+        }
+    }
+}
+
+impl Oracle for TurnStreakOracle {
+    fn name(&self) -> &str {
+        "turn_streak"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let turn = (ctx.game_id, ctx.clock.turn);
+        match ctx.clock.streak_player {
+            Some(player)
+                if ctx.clock.streak == self.max_consecutive + 1 && self.reported != Some(turn) =>
+            {
+                self.reported = Some(turn);
+                vec![self.finding(format!(
+                    "player {} has taken {} consecutive turns",
+                    player, ctx.clock.streak
+                ))]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// Flattens every number in a JSON document into a map keyed by JSON pointer.
+pub fn numeric_leaves(value: &Value) -> BTreeMap<String, f64> {
+    fn walk(value: &Value, path: &mut String, out: &mut BTreeMap<String, f64>) {
+        match *value {
+            Value::Number(ref n) => {
+                if let Some(n) = n.as_f64() {
+                    out.insert(path.clone(), n);
+                }
+            }
+            Value::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("/{}", i));
+                    walk(item, path, out);
+                    path.truncate(len);
+                }
+            }
+            Value::Object(ref map) => {
+                for (k, item) in map {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&k.replace('~', "~0").replace('/', "~1"));
+                    walk(item, path, out);
+                    path.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut out = BTreeMap::new();
+    walk(value, &mut String::new(), &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use brdgme_cmd::api;
+
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use crate::engine::{FuzzGame, MoveClock};
+    use crate::mock::ToyGame;
+
+    fn streak_findings(oracle: &mut TurnStreakOracle, clock: &MoveClock) -> usize {
+        let after = FuzzGame::new(
+            api::GameResponse {
+                state: "{}".to_string(),
+                points: vec![0.0; 2],
+                status: Status::Active {
+                    whose_turn: clock.to_move.clone(),
+                    eliminated: vec![],
+                },
+            },
+            vec![],
+            vec![],
+        );
+        let mut client = ToyGame::default();
+        oracle
+            .check(&StepContext {
+                game_id: GameId { worker: 0, game: 0 },
+                clock,
+                player: clock.streak_player,
+                command: Some("buy"),
+                names: &[],
+                before: None,
+                after: &after,
+                latency: Duration::default(),
+                client: RefCell::new(&mut client),
+            })
+            .len()
+    }
+
+    #[test]
+    fn streaks_count_turns_not_commands() {
+        let mut oracle = TurnStreakOracle::new(2);
+        let mut clock = MoveClock::new(2);
+        clock.offer(&[0]);
+        let mut found = 0;
+        // Player 0 keeps the turn for three turns of three commands each.
+        for turn in 0..3 {
+            for command in 0..3 {
+                let last = turn < 2 && command == 2;
+                let whose_turn: &[usize] = if last { &[0, 1] } else { &[0] };
+                clock.record(0, whose_turn);
+                clock.offer(whose_turn);
+                found += streak_findings(&mut oracle, &clock);
+                if last {
+                    // Back to player 0 alone for their next turn.
+                    clock.offer(&[0]);
+                }
+            }
+        }
+        assert_eq!(clock.streak, 2);
+        assert_eq!(found, 0);
+    }
+}
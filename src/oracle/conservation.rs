@@ -0,0 +1,122 @@
+use failure::{bail, Error};
+use serde_json::Value;
+
+use std::str::FromStr;
+
+use super::Oracle;
+use crate::engine::StepContext;
+use crate::finding::Finding;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Constant,
+    NonDecreasing,
+    NonIncreasing,
+}
+
+// A quantity summed over state paths, such as every card in the deck, the
+// discard pile and all hands. Paths are JSON pointers where a `*` segment
+// matches every key or index, numbers count as their value and arrays and
+// objects as their length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConservationRule {
+    pub paths: Vec<String>,
+    pub direction: Direction,
+}
+
+impl FromStr for ConservationRule {
+    type Err = Error;
+
+    // `PATHS`, `up:PATHS` or `down:PATHS`, paths separated by commas.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (direction, paths) = match s.split_once(':') {
+            Some(("up", paths)) => (Direction::NonDecreasing, paths),
+            Some(("down", paths)) => (Direction::NonIncreasing, paths),
+            _ => (Direction::Constant, s),
+        };
+        let paths: Vec<String> = paths.split(',').map(|p| p.trim().to_string()).collect();
+        if let Some(p) = paths.iter().find(|p| !p.is_empty() && !p.starts_with('/')) {
+            bail!("{} isn't a JSON pointer", p);
+        }
+        Ok(ConservationRule {
+            paths: paths.into_iter().filter(|p| !p.is_empty()).collect(),
+            direction,
+        })
+    }
+}
+
+impl ConservationRule {
+    pub fn total(&self, state: &Value) -> f64 {
+        self.paths
+            .iter()
+            .map(|p| {
+                let segments: Vec<&str> = p.split('/').skip(1).collect();
+                sum(state, &segments)
+            })
+            .sum()
+    }
+}
+
+fn sum(value: &Value, segments: &[&str]) -> f64 {
+    let (segment, rest) = match segments.split_first() {
+        Some(s) => s,
+        None => {
+            return match value {
+                Value::Number(n) => n.as_f64().unwrap_or(0.0),
+                Value::Array(items) => items.len() as f64,
+                Value::Object(map) => map.len() as f64,
+                _ => 0.0,
+            }
+        }
+    };
+    match (value, *segment) {
+        (Value::Array(items), "*") => items.iter().map(|v| sum(v, rest)).sum(),
+        (Value::Object(map), "*") => map.values().map(|v| sum(v, rest)).sum(),
+        (Value::Array(items), i) => i
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| items.get(i))
+            .map_or(0.0, |v| sum(v, rest)),
+        (Value::Object(map), key) => {
+            let key = key.replace("~1", "/").replace("~0", "~");
+            map.get(&key).map_or(0.0, |v| sum(v, rest))
+        }
+        _ => 0.0,
+    }
+}
+
+pub struct ConservationOracle {
+    pub rule: ConservationRule,
+}
+
+impl ConservationOracle {
+    pub fn new(rule: ConservationRule) -> Self {
+        ConservationOracle { rule }
+    }
+}
+
+impl Oracle for ConservationOracle {
+    fn name(&self) -> &str {
+        "conservation"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let (before, after) = match (ctx.before.map(|b| b.state_json()), ctx.after.state_json()) {
+            (Some(Ok(b)), Ok(a)) => (self.rule.total(b), self.rule.total(a)),
+            _ => return vec![],
+        };
+        let change = match self.rule.direction {
+            Direction::Constant if (after - before).abs() > f64::EPSILON => "changed",
+            Direction::NonDecreasing if after < before => "decreased",
+            Direction::NonIncreasing if after > before => "increased",
+            _ => return vec![],
+        };
+        vec![self.finding(format!(
+            "total over {} {} from {} to {}",
+            self.rule.paths.join(", "),
+            change,
+            before,
+            after
+        ))]
+    }
+}
@@ -0,0 +1,44 @@
+use super::{numeric_leaves, Oracle};
+use crate::engine::StepContext;
+use crate::finding::Finding;
+
+pub struct MagnitudeOracle {
+    pub threshold: f64,
+}
+
+impl MagnitudeOracle {
+    pub fn new(threshold: f64) -> Self {
+        MagnitudeOracle { threshold }
+    }
+}
+
+impl Oracle for MagnitudeOracle {
+    fn name(&self) -> &str {
+        "magnitude"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let before = match ctx.before {
+            Some(before) => before,
+            None => return vec![],
+        };
+        let (before, after) = match (before.state_json(), ctx.after.state_json()) {
+            (Ok(b), Ok(a)) => (numeric_leaves(b), numeric_leaves(a)),
+            _ => return vec![],
+        };
+        after
+            .iter()
+            .filter_map(|(path, &a)| {
+                let b = *before.get(path)?;
+                if (a - b).abs() > self.threshold {
+                    Some(self.finding(format!(
+                        "{} jumped from {} to {} in one command",
+                        path, b, a
+                    )))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
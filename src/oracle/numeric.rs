@@ -0,0 +1,75 @@
+use super::Oracle;
+use crate::engine::StepContext;
+use crate::finding::Finding;
+
+// Looks for float maths leaking into what players see: NaN, infinities,
+// scientific notation, negative zero and binary rounding artifacts such as
+// 0.30000000000000004.
+pub struct NumericFormatOracle;
+
+// What's wrong with a token, if anything.
+pub fn problem(token: &str) -> Option<&'static str> {
+    let token = token.trim_end_matches('.');
+    if token == "NaN" {
+        return Some("NaN");
+    }
+    if token == "inf" || token == "-inf" || token == "+inf" {
+        return Some("an infinity");
+    }
+    let digits = token.trim_start_matches(['-', '+']);
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) || token.parse::<f64>().is_err() {
+        return None;
+    }
+    if digits.contains(['e', 'E']) {
+        return Some("scientific notation");
+    }
+    if token.starts_with('-') && digits.chars().all(|c| c == '0' || c == '.') {
+        return Some("negative zero");
+    }
+    match digits.split_once('.') {
+        Some((_, fraction))
+            if fraction.len() >= 10
+                && (fraction.contains("000000") || fraction.contains("999999")) =>
+        {
+            Some("a float rounding artifact")
+        }
+        _ => None,
+    }
+}
+
+// The first badly formatted number in some text, with what's wrong with it.
+pub fn scan(text: &str) -> Option<(&'static str, &str)> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+'))
+        .find_map(|token| problem(token).map(|p| (p, token)))
+}
+
+impl Oracle for NumericFormatOracle {
+    fn name(&self) -> &str {
+        "numeric_format"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let renders = ctx
+            .after
+            .player_renders
+            .iter()
+            .enumerate()
+            .map(|(p, r)| (format!("player {}'s render", p), &r.player_render));
+        let logs = ctx
+            .logs()
+            .iter()
+            .map(|l| ("a log entry".to_string(), &l.content));
+        renders
+            .chain(logs)
+            .filter_map(|(place, text)| {
+                let (problem, token) = scan(text)?;
+                // The token itself is kept out of the message so every
+                // artifact of the same kind shares a signature.
+                Some(
+                    self.finding(format!("{} shows {}", place, problem))
+                        .with_artifact("token", token),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,113 @@
+use failure::Error;
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use std::sync::Barrier;
+use std::thread;
+
+use super::Oracle;
+use crate::engine::StepContext;
+use crate::finding::Finding;
+
+pub type NewClient = Box<dyn Fn() -> Box<dyn Requester + Send> + Send>;
+
+/// Submits the same Play twice at once against the pre-state of an accepted
+/// command, using two extra clients pointed at the same backend. Only useful
+/// for backends that serve requests concurrently, such as a game behind HTTP.
+pub struct RaceOracle {
+    pub every: usize,
+    new_client: NewClient,
+    clients: Option<[Box<dyn Requester + Send>; 2]>,
+    seen: usize,
+}
+
+impl RaceOracle {
+    pub fn new(every: usize, new_client: NewClient) -> Self {
+        RaceOracle {
+            every: every.max(1),
+            new_client,
+            clients: None,
+            seen: 0,
+        }
+    }
+
+    fn race(&mut self, req: &api::Request) -> Vec<Result<api::Response, String>> {
+        let new_client = &self.new_client;
+        let clients = self
+            .clients
+            .get_or_insert_with(|| [new_client(), new_client()]);
+        let barrier = Barrier::new(clients.len());
+        thread::scope(|s| {
+            let handles = clients
+                .iter_mut()
+                .map(|client| {
+                    let barrier = &barrier;
+                    s.spawn(move || {
+                        barrier.wait();
+                        client.request(req)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| match h.join() {
+                    Ok(res) => res.map_err(|e: Error| e.to_string()),
+                    Err(_) => Err("client panicked".to_string()),
+                })
+                .collect()
+        })
+    }
+}
+
+impl Oracle for RaceOracle {
+    fn name(&self) -> &str {
+        "race"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let (before, player, command) = match (ctx.before, ctx.player, ctx.command) {
+            (Some(before), Some(player), Some(command)) => (before, player, command),
+            _ => return vec![],
+        };
+        self.seen = (self.seen + 1) % self.every;
+        if self.seen != 0 {
+            return vec![];
+        }
+        let responses = self.race(&api::Request::Play {
+            player,
+            command: command.to_string(),
+            game: before.game.state.clone(),
+            names: ctx.names.to_vec(),
+        });
+        let problems = responses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, res)| match res {
+                Ok(api::Response::Play { .. }) | Ok(api::Response::UserError { .. }) => None,
+                Ok(api::Response::SystemError { message }) => {
+                    Some(format!("submission {} got a system error: {}", i, message))
+                }
+                Ok(v) => Some(format!(
+                    "submission {} got an unexpected response: {:?}",
+                    i, v
+                )),
+                Err(e) => Some(format!("submission {} failed: {}", i, e)),
+            })
+            .collect::<Vec<_>>();
+        if problems.is_empty() {
+            return vec![];
+        }
+        // A client that saw a broken response may be in a bad state, start
+        // the next race with fresh ones.
+        self.clients = None;
+        let coherent = responses.len() - problems.len();
+        vec![self.finding(format!(
+            "concurrent submissions of {:?} by player {} produced {} coherent outcome(s): {}",
+            command,
+            player,
+            coherent,
+            problems.join("; ")
+        ))]
+    }
+}
@@ -0,0 +1,85 @@
+use super::Oracle;
+use crate::engine::{CommandResponse, StepContext};
+use crate::finding::{Category, Finding, Severity};
+
+/// Re-issues accepted commands with cosmetic variations against the same
+/// pre-state. A parser that accepts `play 5` should also accept `play  5`,
+/// `PLAY 5`, `play ５` and `play 5\n`.
+pub struct RobustnessOracle {
+    /// Only every Nth accepted command is probed, each probe costs one
+    /// request per variant.
+    pub every: usize,
+    seen: usize,
+}
+
+impl RobustnessOracle {
+    pub fn new(every: usize) -> Self {
+        RobustnessOracle {
+            every: every.max(1),
+            seen: 0,
+        }
+    }
+}
+
+fn full_width_digits(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '0'..='9' => std::char::from_u32(c as u32 - '0' as u32 + 0xFF10).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+pub fn variants(command: &str) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "extra whitespace",
+            command.split_whitespace().collect::<Vec<_>>().join("  "),
+        ),
+        ("upper case", command.to_uppercase()),
+        ("lower case", command.to_lowercase()),
+        ("full-width digits", full_width_digits(command)),
+        ("trailing newline", format!("{}\n", command)),
+    ]
+    .into_iter()
+    .filter(|(_, v)| v != command)
+    .collect()
+}
+
+impl Oracle for RobustnessOracle {
+    fn name(&self) -> &str {
+        "robustness"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let (before, player, command) = match (ctx.before, ctx.player, ctx.command) {
+            (Some(before), Some(player), Some(command)) => (before, player, command),
+            _ => return vec![],
+        };
+        self.seen = (self.seen + 1) % self.every;
+        if self.seen != 0 {
+            return vec![];
+        }
+        let mut findings = vec![];
+        for (kind, variant) in variants(command) {
+            let rejected = match ctx.play(&before.game.state, player, &variant) {
+                Ok(CommandResponse::Ok(_)) => continue,
+                Ok(CommandResponse::UserError { message }) => message,
+                Ok(CommandResponse::PartialParse) => "input was only partially parsed".to_string(),
+                Err(e) => e.to_string(),
+            };
+            findings.push(
+                Finding::new(
+                    Category::Oracle,
+                    Severity::Warning,
+                    format!(
+                        "{:?} was accepted but {} variant {:?} was not: {}",
+                        command, kind, variant, rejected
+                    ),
+                )
+                .with_oracle(self.name()),
+            );
+        }
+        findings
+    }
+}
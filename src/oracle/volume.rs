@@ -0,0 +1,67 @@
+use super::Oracle;
+use crate::engine::StepContext;
+use crate::finding::{Category, Finding, Severity};
+
+// Flags commands whose response is unreasonably large or which log an
+// unreasonable amount. Neither breaks a game, but in production they mean
+// oversized messages and slow clients.
+pub struct VolumeOracle {
+    pub max_response_bytes: Option<usize>,
+    pub max_logs: Option<usize>,
+}
+
+impl VolumeOracle {
+    pub fn new(max_response_bytes: Option<usize>, max_logs: Option<usize>) -> Self {
+        VolumeOracle {
+            max_response_bytes,
+            max_logs,
+        }
+    }
+
+    fn warning(&self, message: String) -> Finding {
+        Finding::new(Category::Oracle, Severity::Warning, message).with_oracle(self.name())
+    }
+}
+
+impl Oracle for VolumeOracle {
+    fn name(&self) -> &str {
+        "volume"
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let mut findings = vec![];
+        let after = ctx.after;
+        if let Some(max) = self.max_response_bytes {
+            let state = after.game.state.len();
+            let renders: usize = after
+                .player_renders
+                .iter()
+                .map(|r| r.player_render.len())
+                .sum();
+            let logs: usize = after.logs.iter().map(|l| l.content.len()).sum();
+            let total = state + renders + logs;
+            if total > max {
+                // The largest part goes in the message so findings group by
+                // what grew rather than by exact size.
+                let (part, _) = [("state", state), ("renders", renders), ("logs", logs)]
+                    .iter()
+                    .max_by_key(|(_, n)| *n)
+                    .cloned()
+                    .unwrap_or(("state", state));
+                findings.push(self.warning(format!(
+                    "response of {} bytes is over {} ({} is the largest part: state {}, renders {}, logs {})",
+                    total, max, part, state, renders, logs
+                )));
+            }
+        }
+        if let Some(max) = self.max_logs {
+            let logs = ctx.logs().len();
+            if logs > max {
+                findings.push(
+                    self.warning(format!("command wrote {} log entries, over {}", logs, max)),
+                );
+            }
+        }
+        findings
+    }
+}
@@ -0,0 +1,70 @@
+use failure::{bail, format_err, Error};
+use rand::Rng;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// A pause before each command, so stateful backends see something like a
+// person's pacing and the timeouts and expiries that back to back commands
+// never give a chance to fire.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delay {
+    Fixed(Duration),
+    Uniform(Duration, Duration),
+    // Exponentially distributed with this mean, long pauses being rare but
+    // not absent.
+    Exponential(Duration),
+}
+
+impl Delay {
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            Delay::Fixed(d) => d,
+            Delay::Uniform(min, max) if max > min => {
+                min + Duration::from_micros(rng.gen_range(0, (max - min).as_micros() as u64))
+            }
+            Delay::Uniform(min, _) => min,
+            Delay::Exponential(mean) => {
+                let u: f64 = rng.gen();
+                mean.mul_f64(-(1.0 - u).ln())
+            }
+        }
+    }
+}
+
+fn millis(s: &str) -> Result<Duration, Error> {
+    s.trim()
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|e| format_err!("invalid milliseconds {}: {}", s, e))
+}
+
+impl FromStr for Delay {
+    type Err = Error;
+
+    // `MS`, `MIN..MAX` or `exp:MEAN`, all in milliseconds.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if let Some(mean) = s.strip_prefix("exp:") {
+            return Ok(Delay::Exponential(millis(mean)?));
+        }
+        if let Some((min, max)) = s.split_once("..") {
+            let (min, max) = (millis(min)?, millis(max)?);
+            if max < min {
+                bail!("delay range {} ends before it starts", s);
+            }
+            return Ok(Delay::Uniform(min, max));
+        }
+        Ok(Delay::Fixed(millis(s)?))
+    }
+}
+
+impl fmt::Display for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Delay::Fixed(d) => write!(f, "{}", d.as_millis()),
+            Delay::Uniform(min, max) => write!(f, "{}..{}", min.as_millis(), max.as_millis()),
+            Delay::Exponential(mean) => write!(f, "exp:{}", mean.as_millis()),
+        }
+    }
+}
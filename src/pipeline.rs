@@ -0,0 +1,184 @@
+use failure::{bail, format_err, Error};
+
+use brdgme_cmd::requester;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::campaign::{self, fuzz_with_config};
+use crate::config::Config;
+use crate::finding::Finding;
+use crate::replay::{self, Reproduction};
+use crate::report::Summary;
+use crate::stop::parse_duration;
+use crate::strategy;
+
+// One step of a pipeline, given as `STRATEGY:DURATION` to fuzz with a
+// strategy for a while, or `replay` to check which findings so far still
+// reproduce.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Phase {
+    Fuzz {
+        strategy: String,
+        duration: Duration,
+    },
+    Replay,
+}
+
+impl FromStr for Phase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s == "replay" {
+            return Ok(Phase::Replay);
+        }
+        let (strategy, duration) = s
+            .split_once(':')
+            .ok_or_else(|| format_err!("expected STRATEGY:DURATION or replay, got {}", s))?;
+        if strategy::by_name(strategy).is_none() {
+            bail!("unknown strategy {}", strategy);
+        }
+        Ok(Phase::Fuzz {
+            strategy: strategy.to_string(),
+            duration: parse_duration(duration)?,
+        })
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Phase::Fuzz {
+                ref strategy,
+                duration,
+            } => write!(f, "{} for {}s", strategy, duration.as_secs()),
+            Phase::Replay => write!(f, "replay"),
+        }
+    }
+}
+
+pub enum PhaseResult {
+    Fuzz(Box<Summary>),
+    // Each unique finding so far, by signature, and whether it reproduced.
+    Replay(BTreeMap<String, Result<Reproduction, String>>),
+}
+
+#[derive(Default)]
+pub struct PipelineReport {
+    pub phases: Vec<(Phase, PhaseResult)>,
+}
+
+impl PipelineReport {
+    pub fn findings(&self) -> impl Iterator<Item = &Finding> {
+        self.phases.iter().flat_map(|(_, result)| match *result {
+            PhaseResult::Fuzz(ref summary) => summary.findings.iter(),
+            PhaseResult::Replay(_) => [].iter(),
+        })
+    }
+
+    pub fn failed(&self, fail_on_regression: bool) -> bool {
+        self.phases.iter().any(|(_, result)| match *result {
+            PhaseResult::Fuzz(ref summary) => summary.failed(fail_on_regression),
+            PhaseResult::Replay(_) => false,
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = "Pipeline\n".to_string();
+        for (i, (phase, result)) in self.phases.iter().enumerate() {
+            match *result {
+                PhaseResult::Fuzz(ref summary) => out.push_str(&format!(
+                    "  {}. {}: {} commands, {} games, {} unique findings\n",
+                    i + 1,
+                    phase,
+                    summary.tally.commands,
+                    summary.tally.finished,
+                    summary.unique_findings().len()
+                )),
+                PhaseResult::Replay(ref results) => {
+                    out.push_str(&format!(
+                        "  {}. {}: {} findings\n",
+                        i + 1,
+                        phase,
+                        results.len()
+                    ));
+                    for (signature, result) in results {
+                        let result = match *result {
+                            Ok(r) => format!("{:?}", r),
+                            Err(ref e) => format!("failed: {}", e),
+                        };
+                        out.push_str(&format!("       {:<16} {}\n", result, signature));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+// Runs each phase in turn as one campaign. Fuzz phases share a campaign id,
+// so their artifacts land together, and replay phases check every finding
+// from the phases before them.
+pub fn pipeline<F, R>(new_requester: F, config: Config, phases: &[Phase]) -> PipelineReport
+where
+    F: Fn() -> R + Clone + Send + 'static,
+    R: requester::Requester + 'static,
+{
+    let campaign_id = config
+        .campaign_id
+        .clone()
+        .unwrap_or_else(campaign::new_campaign_id);
+    let mut report = PipelineReport::default();
+    for (i, phase) in phases.iter().enumerate() {
+        if config.cancel.is_cancelled() {
+            break;
+        }
+        eprintln!("\nPhase {} of {}: {}", i + 1, phases.len(), phase);
+        let result = match *phase {
+            Phase::Fuzz {
+                ref strategy,
+                duration,
+            } => {
+                let name = strategy.clone();
+                let summary = fuzz_with_config(
+                    new_requester.clone(),
+                    Config {
+                        strategy: Arc::new(move || strategy::by_name(&name).unwrap()),
+                        duration: Some(duration),
+                        campaign_id: Some(campaign_id.clone()),
+                        phase: Some(format!("{}/{} {}", i + 1, phases.len(), phase)),
+                        ..config.clone()
+                    },
+                );
+                PhaseResult::Fuzz(Box::new(summary))
+            }
+            Phase::Replay => {
+                let mut unique: BTreeMap<&str, &Finding> = BTreeMap::new();
+                for f in report.findings() {
+                    unique.entry(&f.signature).or_insert(f);
+                }
+                let mut client = new_requester();
+                let results = unique
+                    .into_iter()
+                    .map(|(signature, finding)| {
+                        let result = replay::check_repro(
+                            &mut client,
+                            finding,
+                            &mut (config.oracles)(),
+                            &config.cancel,
+                        )
+                        .map_err(|e| e.to_string());
+                        (signature.to_string(), result)
+                    })
+                    .collect();
+                PhaseResult::Replay(results)
+            }
+        };
+        report.phases.push((phase.clone(), result));
+    }
+    eprintln!("\n{}", report.render());
+    report
+}
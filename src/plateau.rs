@@ -0,0 +1,141 @@
+use failure::{bail, Error};
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::PlateauConfig;
+use crate::seen::SeenSet;
+use crate::strategy;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Escalation {
+    // Swap every worker onto another built in strategy.
+    Strategy(String),
+    // Give every worker a fresh seed stream.
+    Reseed,
+}
+
+impl FromStr for Escalation {
+    type Err = Error;
+
+    // `strategy:NAME` or `reseed`.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.split_once(':') {
+            Some(("strategy", name)) => {
+                if strategy::by_name(name).is_none() {
+                    bail!("unknown strategy {}", name);
+                }
+                Ok(Escalation::Strategy(name.to_string()))
+            }
+            None if s == "reseed" => Ok(Escalation::Reseed),
+            _ => bail!("expected strategy:NAME or reseed, got {}", s),
+        }
+    }
+}
+
+impl fmt::Display for Escalation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Escalation::Strategy(ref name) => write!(f, "switching strategy to {}", name),
+            Escalation::Reseed => write!(f, "reseeding workers"),
+        }
+    }
+}
+
+// What workers pick up at their next game once an escalation is applied.
+#[derive(Debug, Default)]
+pub struct Escalations {
+    generation: AtomicUsize,
+    strategy: Mutex<Option<String>>,
+    reseeds: AtomicUsize,
+}
+
+impl Escalations {
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn strategy(&self) -> Option<String> {
+        self.strategy.lock().unwrap().clone()
+    }
+
+    pub fn reseeds(&self) -> usize {
+        self.reseeds.load(Ordering::SeqCst)
+    }
+
+    fn apply(&self, escalation: &Escalation) {
+        match *escalation {
+            Escalation::Strategy(ref name) => *self.strategy.lock().unwrap() = Some(name.clone()),
+            Escalation::Reseed => {
+                self.reseeds.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlateauEvent {
+    pub idle: Duration,
+    pub action: Option<Escalation>,
+}
+
+// Watches coverage fingerprints, such as command heads reaching a new
+// outcome or a new finish reason, and escalates when none have turned up for
+// a while. Actions are taken in order, one per plateau, after which plateaus
+// are only reported.
+pub struct Plateau {
+    config: PlateauConfig,
+    seen: SeenSet,
+    last_new: Instant,
+    next_action: usize,
+    escalations: Arc<Escalations>,
+}
+
+impl Plateau {
+    pub fn new(config: PlateauConfig, seen: SeenSet) -> Self {
+        Plateau {
+            config,
+            seen,
+            last_new: Instant::now(),
+            next_action: 0,
+            escalations: Arc::default(),
+        }
+    }
+
+    pub fn escalations(&self) -> Arc<Escalations> {
+        self.escalations.clone()
+    }
+
+    pub fn fingerprints(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn seen(&self) -> &SeenSet {
+        &self.seen
+    }
+
+    pub fn observe(&mut self, fingerprint: &str) {
+        if self.seen.insert(fingerprint) {
+            self.last_new = Instant::now();
+        }
+    }
+
+    // Called periodically, returns the plateau hit if any.
+    pub fn poll(&mut self) -> Option<PlateauEvent> {
+        let idle = self.last_new.elapsed();
+        if idle < self.config.after {
+            return None;
+        }
+        self.last_new = Instant::now();
+        let action = self.config.actions.get(self.next_action).cloned();
+        if let Some(ref action) = action {
+            self.next_action += 1;
+            self.escalations.apply(action);
+        }
+        Some(PlateauEvent { idle, action })
+    }
+}
@@ -0,0 +1,304 @@
+use failure::{bail, format_err, Error};
+use libc::{c_char, c_void};
+use rand::Rng;
+use serde_json::json;
+
+use brdgme_game::command;
+
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::engine::StepContext;
+use crate::finding::Finding;
+use crate::oracle::Oracle;
+use crate::strategy::{self, FuzzRng, Strategy};
+
+pub const ABI_VERSION: u32 = 1;
+
+// Strategies and oracles loaded from a shared library, so game specific
+// heuristics can live outside this crate. A plugin exports, with C linkage:
+//
+//   uint32_t brdgme_fuzz_plugin_version(void);  returning ABI_VERSION
+//   const char *brdgme_fuzz_plugin_name(void);
+//   void brdgme_fuzz_free_string(char *s);
+//
+// and a strategy, an oracle or both:
+//
+//   void *brdgme_fuzz_strategy_new(void);
+//   char *brdgme_fuzz_strategy_command(void *strategy, const char *spec,
+//                                      const char *names, uint64_t random);
+//   void brdgme_fuzz_strategy_new_game(void *strategy);  optional
+//   void brdgme_fuzz_strategy_free(void *strategy);
+//
+//   void *brdgme_fuzz_oracle_new(void);
+//   char *brdgme_fuzz_oracle_check(void *oracle, const char *step);
+//   void brdgme_fuzz_oracle_free(void *oracle);
+//
+// Everything passed across is JSON: the command spec, the player names and
+// a step holding the player, command, names and the games before and after.
+// A strategy returning NULL falls back to a random command, an oracle
+// returns NULL or an array of finding messages. Strings returned by the
+// plugin are handed back to brdgme_fuzz_free_string. Each instance is used
+// by one worker, so from one thread at a time, but workers are threads of
+// their own.
+pub struct Plugin {
+    handle: *mut c_void,
+    name: String,
+    free_string: unsafe extern "C" fn(*mut c_char),
+    strategy: Option<StrategyFns>,
+    oracle: Option<OracleFns>,
+}
+
+// Only the library handle and function pointers are shared, and dlsym'd
+// functions may be called from any thread.
+unsafe impl Send for Plugin {}
+unsafe impl Sync for Plugin {}
+
+#[derive(Clone, Copy)]
+struct StrategyFns {
+    new: unsafe extern "C" fn() -> *mut c_void,
+    command: unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char, u64) -> *mut c_char,
+    new_game: Option<unsafe extern "C" fn(*mut c_void)>,
+    free: unsafe extern "C" fn(*mut c_void),
+}
+
+#[derive(Clone, Copy)]
+struct OracleFns {
+    new: unsafe extern "C" fn() -> *mut c_void,
+    check: unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_char,
+    free: unsafe extern "C" fn(*mut c_void),
+}
+
+impl Plugin {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            bail!("failed to load plugin {}: {}", path.display(), dlerror());
+        }
+        // Closed on drop from here on, including when loading fails below.
+        let mut plugin = Plugin {
+            handle,
+            name: String::new(),
+            free_string: noop_free,
+            strategy: None,
+            oracle: None,
+        };
+        let version: unsafe extern "C" fn() -> u32 = plugin
+            .symbol("brdgme_fuzz_plugin_version")
+            .ok_or_else(|| format_err!("{} isn't a brdgme-fuzz plugin", path.display()))?;
+        let version = unsafe { version() };
+        if version != ABI_VERSION {
+            bail!(
+                "plugin {} uses ABI version {}, expected {}",
+                path.display(),
+                version,
+                ABI_VERSION
+            );
+        }
+        let name: unsafe extern "C" fn() -> *const c_char = plugin
+            .symbol("brdgme_fuzz_plugin_name")
+            .ok_or_else(|| format_err!("plugin {} has no name", path.display()))?;
+        let name = unsafe { name() };
+        if name.is_null() {
+            bail!("plugin {} has no name", path.display());
+        }
+        plugin.name = unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned();
+        plugin.free_string = plugin
+            .symbol("brdgme_fuzz_free_string")
+            .ok_or_else(|| format_err!("plugin {} has no free function", path.display()))?;
+        plugin.strategy = match (
+            plugin.symbol("brdgme_fuzz_strategy_new"),
+            plugin.symbol("brdgme_fuzz_strategy_command"),
+            plugin.symbol("brdgme_fuzz_strategy_free"),
+        ) {
+            (Some(new), Some(command), Some(free)) => Some(StrategyFns {
+                new,
+                command,
+                new_game: plugin.symbol("brdgme_fuzz_strategy_new_game"),
+                free,
+            }),
+            _ => None,
+        };
+        plugin.oracle = match (
+            plugin.symbol("brdgme_fuzz_oracle_new"),
+            plugin.symbol("brdgme_fuzz_oracle_check"),
+            plugin.symbol("brdgme_fuzz_oracle_free"),
+        ) {
+            (Some(new), Some(check), Some(free)) => Some(OracleFns { new, check, free }),
+            _ => None,
+        };
+        if plugin.strategy.is_none() && plugin.oracle.is_none() {
+            bail!(
+                "plugin {} exports neither a strategy nor an oracle",
+                path.display()
+            );
+        }
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has_strategy(&self) -> bool {
+        self.strategy.is_some()
+    }
+
+    pub fn has_oracle(&self) -> bool {
+        self.oracle.is_some()
+    }
+
+    // Function pointer types only, as dlsym hands back a data pointer.
+    fn symbol<T: Copy>(&self, name: &str) -> Option<T> {
+        let c_name = CString::new(name).ok()?;
+        let symbol = unsafe { libc::dlsym(self.handle, c_name.as_ptr()) };
+        if symbol.is_null() {
+            return None;
+        }
+        Some(unsafe { std::mem::transmute_copy::<*mut c_void, T>(&symbol) })
+    }
+
+    fn take_string(&self, s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned();
+        unsafe { (self.free_string)(s) };
+        Some(owned)
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+unsafe extern "C" fn noop_free(_: *mut c_char) {}
+
+fn dlerror() -> String {
+    let e = unsafe { libc::dlerror() };
+    if e.is_null() {
+        return "unknown error".to_string();
+    }
+    unsafe { CStr::from_ptr(e) }.to_string_lossy().into_owned()
+}
+
+pub fn strategy(plugin: &Arc<Plugin>) -> Option<PluginStrategy> {
+    let fns = plugin.strategy?;
+    Some(PluginStrategy {
+        plugin: plugin.clone(),
+        instance: unsafe { (fns.new)() },
+        fns,
+    })
+}
+
+pub fn oracle(plugin: &Arc<Plugin>) -> Option<PluginOracle> {
+    let fns = plugin.oracle?;
+    Some(PluginOracle {
+        plugin: plugin.clone(),
+        instance: unsafe { (fns.new)() },
+        fns,
+    })
+}
+
+pub struct PluginStrategy {
+    plugin: Arc<Plugin>,
+    instance: *mut c_void,
+    fns: StrategyFns,
+}
+
+// Instances stay with the worker they were made for.
+unsafe impl Send for PluginStrategy {}
+
+impl Strategy for PluginStrategy {
+    fn name(&self) -> &str {
+        self.plugin.name()
+    }
+
+    fn command(&mut self, spec: &command::Spec, names: &[String], rng: &mut FuzzRng) -> String {
+        let args = (
+            serde_json::to_string(spec)
+                .ok()
+                .and_then(|s| CString::new(s).ok()),
+            serde_json::to_string(names)
+                .ok()
+                .and_then(|s| CString::new(s).ok()),
+        );
+        if let (Some(spec_json), Some(names_json)) = args {
+            let out = unsafe {
+                (self.fns.command)(
+                    self.instance,
+                    spec_json.as_ptr(),
+                    names_json.as_ptr(),
+                    rng.gen(),
+                )
+            };
+            if let Some(command) = self.plugin.take_string(out) {
+                return command;
+            }
+        }
+        strategy::rand_command(spec, names, rng)
+    }
+
+    fn new_game(&mut self) {
+        if let Some(new_game) = self.fns.new_game {
+            unsafe { new_game(self.instance) };
+        }
+    }
+}
+
+impl Drop for PluginStrategy {
+    fn drop(&mut self) {
+        unsafe { (self.fns.free)(self.instance) };
+    }
+}
+
+pub struct PluginOracle {
+    plugin: Arc<Plugin>,
+    instance: *mut c_void,
+    fns: OracleFns,
+}
+
+unsafe impl Send for PluginOracle {}
+
+impl Oracle for PluginOracle {
+    fn name(&self) -> &str {
+        self.plugin.name()
+    }
+
+    fn check(&mut self, ctx: &StepContext) -> Vec<Finding> {
+        let step = json!({
+            "player": ctx.player,
+            "command": ctx.command,
+            "names": ctx.names,
+            "before": ctx.before,
+            "after": ctx.after,
+            "latency_ms": ctx.latency.as_secs_f64() * 1000.0,
+        });
+        let step = match CString::new(step.to_string()) {
+            Ok(step) => step,
+            Err(_) => return vec![],
+        };
+        let out = unsafe { (self.fns.check)(self.instance, step.as_ptr()) };
+        let messages = match self.plugin.take_string(out) {
+            Some(out) => match serde_json::from_str::<Vec<String>>(&out) {
+                Ok(messages) => messages,
+                Err(e) => vec![format!("plugin returned invalid findings {:?}: {}", out, e)],
+            },
+            None => return vec![],
+        };
+        messages.into_iter().map(|m| self.finding(m)).collect()
+    }
+}
+
+impl Drop for PluginOracle {
+    fn drop(&mut self) {
+        unsafe { (self.fns.free)(self.instance) };
+    }
+}
@@ -0,0 +1,175 @@
+use failure::{bail, Error};
+use serde_json::Value;
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::backend::trace::TraceEntry;
+use crate::finding::Finding;
+
+pub const REDACTED: &str = "[redacted]";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    // A JSON pointer into the game state, e.g. `/players/0/name`.
+    Pointer(String),
+    // A field name matched anywhere in the state, `*` matches any run of
+    // characters, e.g. `*_text`.
+    Field(String),
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s.is_empty() {
+            bail!("empty redaction rule");
+        }
+        Ok(if s.starts_with('/') {
+            Rule::Pointer(s.to_string())
+        } else {
+            Rule::Field(s.to_string())
+        })
+    }
+}
+
+fn glob(pattern: &str, s: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut rest = match s.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+// Applied to game states on their way out to artifacts, traces and reports.
+// States are found as JSON strings embedded in whatever is being written, so
+// the same rules cover findings, trace entries and anything built from them.
+#[derive(Clone, Debug, Default)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+impl Redactor {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Redactor { rules }
+    }
+
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn redact_state(&self, state: &mut Value) {
+        for rule in &self.rules {
+            match rule {
+                Rule::Pointer(pointer) => {
+                    if let Some(v) = state.pointer_mut(pointer) {
+                        *v = Value::String(REDACTED.to_string());
+                    }
+                }
+                Rule::Field(pattern) => redact_fields(state, pattern),
+            }
+        }
+    }
+
+    // Redacts every string inside `value` that is itself a JSON object or
+    // array, which is how states travel through requests and responses.
+    pub fn redact_embedded(&self, value: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        match value {
+            Value::String(s) => {
+                if let Some(redacted) = self.redact_str(s) {
+                    *s = redacted;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|v| self.redact_embedded(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.redact_embedded(v)),
+            _ => {}
+        }
+    }
+
+    fn redact_str(&self, s: &str) -> Option<String> {
+        let trimmed = s.trim_start();
+        if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+            return None;
+        }
+        let mut parsed: Value = serde_json::from_str(s).ok()?;
+        self.redact_state(&mut parsed);
+        // Embedded artifacts such as the serialised game hold the state one
+        // level further down.
+        self.redact_embedded(&mut parsed);
+        if s.contains('\n') {
+            serde_json::to_string_pretty(&parsed).ok()
+        } else {
+            serde_json::to_string(&parsed).ok()
+        }
+    }
+
+    pub fn redact_finding(&self, finding: &Finding) -> Finding {
+        let mut finding = finding.clone();
+        if self.is_empty() {
+            return finding;
+        }
+        if let Some(ref mut game) = finding.game {
+            if let Some(redacted) = self.redact_str(&game.initial_state) {
+                game.initial_state = redacted;
+            }
+            for state in Arc::make_mut(&mut game.checkpoints).values_mut() {
+                if let Some(redacted) = self.redact_str(state) {
+                    *state = redacted;
+                }
+            }
+        }
+        for content in finding.artifacts.values_mut() {
+            if let Some(redacted) = self.redact_str(content) {
+                *content = redacted;
+            }
+        }
+        finding
+    }
+
+    pub fn redact_trace(&self, entry: &TraceEntry) -> TraceEntry {
+        if self.is_empty() {
+            return entry.clone();
+        }
+        let mut value = match serde_json::to_value(entry) {
+            Ok(value) => value,
+            Err(_) => return entry.clone(),
+        };
+        self.redact_embedded(&mut value);
+        serde_json::from_value(value).unwrap_or_else(|_| entry.clone())
+    }
+}
+
+fn redact_fields(value: &mut Value, pattern: &str) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if glob(pattern, k) {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    redact_fields(v, pattern);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| redact_fields(v, pattern)),
+        _ => {}
+    }
+}
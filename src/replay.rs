@@ -0,0 +1,644 @@
+use failure::{format_err, Error};
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use std::cell::RefCell;
+
+use crate::backend::trace::TraceEntry;
+use crate::cancel::CancelToken;
+use crate::canonical::Canonicalizer;
+use crate::engine::{
+    error_finding, exec_command, render_violation, render_violation_message, whose_turn_violation,
+    whose_turn_violation_message, CommandResponse, FuzzGame, MoveClock, StepContext,
+};
+use crate::finding::{Category, Finding, HistoryEntry, Severity};
+use crate::oracle::{numeric_leaves, OracleRegistry};
+use crate::timing::Instant;
+use crate::wallclock;
+
+pub fn replay(
+    client: &mut (impl Requester + ?Sized),
+    initial_state: &str,
+    names: &[String],
+    history: &[HistoryEntry],
+    cancel: &CancelToken,
+) -> Result<Vec<TraceEntry>, Error> {
+    replay_at(client, initial_state, names, history, &[], cancel)
+}
+
+// Replays with the virtual time of each command, see GameMeta::times.
+pub fn replay_at(
+    client: &mut (impl Requester + ?Sized),
+    initial_state: &str,
+    names: &[String],
+    history: &[HistoryEntry],
+    times: &[u64],
+    cancel: &CancelToken,
+) -> Result<Vec<TraceEntry>, Error> {
+    let _reset = wallclock::Reset;
+    let mut state = initial_state.to_string();
+    let mut entries = vec![];
+    for (i, entry) in history.iter().enumerate() {
+        cancel.check()?;
+        wallclock::set_virtual_now(times.get(i).copied());
+        let request = api::Request::Play {
+            command: entry.command.clone(),
+            game: state.clone(),
+            names: names.to_vec(),
+            player: entry.player,
+        };
+        let res = client.request(&request);
+        entries.push(TraceEntry::new(&request, &res));
+        match res {
+            Ok(api::Response::Play { game, .. }) => state = game.state,
+            _ => break,
+        }
+    }
+    Ok(entries)
+}
+
+// Request and resource findings happen on a command that never made it into
+// the history, so it's appended to reproduce the failure.
+pub fn finding_history(finding: &Finding) -> Vec<HistoryEntry> {
+    let mut history = finding.history.to_vec();
+    if let (Category::Request | Category::Resource, Some(command), Some(player)) =
+        (finding.category, &finding.command, finding.player)
+    {
+        history.push(HistoryEntry {
+            player,
+            command: command.clone(),
+        });
+    }
+    history
+}
+
+pub fn replay_finding(
+    client: &mut (impl Requester + ?Sized),
+    finding: &Finding,
+    cancel: &CancelToken,
+) -> Result<Vec<TraceEntry>, Error> {
+    let game = finding
+        .game
+        .as_ref()
+        .ok_or_else(|| format_err!("finding {} has no game to replay", finding.id))?;
+    replay_at(
+        client,
+        &game.initial_state,
+        &game.names,
+        &finding_history(finding),
+        &game.times,
+        cancel,
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reproduction {
+    Reproduced,
+    NotReproduced,
+    // The replay went somewhere the original game didn't, such as a history
+    // command now being rejected, so the finding can't be judged either way.
+    Inconclusive,
+}
+
+impl Reproduction {
+    // Exit codes as understood by `git bisect run`, 125 skipping the commit.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Reproduction::Reproduced => 1,
+            Reproduction::NotReproduced => 0,
+            Reproduction::Inconclusive => 125,
+        }
+    }
+}
+
+// Whether replaying the history reruns whatever raised the finding, so not
+// seeing it again means it's gone. Findings from checks the engine makes
+// outside the oracle registry, such as starvation or spectating, from
+// oracles not registered here, or from the new game before any command,
+// can't show up again in a replay.
+fn replays(finding: &Finding, history: &[HistoryEntry], oracles: &OracleRegistry) -> bool {
+    if history.is_empty() {
+        return false;
+    }
+    match (finding.oracle.as_deref(), finding.category) {
+        (Some(name), _) => oracles
+            .stats()
+            .any(|(oracle, enabled, _)| enabled && oracle == name),
+        (None, Category::Request | Category::Resource) => finding.command.is_some(),
+        // Numbers are masked in signatures, so any will do.
+        (None, Category::Protocol) => [
+            render_violation_message(0, 0),
+            whose_turn_violation_message(0, 0),
+        ]
+        .iter()
+        .any(|m| {
+            Finding::new(Category::Protocol, Severity::Error, m.as_str()).signature
+                == finding.signature
+        }),
+        (None, Category::Oracle) => false,
+    }
+}
+
+// Replays the finding's game once, rerunning the oracles after every command,
+// and reports whether the same signature shows up again. Findings the replay
+// can't rerun the check for are inconclusive rather than not reproduced, so
+// bisecting them skips commits instead of marking them good.
+pub fn check_repro(
+    client: &mut dyn Requester,
+    finding: &Finding,
+    oracles: &mut OracleRegistry,
+    cancel: &CancelToken,
+) -> Result<Reproduction, Error> {
+    let meta = finding
+        .game
+        .as_ref()
+        .ok_or_else(|| format_err!("finding {} has no game to replay", finding.id))?;
+    let history = finding_history(finding);
+    if !replays(finding, &history, oracles) {
+        return Ok(Reproduction::Inconclusive);
+    }
+    let _reset = wallclock::Reset;
+    wallclock::set_virtual_now(meta.times.first().copied());
+    let mut state = meta.initial_state.clone();
+    // The initial render isn't stored with the finding, backends that can't
+    // answer Status just leave the first command without a before.
+    let mut before = match client.request(&api::Request::Status {
+        game: state.clone(),
+    }) {
+        Ok(api::Response::Status {
+            game,
+            player_renders,
+            ..
+        }) => Some(FuzzGame::new(game, player_renders, vec![])),
+        _ => None,
+    };
+    let mut clock = MoveClock::new(meta.names.len());
+    if let Some(ref before) = before {
+        clock.offer(before.whose_turn());
+    }
+    for (i, entry) in history.iter().enumerate() {
+        cancel.check()?;
+        let last = i + 1 == history.len();
+        wallclock::set_virtual_now(meta.times.get(i).copied());
+        let start = Instant::now();
+        let response = exec_command(
+            client,
+            entry.command.clone(),
+            state.clone(),
+            entry.player,
+            meta.names.clone(),
+        );
+        let latency = start.elapsed();
+        let after = match (response, finding.category) {
+            (Ok(CommandResponse::Ok(game)), _) => game,
+            // A different failure on the same command may well hide the
+            // original one, so it can't be judged either way.
+            (Err(e), Category::Request | Category::Resource) if last => {
+                return Ok(if error_finding(&e).signature == finding.signature {
+                    Reproduction::Reproduced
+                } else {
+                    Reproduction::Inconclusive
+                });
+            }
+            _ => return Ok(Reproduction::Inconclusive),
+        };
+        clock.record(entry.player, after.whose_turn());
+        clock.offer(after.whose_turn());
+        let violations: Vec<Finding> = render_violation(&after, meta.names.len())
+            .into_iter()
+            .chain(whose_turn_violation(&after))
+            .map(|m| Finding::new(Category::Protocol, Severity::Error, m))
+            .collect();
+        let found = oracles.run(&StepContext {
+            game_id: meta.id,
+            clock: &clock,
+            player: Some(entry.player),
+            command: Some(&entry.command),
+            names: &meta.names,
+            before: before.as_ref(),
+            after: &after,
+            latency,
+            client: RefCell::new(client),
+        });
+        if found
+            .iter()
+            .chain(&violations)
+            .any(|f| f.signature == finding.signature)
+        {
+            return Ok(Reproduction::Reproduced);
+        }
+        state = after.game.state.clone();
+        before = Some(after);
+    }
+    Ok(Reproduction::NotReproduced)
+}
+
+// A game as recorded in a trace: where it started and each accepted command
+// with the state it produced and the virtual time it was played at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedGame {
+    pub initial_state: String,
+    pub names: Vec<String>,
+    pub steps: Vec<(HistoryEntry, String)>,
+    pub times: Vec<Option<u64>>,
+}
+
+impl RecordedGame {
+    pub fn from_trace(entries: &[TraceEntry]) -> Option<Self> {
+        let mut entries = entries.iter();
+        let initial_state = entries.find_map(|e| match e.response {
+            Some(api::Response::New { ref game, .. }) => Some(game.state.clone()),
+            _ => None,
+        })?;
+        let mut names = vec![];
+        let mut steps = vec![];
+        let mut times = vec![];
+        for e in entries {
+            if let (
+                api::Request::Play {
+                    player,
+                    ref command,
+                    names: ref n,
+                    ..
+                },
+                Some(api::Response::Play { ref game, .. }),
+            ) = (&e.request, &e.response)
+            {
+                names = n.clone();
+                steps.push((
+                    HistoryEntry {
+                        player: *player,
+                        command: command.clone(),
+                    },
+                    game.state.clone(),
+                ));
+                times.push(e.now);
+            }
+        }
+        Some(RecordedGame {
+            initial_state,
+            names,
+            steps,
+            times,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    // Index into the recorded steps of the first command whose result
+    // differs.
+    pub index: usize,
+    pub step: HistoryEntry,
+    pub expected: String,
+    // None when the command no longer succeeds at all.
+    pub actual: Option<String>,
+    pub diff: Vec<String>,
+}
+
+// Leaf by leaf differences between two JSON states, falling back to the raw
+// strings when either isn't JSON.
+pub fn state_diff(expected: &str, actual: &str) -> Vec<String> {
+    let (e, a) = match (
+        serde_json::from_str::<serde_json::Value>(expected),
+        serde_json::from_str::<serde_json::Value>(actual),
+    ) {
+        (Ok(e), Ok(a)) => (leaves(&e), leaves(&a)),
+        _ => return vec![format!("- {}", expected), format!("+ {}", actual)],
+    };
+    let mut diff = vec![];
+    for (path, ev) in &e {
+        match a.get(path) {
+            Some(av) if av == ev => {}
+            Some(av) => diff.push(format!("{}: {} -> {}", path, ev, av)),
+            None => diff.push(format!("{}: {} -> (missing)", path, ev)),
+        }
+    }
+    for (path, av) in &a {
+        if !e.contains_key(path) {
+            diff.push(format!("{}: (missing) -> {}", path, av));
+        }
+    }
+    diff
+}
+
+fn leaves(value: &serde_json::Value) -> std::collections::BTreeMap<String, String> {
+    let mut out = std::collections::BTreeMap::new();
+    collect_leaves(value, String::new(), &mut out);
+    // Numbers go through the same flattening the oracles use so 1 and 1.0
+    // compare equal.
+    for (path, n) in numeric_leaves(value) {
+        out.insert(path, n.to_string());
+    }
+    out
+}
+
+fn collect_leaves(
+    value: &serde_json::Value,
+    path: String,
+    out: &mut std::collections::BTreeMap<String, String>,
+) {
+    match value {
+        serde_json::Value::Array(items) => {
+            for (i, v) in items.iter().enumerate() {
+                collect_leaves(v, format!("{}/{}", path, i), out);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                let k = k.replace('~', "~0").replace('/', "~1");
+                collect_leaves(v, format!("{}/{}", path, k), out);
+            }
+        }
+        leaf => {
+            out.insert(path, leaf.to_string());
+        }
+    }
+}
+
+// Finds the earliest recorded command whose replayed result differs from the
+// recording, replaying the game once from the start and comparing each state
+// as it's reached. Each command is played at the virtual time it was recorded
+// at. States are compared and diffed canonicalised.
+pub fn localize_divergence(
+    client: &mut (impl Requester + ?Sized),
+    recorded: &RecordedGame,
+    canonical: &Canonicalizer,
+    cancel: &CancelToken,
+) -> Result<Option<Divergence>, Error> {
+    let _reset = wallclock::Reset;
+    let mut state = recorded.initial_state.clone();
+    for (index, (step, expected)) in recorded.steps.iter().enumerate() {
+        cancel.check()?;
+        wallclock::set_virtual_now(recorded.times.get(index).copied().flatten());
+        let actual = match exec_command(
+            client,
+            step.command.clone(),
+            state,
+            step.player,
+            recorded.names.clone(),
+        ) {
+            Ok(CommandResponse::Ok(game)) => game.game.state,
+            _ => {
+                return Ok(Some(Divergence {
+                    index,
+                    step: step.clone(),
+                    expected: expected.clone(),
+                    actual: None,
+                    diff: vec![format!(
+                        "{:?} by player {} is no longer accepted",
+                        step.command, step.player
+                    )],
+                }))
+            }
+        };
+        if !canonical.same(&actual, expected) {
+            return Ok(Some(Divergence {
+                index,
+                step: step.clone(),
+                expected: expected.clone(),
+                diff: state_diff(&canonical.state(expected), &canonical.state(&actual)),
+                actual: Some(actual),
+            }));
+        }
+        state = actual;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use crate::engine::GameId;
+    use crate::finding::GameMeta;
+    use crate::mock::{MockRequester, ToyGame};
+
+    fn add(player: usize, n: u32) -> HistoryEntry {
+        HistoryEntry {
+            player,
+            command: format!("add {}", n),
+        }
+    }
+
+    fn on_toy_game(finding: Finding, history: Vec<HistoryEntry>) -> Finding {
+        let initial_state = match ToyGame::default().request(&api::Request::New { players: 2 }) {
+            Ok(api::Response::New { game, .. }) => game.state,
+            other => panic!("unexpected new game response {:?}", other),
+        };
+        Finding {
+            history: Arc::new(history),
+            game: Some(GameMeta {
+                id: GameId { worker: 0, game: 0 },
+                players: 2,
+                names: vec!["a".to_string(), "b".to_string()],
+                turn: 0,
+                initial_state,
+                checkpoints: Arc::default(),
+                bots: BTreeMap::new(),
+                times: vec![],
+            }),
+            ..finding
+        }
+    }
+
+    fn check(client: &mut dyn Requester, finding: &Finding) -> Reproduction {
+        check_repro(
+            client,
+            finding,
+            &mut OracleRegistry::with_defaults(),
+            &CancelToken::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn engine_checks_are_inconclusive() {
+        let finding = on_toy_game(
+            Finding::new(Category::Oracle, Severity::Warning, "starved").with_oracle("starvation"),
+            vec![add(0, 1)],
+        );
+        assert_eq!(
+            check(&mut ToyGame::default(), &finding),
+            Reproduction::Inconclusive
+        );
+    }
+
+    #[test]
+    fn empty_history_is_inconclusive() {
+        let finding = on_toy_game(
+            Finding::new(Category::Oracle, Severity::Error, "bad points").with_oracle("points"),
+            vec![],
+        );
+        assert_eq!(
+            check(&mut ToyGame::default(), &finding),
+            Reproduction::Inconclusive
+        );
+    }
+
+    #[test]
+    fn registered_oracle_not_seen_is_not_reproduced() {
+        let finding = on_toy_game(
+            Finding::new(Category::Oracle, Severity::Error, "bad points").with_oracle("points"),
+            vec![add(0, 1), add(1, 2)],
+        );
+        assert_eq!(
+            check(&mut ToyGame::default(), &finding),
+            Reproduction::NotReproduced
+        );
+    }
+
+    #[test]
+    fn spectate_violations_are_inconclusive() {
+        let finding = on_toy_game(
+            Finding::new(
+                Category::Protocol,
+                Severity::Error,
+                "status request failed: x",
+            ),
+            vec![add(0, 1)],
+        );
+        assert_eq!(
+            check(&mut ToyGame::default(), &finding),
+            Reproduction::Inconclusive
+        );
+    }
+
+    #[test]
+    fn request_errors_compare_signatures() {
+        let crashing = |message: &'static str| {
+            let mut toy = ToyGame::default();
+            MockRequester::new().fallback(move |req| match *req {
+                api::Request::Play { ref command, .. } if command == "add 3" => {
+                    Err(format_err!("{}", message))
+                }
+                _ => toy.request(req),
+            })
+        };
+        let mut finding = on_toy_game(
+            Finding::new(Category::Request, Severity::Critical, "panicked at 12"),
+            vec![add(0, 1)],
+        );
+        finding.player = Some(1);
+        finding.command = Some("add 3".to_string());
+        assert_eq!(
+            check(&mut crashing("panicked at 34"), &finding),
+            Reproduction::Reproduced
+        );
+        assert_eq!(
+            check(&mut crashing("out of memory"), &finding),
+            Reproduction::Inconclusive
+        );
+    }
+
+    fn names() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
+
+    // A toy game played with `commands`, each at a virtual time of its own.
+    fn recorded(commands: &[&str]) -> RecordedGame {
+        let mut toy = ToyGame::default();
+        let new = api::Request::New { players: 2 };
+        let res = toy.request(&new);
+        let initial_state = match res {
+            Ok(api::Response::New { ref game, .. }) => game.state.clone(),
+            ref other => panic!("unexpected new game response {:?}", other),
+        };
+        let history: Vec<HistoryEntry> = commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| HistoryEntry {
+                player: i % 2,
+                command: command.to_string(),
+            })
+            .collect();
+        let times: Vec<u64> = (0..history.len() as u64).map(|i| 1000 + i).collect();
+        let mut trace = vec![TraceEntry::new(&new, &res)];
+        trace.extend(
+            replay_at(
+                &mut toy,
+                &initial_state,
+                &names(),
+                &history,
+                &times,
+                &CancelToken::default(),
+            )
+            .unwrap(),
+        );
+        RecordedGame::from_trace(&trace).unwrap()
+    }
+
+    #[test]
+    fn identical_replays_have_no_divergence() {
+        let recorded = recorded(&["add 1", "add 2", "add 3"]);
+        let times = Arc::new(Mutex::new(vec![]));
+        let mut client = MockRequester::new().fallback({
+            let times = times.clone();
+            let mut toy = ToyGame::default();
+            move |req| {
+                times.lock().unwrap().push(wallclock::virtual_now());
+                toy.request(req)
+            }
+        });
+        assert_eq!(
+            localize_divergence(
+                &mut client,
+                &recorded,
+                &Canonicalizer::default(),
+                &CancelToken::default(),
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(client.requests.len(), 3);
+        assert_eq!(
+            *times.lock().unwrap(),
+            vec![Some(1000), Some(1001), Some(1002)]
+        );
+    }
+
+    #[test]
+    fn divergence_is_the_first_differing_state() {
+        let recorded = recorded(&["add 2", "add 2", "add 2", "add 1"]);
+        let mut client = MockRequester::wrapping(ToyGame {
+            target: 5,
+            ..ToyGame::default()
+        });
+        let divergence = localize_divergence(
+            &mut client,
+            &recorded,
+            &Canonicalizer::default(),
+            &CancelToken::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(divergence.index, 2);
+        assert!(divergence.actual.is_some());
+        assert!(!divergence.diff.is_empty());
+        assert_eq!(client.requests.len(), 3);
+    }
+
+    #[test]
+    fn failed_commands_diverge() {
+        let recorded = recorded(&["add 1", "add 1", "add 1"]);
+        let mut toy = ToyGame::default();
+        let mut client = MockRequester::new().fallback(move |req| match *req {
+            api::Request::Play { player: 1, .. } => Err(format_err!("boom")),
+            _ => toy.request(req),
+        });
+        let divergence = localize_divergence(
+            &mut client,
+            &recorded,
+            &Canonicalizer::default(),
+            &CancelToken::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.actual, None);
+    }
+}
@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::finding::{Finding, Severity};
+use crate::replay::Reproduction;
+use crate::seen::SeenStats;
+
+pub mod acceptance;
+pub mod baseline;
+pub mod batch;
+pub mod blind_spot;
+pub mod budget;
+pub mod coverage;
+pub mod eta;
+pub mod fairness;
+pub mod finish;
+pub mod header;
+pub mod heatmap;
+pub mod overhead;
+pub mod placings;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod reads;
+pub mod render_parse;
+pub mod rotate;
+pub mod spec_nodes;
+pub mod storybook;
+pub mod suppress;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod user_errors;
+pub mod warmup;
+pub mod webhook;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FuzzTally {
+    pub started: usize,
+    pub finished: usize,
+    pub commands: usize,
+    pub invalid_input: usize,
+    pub finished_turns: usize,
+    pub longest_game: usize,
+    #[serde(default)]
+    pub active_workers: usize,
+    #[serde(default)]
+    pub scaling_events: usize,
+    #[serde(default)]
+    pub protocol_violations: usize,
+    #[serde(default)]
+    pub plateaus: usize,
+    #[serde(default)]
+    pub blind_spots: usize,
+    #[serde(default)]
+    pub skipped_turns: usize,
+    #[serde(default)]
+    pub campaign_id: String,
+    #[serde(default)]
+    pub progress: Option<eta::Eta>,
+}
+
+impl FuzzTally {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Games started: {}   Games finished: {}   Commands: {}   Commands failed: {}   Avg turns: {:.1}   Max turns: {}",
+            self.started,
+            self.finished,
+            self.commands,
+            self.invalid_input,
+            self.avg_turns(),
+            self.longest_game
+        );
+        if let Some(ref progress) = self.progress {
+            out.push_str(&format!("   {}", progress.render()));
+        }
+        out
+    }
+
+    pub fn avg_turns(&self) -> f64 {
+        if self.finished == 0 {
+            return 0.0;
+        }
+        self.finished_turns as f64 / self.finished as f64
+    }
+}
+
+pub fn output_tally(tally: &FuzzTally) {
+    #[cfg(feature = "prometheus")]
+    prometheus::observe(tally);
+    #[cfg(feature = "tui")]
+    tui::draw(tally);
+    #[cfg(not(feature = "tui"))]
+    eprintln!("{}", tally.render());
+}
+
+#[derive(Debug)]
+pub struct Summary {
+    pub header: header::RunHeader,
+    pub tally: FuzzTally,
+    pub elapsed: Duration,
+    pub findings: Vec<Finding>,
+    pub acceptance: acceptance::AcceptanceTable,
+    pub regressions: Vec<baseline::Regression>,
+    pub artifacts: Vec<PathBuf>,
+    pub reports: Vec<PathBuf>,
+    pub user_errors: user_errors::UserErrors,
+    pub finish_reasons: finish::FinishReasons,
+    pub fairness: fairness::Fairness,
+    pub wins: placings::WinTable,
+    pub reads: reads::ReadTable,
+    pub render_parse: render_parse::RenderParse,
+    pub windows: Option<warmup::Windows>,
+    pub breaches: Vec<budget::Breach>,
+    // Stats for each bounded dedup set, see seen::SeenSet.
+    pub dedup: Vec<(String, SeenStats)>,
+    // Known issues, which are reported but don't fail the run.
+    pub suppressions: suppress::Suppressions,
+    // Suppressions replayed at the start of the campaign, by signature.
+    pub reverified: BTreeMap<String, Result<Reproduction, String>>,
+    // Unique findings from oracles in shadow mode, with how often each was
+    // seen. They never fail the run.
+    pub shadow: Vec<(Finding, usize)>,
+    // Where each worker's time went.
+    pub overhead: Vec<overhead::Overhead>,
+    // Findings counted but not printed or saved once over the per-minute
+    // cap, and why the target looked broken if it did.
+    pub throttled: usize,
+    pub diagnosis: Option<String>,
+    // States and spec branches reached, when collected.
+    pub coverage: Option<coverage::Coverage>,
+}
+
+impl Summary {
+    pub fn failed(&self, fail_on_regression: bool) -> bool {
+        self.findings
+            .iter()
+            .any(|f| !self.suppressions.suppresses(f))
+            || !self.breaches.is_empty()
+            || (fail_on_regression && !self.regressions.is_empty())
+    }
+
+    // Findings collapsed by signature, most severe and most frequent first.
+    pub fn unique_findings(&self) -> Vec<(&Finding, usize)> {
+        let mut by_signature: BTreeMap<&str, (&Finding, usize)> = BTreeMap::new();
+        for f in &self.findings {
+            by_signature.entry(&f.signature).or_insert((f, 0)).1 += 1;
+        }
+        let mut unique: Vec<(&Finding, usize)> = by_signature.into_values().collect();
+        unique.sort_by(|a, b| {
+            b.0.severity
+                .cmp(&a.0.severity)
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.0.id.cmp(&b.0.id))
+        });
+        unique
+    }
+
+    // Unique and total findings per panic location.
+    pub fn panic_locations(&self) -> BTreeMap<String, (usize, usize)> {
+        let mut locations = BTreeMap::new();
+        for (f, count) in self.unique_findings() {
+            if let Some(ref panic) = f.panic {
+                let entry = locations.entry(panic.location()).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += count;
+            }
+        }
+        locations
+    }
+
+    pub fn render(&self) -> String {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut out = format!(
+            "Campaign summary\n{}Elapsed: {:.1}s   Throughput: {:.1} commands/s, {:.2} games/s\n{}\n",
+            self.header.render(),
+            self.elapsed.as_secs_f64(),
+            self.tally.commands as f64 / secs,
+            self.tally.finished as f64 / secs,
+            self.tally.render()
+        );
+        if let Some(ref windows) = self.windows {
+            out.push_str(&windows.render());
+        }
+
+        let unique = self.unique_findings();
+        let by_severity = [
+            Severity::Security,
+            Severity::Critical,
+            Severity::Error,
+            Severity::Warning,
+            Severity::Info,
+        ]
+        .iter()
+        .map(|s| {
+            let n = unique.iter().filter(|(f, _)| f.severity == *s).count();
+            format!("{:?} {}", s, n).to_lowercase()
+        })
+        .collect::<Vec<_>>();
+        out.push_str(&format!(
+            "\nFindings: {} unique ({})\n",
+            unique.len(),
+            by_severity.join(", ")
+        ));
+        let known = unique
+            .iter()
+            .filter(|(f, _)| self.suppressions.suppresses(f))
+            .count();
+        if known > 0 {
+            out.push_str(&format!(
+                "Known issues: {} unique, not failing the run\n",
+                known
+            ));
+        }
+        for (f, _) in unique
+            .iter()
+            .filter(|(f, _)| self.suppressions.regressed(f))
+        {
+            out.push_str(&format!("Marked fixed but seen again: {}\n", f.signature));
+        }
+        if !self.shadow.is_empty() {
+            let mut by_oracle: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+            for (f, count) in &self.shadow {
+                let entry = by_oracle
+                    .entry(f.oracle.as_deref().unwrap_or("unknown"))
+                    .or_default();
+                entry.0 += 1;
+                entry.1 += count;
+            }
+            out.push_str("Shadow findings, not failing the run:\n");
+            for (oracle, (unique, total)) in by_oracle {
+                out.push_str(&format!(
+                    "  {}  {} unique, {} total\n",
+                    oracle, unique, total
+                ));
+            }
+        }
+        if let Some(reverified) = self.suppressions.render_reverified(&self.reverified) {
+            out.push_str(&reverified);
+        }
+        if let Some(ref diagnosis) = self.diagnosis {
+            out.push_str(&format!("{}\n", diagnosis));
+        }
+        if self.throttled > 0 {
+            out.push_str(&format!(
+                "Findings throttled: {} counted but not printed or saved\n",
+                self.throttled
+            ));
+        }
+        for breach in &self.breaches {
+            out.push_str(&format!("Budget exceeded: {}\n", breach));
+        }
+        if self.tally.blind_spots > 0 {
+            out.push_str(&format!(
+                "Generator blind spots: {} games abandoned with no command accepted\n",
+                self.tally.blind_spots
+            ));
+        }
+        if self.tally.skipped_turns > 0 {
+            out.push_str(&format!(
+                "Skipped turns: {} turns given up on with no command accepted\n",
+                self.tally.skipped_turns
+            ));
+        }
+        if self.tally.protocol_violations > 0 {
+            out.push_str(&format!(
+                "Protocol violations: {} (fuzzing continued past them)\n",
+                self.tally.protocol_violations
+            ));
+        }
+        let panics = self.panic_locations();
+        if !panics.is_empty() {
+            out.push_str("Panics by location:\n");
+            for (location, (unique, total)) in &panics {
+                out.push_str(&format!(
+                    "  {}  {} unique, {} total\n",
+                    location, unique, total
+                ));
+            }
+        }
+        for (f, count) in unique.iter().take(5) {
+            let triage = match self.suppressions.get(&f.signature) {
+                Some(s) => format!(" ({:?})", s.state).to_lowercase(),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "  [{}] {} x{}{}  {}\n",
+                format!("{:?}", f.severity).to_lowercase(),
+                f.id,
+                count,
+                triage,
+                f.message
+            ));
+        }
+
+        let accepted = self.tally.commands - self.tally.invalid_input;
+        out.push_str(&format!(
+            "\nCoverage: {:.1}% of command heads accepted at least once, {:.1}% of commands accepted\n",
+            self.acceptance.coverage() * 100.0,
+            if self.tally.commands == 0 {
+                0.0
+            } else {
+                accepted as f64 / self.tally.commands as f64 * 100.0
+            }
+        ));
+        if let Some(line) = self.user_errors.render_top(5) {
+            out.push_str(&format!("{}\n", line));
+        }
+        if let Some(reasons) = self.finish_reasons.render() {
+            out.push_str(&format!("\n{}", reasons));
+        }
+        if let Some(fairness) = self.fairness.render() {
+            out.push_str(&format!("\n{}", fairness));
+        }
+        if let Some(wins) = self.wins.render() {
+            out.push_str(&format!("\n{}", wins));
+        }
+        let slowest = self.acceptance.slowest(5);
+        if !slowest.is_empty() {
+            out.push_str("\nSlowest commands:\n");
+            for (head, row) in slowest {
+                out.push_str(&format!(
+                    "  {:<20} max {:?}  avg {:?}\n",
+                    head,
+                    row.max_latency,
+                    row.avg_latency()
+                ));
+            }
+        }
+        if let Some(overhead) = overhead::Overhead::render(&self.overhead) {
+            out.push_str(&format!("\n{}", overhead));
+        }
+        if let Some(reads) = self.reads.render() {
+            out.push_str(&format!("\n{}", reads));
+        }
+        if let Some(render_parse) = self.render_parse.render() {
+            out.push_str(&format!("\n{}", render_parse));
+        }
+        // Only worth showing once entries have been evicted and accuracy
+        // starts to drop.
+        for (name, stats) in self.dedup.iter().filter(|(_, s)| s.evicted > 0) {
+            out.push_str(&format!("\nDedup {}: {}\n", name, stats.render()));
+        }
+        for (title, paths) in &[("Artifacts", &self.artifacts), ("Reports", &self.reports)] {
+            if !paths.is_empty() {
+                out.push_str(&format!("\n{}:\n", title));
+                for p in paths.iter() {
+                    out.push_str(&format!("  {}\n", p.display()));
+                }
+            }
+        }
+        out
+    }
+}
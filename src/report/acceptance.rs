@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Accepted,
+    Rejected,
+    Partial,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AcceptanceRow {
+    pub generated: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub partial: usize,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl AcceptanceRow {
+    pub fn rejection_rate(&self) -> f64 {
+        if self.generated == 0 {
+            return 0.0;
+        }
+        (self.rejected + self.partial) as f64 / self.generated as f64
+    }
+
+    pub fn avg_latency(&self) -> Duration {
+        if self.generated == 0 {
+            return Duration::default();
+        }
+        self.total_latency / self.generated as u32
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AcceptanceTable {
+    pub rows: HashMap<String, AcceptanceRow>,
+}
+
+impl AcceptanceTable {
+    pub fn record(&mut self, head: &str, outcome: Outcome, latency: Duration) {
+        let row = self.rows.entry(head.to_string()).or_default();
+        row.generated += 1;
+        row.total_latency += latency;
+        row.max_latency = row.max_latency.max(latency);
+        match outcome {
+            Outcome::Accepted => row.accepted += 1,
+            Outcome::Rejected => row.rejected += 1,
+            Outcome::Partial => row.partial += 1,
+        }
+    }
+
+    pub fn sorted(&self) -> Vec<(&str, &AcceptanceRow)> {
+        let mut rows: Vec<(&str, &AcceptanceRow)> =
+            self.rows.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        rows.sort_by(|a, b| {
+            b.1.rejection_rate()
+                .partial_cmp(&a.1.rejection_rate())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(b.0))
+        });
+        rows
+    }
+
+    pub fn merge(&mut self, other: AcceptanceTable) {
+        for (head, r) in other.rows {
+            let row = self.rows.entry(head).or_default();
+            row.generated += r.generated;
+            row.accepted += r.accepted;
+            row.rejected += r.rejected;
+            row.partial += r.partial;
+            row.total_latency += r.total_latency;
+            row.max_latency = row.max_latency.max(r.max_latency);
+        }
+    }
+
+    // Share of command heads that were accepted at least once.
+    pub fn coverage(&self) -> f64 {
+        if self.rows.is_empty() {
+            return 0.0;
+        }
+        self.rows.values().filter(|r| r.accepted > 0).count() as f64 / self.rows.len() as f64
+    }
+
+    pub fn slowest(&self, n: usize) -> Vec<(&str, &AcceptanceRow)> {
+        let mut rows: Vec<(&str, &AcceptanceRow)> =
+            self.rows.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        rows.sort_by(|a, b| {
+            b.1.max_latency
+                .cmp(&a.1.max_latency)
+                .then_with(|| a.0.cmp(b.0))
+        });
+        rows.truncate(n);
+        rows
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{:<20} {:>10} {:>10} {:>10} {:>10} {:>8}\n",
+            "Command", "Generated", "Accepted", "Rejected", "Partial", "Reject%"
+        );
+        for (head, row) in self.sorted() {
+            out.push_str(&format!(
+                "{:<20} {:>10} {:>10} {:>10} {:>10} {:>7.1}%\n",
+                head,
+                row.generated,
+                row.accepted,
+                row.rejected,
+                row.partial,
+                row.rejection_rate() * 100.0
+            ));
+        }
+        out
+    }
+}
+
+pub fn command_head(command: &str) -> String {
+    command
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
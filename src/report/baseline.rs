@@ -0,0 +1,104 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::FuzzTally;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub commands_per_sec: f64,
+    pub avg_turns: f64,
+    pub user_error_rate: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Thresholds {
+    // Maximum relative drop in throughput, 0.2 being 20% slower.
+    pub commands_per_sec: f64,
+    // Maximum relative change in average game length in either direction.
+    pub avg_turns: f64,
+    // Maximum absolute increase in the fraction of commands rejected.
+    pub user_error_rate: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            commands_per_sec: 0.2,
+            avg_turns: 0.25,
+            user_error_rate: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Baseline {
+    pub fn from_tally(tally: &FuzzTally, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        Baseline {
+            commands_per_sec: if secs > 0.0 {
+                tally.commands as f64 / secs
+            } else {
+                0.0
+            },
+            avg_turns: tally.avg_turns(),
+            user_error_rate: if tally.commands > 0 {
+                tally.invalid_input as f64 / tally.commands as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn compare(&self, current: &Baseline, thresholds: &Thresholds) -> Vec<Regression> {
+        let mut regressions = vec![];
+        let mut check = |metric, baseline: f64, current: f64, regressed: bool| {
+            if regressed {
+                regressions.push(Regression {
+                    metric,
+                    baseline,
+                    current,
+                });
+            }
+        };
+        check(
+            "commands/sec",
+            self.commands_per_sec,
+            current.commands_per_sec,
+            current.commands_per_sec < self.commands_per_sec * (1.0 - thresholds.commands_per_sec),
+        );
+        check(
+            "avg turns",
+            self.avg_turns,
+            current.avg_turns,
+            self.avg_turns > 0.0
+                && ((current.avg_turns - self.avg_turns) / self.avg_turns).abs()
+                    > thresholds.avg_turns,
+        );
+        check(
+            "user error rate",
+            self.user_error_rate,
+            current.user_error_rate,
+            current.user_error_rate - self.user_error_rate > thresholds.user_error_rate,
+        );
+        regressions
+    }
+}
@@ -0,0 +1,112 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::{FuzzTally, Summary};
+use crate::finding::Severity;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchRow {
+    pub seed: u64,
+    pub tally: FuzzTally,
+    // Finding count per signature for this seed.
+    pub findings: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchColumn {
+    pub signature: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// One row per seed, one column per distinct finding signature seen in any
+// seed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub rows: Vec<BatchRow>,
+    pub columns: Vec<BatchColumn>,
+}
+
+impl BatchReport {
+    pub fn record(&mut self, seed: u64, summary: &Summary) {
+        let mut findings = BTreeMap::new();
+        for f in &summary.findings {
+            *findings.entry(f.signature.clone()).or_insert(0) += 1;
+            if !self.columns.iter().any(|c| c.signature == f.signature) {
+                self.columns.push(BatchColumn {
+                    signature: f.signature.clone(),
+                    severity: f.severity,
+                    message: f.message.clone(),
+                });
+            }
+        }
+        self.rows.push(BatchRow {
+            seed,
+            tally: summary.tally.clone(),
+            findings,
+        });
+    }
+
+    // Seeds exposing the signature, in the order they were run.
+    pub fn seeds_for(&self, signature: &str) -> Vec<u64> {
+        self.rows
+            .iter()
+            .filter(|r| r.findings.contains_key(signature))
+            .map(|r| r.seed)
+            .collect()
+    }
+
+    pub fn reproduction_rate(&self, signature: &str) -> f64 {
+        if self.rows.is_empty() {
+            return 0.0;
+        }
+        self.seeds_for(signature).len() as f64 / self.rows.len() as f64
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("{:<20} {:>10} {:>10}", "Seed", "Commands", "Findings");
+        for i in 0..self.columns.len() {
+            out.push_str(&format!(" {:>5}", format!("F{}", i + 1)));
+        }
+        out.push('\n');
+        for row in &self.rows {
+            out.push_str(&format!(
+                "{:<20} {:>10} {:>10}",
+                row.seed,
+                row.tally.commands,
+                row.findings.values().sum::<usize>()
+            ));
+            for c in &self.columns {
+                match row.findings.get(&c.signature) {
+                    Some(n) => out.push_str(&format!(" {:>5}", n)),
+                    None => out.push_str(&format!(" {:>5}", "-")),
+                }
+            }
+            out.push('\n');
+        }
+        for (i, c) in self.columns.iter().enumerate() {
+            let seeds = self.seeds_for(&c.signature);
+            out.push_str(&format!(
+                "\nF{} [{}] {}/{} seeds ({:.1}%), first seed {}: {}",
+                i + 1,
+                format!("{:?}", c.severity).to_lowercase(),
+                seeds.len(),
+                self.rows.len(),
+                self.reproduction_rate(&c.signature) * 100.0,
+                seeds.first().map(|s| s.to_string()).unwrap_or_default(),
+                c.message
+            ));
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use brdgme_cmd::api;
+
+use crate::canonical::Canonicalizer;
+use crate::engine::GameId;
+use crate::storage::Storage;
+
+// A state where the generator couldn't come up with any command the game
+// accepted. Not a bug in the game as such, but a gap in the command spec or
+// in how commands are generated from it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindSpot {
+    pub game_id: GameId,
+    pub seed: u64,
+    pub names: Vec<String>,
+    pub state: String,
+    pub player_renders: Vec<api::PlayerRender>,
+    // The last few commands tried, with why they were rejected.
+    pub rejected: Vec<(String, String)>,
+    pub attempts: usize,
+    // Whose turn was skipped, see Config::turn_attempts, or None when the
+    // whole game was abandoned. Every attempt on the turn is in `rejected`.
+    #[serde(default)]
+    pub player: Option<usize>,
+}
+
+impl BlindSpot {
+    // Named by canonical state so the same blind spot reached twice is saved
+    // once.
+    pub fn save(&self, storage: &dyn Storage, canonical: &Canonicalizer) -> Result<String, Error> {
+        let hash = canonical.hash(&self.state);
+        let key = match self.player {
+            Some(player) => format!("blind-spot-{}-player-{}.json", hash, player),
+            None => format!("blind-spot-{}.json", hash),
+        };
+        storage.put(&key, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(storage.locate(&key))
+    }
+}
@@ -0,0 +1,134 @@
+use failure::{bail, format_err, Error};
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::Summary;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    // Fraction of commands rejected with a user error.
+    UserErrors,
+    // Commands only partially parsed.
+    Partial,
+    ProtocolViolations,
+    ReadFailures,
+    // Average time to parse a sampled render, in milliseconds.
+    RenderLatency,
+}
+
+impl Metric {
+    fn name(self) -> &'static str {
+        match self {
+            Metric::UserErrors => "user-errors",
+            Metric::Partial => "partial",
+            Metric::ProtocolViolations => "violations",
+            Metric::ReadFailures => "read-failures",
+            Metric::RenderLatency => "render-ms",
+        }
+    }
+
+    fn measure(self, summary: &Summary) -> f64 {
+        let rows = summary.acceptance.rows.values();
+        match self {
+            Metric::UserErrors => {
+                let rejected: usize = rows.map(|r| r.rejected).sum();
+                if summary.tally.commands == 0 {
+                    0.0
+                } else {
+                    rejected as f64 / summary.tally.commands as f64
+                }
+            }
+            Metric::Partial => rows.map(|r| r.partial).sum::<usize>() as f64,
+            Metric::ProtocolViolations => summary.tally.protocol_violations as f64,
+            Metric::ReadFailures => {
+                summary.reads.rows.values().map(|r| r.failed).sum::<usize>() as f64
+            }
+            Metric::RenderLatency => summary.render_parse.avg_latency().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+// A limit on something that isn't an error by itself but shows the game or
+// generator drifting, such as `user-errors=5%` or `partial=10`. Rates are
+// given as a percentage, render-ms in milliseconds and everything else as a
+// count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Budget {
+    pub metric: Metric,
+    pub limit: f64,
+}
+
+impl FromStr for Budget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (metric, limit) = s
+            .split_once('=')
+            .ok_or_else(|| format_err!("expected METRIC=LIMIT, got {}", s))?;
+        let metric = match metric {
+            "user-errors" => Metric::UserErrors,
+            "partial" => Metric::Partial,
+            "violations" => Metric::ProtocolViolations,
+            "read-failures" => Metric::ReadFailures,
+            "render-ms" => Metric::RenderLatency,
+            _ => bail!(
+                "unknown budget {}, expected user-errors, partial, violations, read-failures or render-ms",
+                metric
+            ),
+        };
+        let limit = match (metric, limit.strip_suffix('%')) {
+            (Metric::UserErrors, Some(percent)) => percent.parse::<f64>()? / 100.0,
+            (Metric::UserErrors, None) => bail!("user-errors takes a percentage such as 5%"),
+            (Metric::RenderLatency, None) => limit.parse::<f64>()?,
+            (_, Some(_)) => bail!("{} takes a count", metric.name()),
+            (_, None) => limit.parse::<usize>()? as f64,
+        };
+        Ok(Budget { metric, limit })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breach {
+    pub budget: Budget,
+    pub actual: f64,
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.budget.metric {
+            Metric::UserErrors => write!(
+                f,
+                "user-errors at {:.2}% is over the budget of {:.2}%",
+                self.actual * 100.0,
+                self.budget.limit * 100.0
+            ),
+            Metric::RenderLatency => write!(
+                f,
+                "render-ms at {:.3}ms is over the budget of {}ms",
+                self.actual, self.budget.limit
+            ),
+            metric => write!(
+                f,
+                "{} at {} is over the budget of {}",
+                metric.name(),
+                self.actual,
+                self.budget.limit
+            ),
+        }
+    }
+}
+
+pub fn check(budgets: &[Budget], summary: &Summary) -> Vec<Breach> {
+    budgets
+        .iter()
+        .filter_map(|&budget| {
+            let actual = budget.metric.measure(summary);
+            if actual > budget.limit {
+                Some(Breach { budget, actual })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+
+use super::acceptance::Outcome;
+use super::spec_nodes::Choice;
+
+// What a campaign reached: the canonical hash of every state games were in,
+// and every spec branch taken by an accepted command. Rejected commands
+// don't move the game, so their branches don't count as reached.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coverage {
+    pub states: BTreeSet<String>,
+    pub branches: BTreeSet<Choice>,
+    // Accepted commands that didn't parse against their spec, so whose
+    // branches are unknown.
+    pub unattributed: usize,
+}
+
+impl Coverage {
+    pub fn reach(&mut self, state: String) {
+        self.states.insert(state);
+    }
+
+    pub fn record(&mut self, choices: Option<&[Choice]>, outcome: Outcome) {
+        if outcome != Outcome::Accepted {
+            return;
+        }
+        match choices {
+            Some(choices) => self.branches.extend(choices.iter().cloned()),
+            None => self.unattributed += 1,
+        }
+    }
+
+    // What this reached that the other didn't.
+    pub fn difference(&self, other: &Coverage) -> Coverage {
+        Coverage {
+            states: self.states.difference(&other.states).cloned().collect(),
+            branches: self.branches.difference(&other.branches).cloned().collect(),
+            unattributed: 0,
+        }
+    }
+
+    // What both reached.
+    pub fn intersection(&self, other: &Coverage) -> Coverage {
+        Coverage {
+            states: self.states.intersection(&other.states).cloned().collect(),
+            branches: self
+                .branches
+                .intersection(&other.branches)
+                .cloned()
+                .collect(),
+            unattributed: 0,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::stop::{Progress, StopCondition};
+
+// How far a campaign is through its limits: --duration, --steps and the
+// time, games and commands parts of --stop. Findings, plateaus and stop
+// files can end a campaign at any moment, so they're left out and the ETA
+// is the latest the campaign should finish.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Eta {
+    // The pipeline phase running, such as `2/3 random for 60s`.
+    #[serde(default)]
+    pub phase: Option<String>,
+    // Each limit with the share of it used so far.
+    pub budgets: Vec<(String, f64)>,
+    pub fraction: f64,
+    pub remaining_secs: Option<f64>,
+}
+
+impl Eta {
+    // None when the campaign has no limit it can be measured against, such
+    // as one running until it's stopped.
+    pub fn estimate(config: &Config, progress: &Progress, steps: usize) -> Option<Self> {
+        let mut budgets = vec![];
+        let mut fraction: Option<f64> = None;
+        let mut bound = |f: Option<f64>| {
+            if let Some(f) = f {
+                fraction = Some(fraction.map_or(f, |g| g.max(f)));
+            }
+        };
+        if let Some(d) = config.duration {
+            let f = ratio(progress.elapsed.as_secs_f64(), d.as_secs_f64());
+            budgets.push((format!("duration={}s", d.as_secs()), f));
+            bound(Some(f));
+        }
+        if let Some(max) = config.max_steps {
+            let f = ratio(steps as f64, max as f64);
+            budgets.push((format!("steps={}", max), f));
+            bound(Some(f));
+        }
+        if let Some(ref stop) = config.stop {
+            bound(stop_fraction(stop, progress, &mut budgets));
+        }
+        let fraction = fraction?.min(1.0);
+        let elapsed = progress.elapsed.as_secs_f64();
+        Some(Eta {
+            phase: config.phase.clone(),
+            budgets,
+            fraction,
+            remaining_secs: if fraction > 0.0 {
+                Some(elapsed * (1.0 - fraction) / fraction)
+            } else {
+                None
+            },
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let budgets: Vec<String> = self
+            .budgets
+            .iter()
+            .map(|(limit, f)| format!("{} {:.0}%", limit, f * 100.0))
+            .collect();
+        let mut out = format!(
+            "Progress: {:.0}% ({}), ETA {}",
+            self.fraction * 100.0,
+            budgets.join(", "),
+            match self.remaining_secs {
+                Some(secs) => render_secs(secs),
+                None => "unknown".to_string(),
+            }
+        );
+        if let Some(ref phase) = self.phase {
+            out.push_str(&format!(" in phase {}", phase));
+        }
+        out
+    }
+}
+
+fn ratio(used: f64, limit: f64) -> f64 {
+    if limit <= 0.0 {
+        1.0
+    } else {
+        (used / limit).min(1.0)
+    }
+}
+
+// Any condition being met stops the campaign, so it's as far along as its
+// furthest part, and all of them must be for the slowest. Parts that can't
+// be measured make a conjunction unmeasurable.
+fn stop_fraction(
+    condition: &StopCondition,
+    progress: &Progress,
+    budgets: &mut Vec<(String, f64)>,
+) -> Option<f64> {
+    let f = match *condition {
+        StopCondition::Elapsed(d) => ratio(progress.elapsed.as_secs_f64(), d.as_secs_f64()),
+        StopCondition::Games(n) => ratio(progress.games as f64, n as f64),
+        StopCondition::Commands(n) => ratio(progress.commands as f64, n as f64),
+        StopCondition::UniqueFindings(_) | StopCondition::Plateau | StopCondition::File(_) => {
+            return None
+        }
+        StopCondition::Any(ref cs) => {
+            return cs
+                .iter()
+                .filter_map(|c| stop_fraction(c, progress, budgets))
+                .reduce(f64::max)
+        }
+        StopCondition::All(ref cs) => {
+            let fs: Vec<Option<f64>> = cs
+                .iter()
+                .map(|c| stop_fraction(c, progress, budgets))
+                .collect();
+            return fs.into_iter().try_fold(1.0, |min, f| f.map(|f| f.min(min)));
+        }
+    };
+    budgets.push((condition.to_string(), f));
+    Some(f)
+}
+
+// Such as 45s, 12m30s or 3h05m.
+fn render_secs(secs: f64) -> String {
+    let secs = Duration::from_secs_f64(secs.max(0.0)).as_secs();
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}h{:02}m", s / (60 * 60), s / 60 % 60),
+    }
+}
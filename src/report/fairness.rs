@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+// Turn rotation across seats, summed over finished games with the same player
+// count. Random play should spread turns roughly evenly, a seat that's
+// skipped or favoured stands out after enough games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeatTotals {
+    pub games: usize,
+    // Times each seat appeared in whose_turn.
+    pub offered: Vec<usize>,
+    // Commands each seat had accepted.
+    pub moves: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Fairness {
+    pub by_players: BTreeMap<usize, SeatTotals>,
+    // How far a seat's share may stray from an even split, relative to it,
+    // before it's flagged. Games where the first player gets an extra turn
+    // need a looser tolerance.
+    pub tolerance: f64,
+    pub min_games: usize,
+}
+
+impl Default for Fairness {
+    fn default() -> Self {
+        Fairness::new(0.25)
+    }
+}
+
+fn add(totals: &mut Vec<usize>, counts: &[usize]) {
+    if totals.len() < counts.len() {
+        totals.resize(counts.len(), 0);
+    }
+    for (t, c) in totals.iter_mut().zip(counts) {
+        *t += c;
+    }
+}
+
+fn skewed(counts: &[usize], seats: usize, tolerance: f64) -> Vec<(usize, f64)> {
+    let total: usize = counts.iter().sum();
+    if total == 0 || seats == 0 {
+        return vec![];
+    }
+    let even = 1.0 / seats as f64;
+    (0..seats)
+        .map(|seat| {
+            (
+                seat,
+                counts.get(seat).copied().unwrap_or(0) as f64 / total as f64,
+            )
+        })
+        .filter(|(_, share)| (share - even).abs() / even > tolerance)
+        .collect()
+}
+
+impl Fairness {
+    pub fn new(tolerance: f64) -> Self {
+        Fairness {
+            by_players: BTreeMap::new(),
+            tolerance,
+            min_games: 30,
+        }
+    }
+
+    pub fn record(&mut self, players: usize, offered: &[usize], moves: &[usize]) {
+        let totals = self.by_players.entry(players).or_default();
+        totals.games += 1;
+        add(&mut totals.offered, offered);
+        add(&mut totals.moves, moves);
+    }
+
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = vec![];
+        for (&players, totals) in &self.by_players {
+            if totals.games < self.min_games {
+                continue;
+            }
+            for (what, counts) in &[("turns", &totals.offered), ("commands", &totals.moves)] {
+                for (seat, share) in skewed(counts, players, self.tolerance) {
+                    flags.push(format!(
+                        "seat {} had {:.1}% of {} over {} {}-player games, an even split is {:.1}%",
+                        seat,
+                        share * 100.0,
+                        what,
+                        totals.games,
+                        players,
+                        100.0 / players as f64
+                    ));
+                }
+            }
+        }
+        flags
+    }
+
+    pub fn render(&self) -> Option<String> {
+        let flags = self.flags();
+        if flags.is_empty() {
+            return None;
+        }
+        let mut out = "Turn fairness:\n".to_string();
+        for flag in flags {
+            out.push_str(&format!("  {}\n", flag));
+        }
+        Some(out)
+    }
+}
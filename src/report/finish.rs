@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use crate::engine::FuzzGame;
+
+// Used when no hook is configured: the last log of the finishing command,
+// numbers masked so "Alice wins with 42 points" and "Bob wins with 17 points"
+// count as the same way of ending.
+pub fn last_log_reason(game: &FuzzGame) -> Option<String> {
+    let log = game.logs.last()?;
+    let reason: String = log
+        .content
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_digit() { 'N' } else { c })
+        .collect();
+    if reason.is_empty() {
+        None
+    } else {
+        Some(reason)
+    }
+}
+
+pub const UNKNOWN: &str = "unknown";
+
+#[derive(Clone, Debug, Default)]
+pub struct FinishReasons {
+    pub counts: BTreeMap<String, usize>,
+    // Reasons the rules allow for, any still at zero when the campaign ends
+    // point at an end condition that can't be reached.
+    pub expected: Vec<String>,
+}
+
+impl FinishReasons {
+    pub fn new(expected: Vec<String>) -> Self {
+        FinishReasons {
+            counts: BTreeMap::new(),
+            expected,
+        }
+    }
+
+    pub fn record(&mut self, reason: &str) {
+        *self.counts.entry(reason.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn unseen(&self) -> Vec<&str> {
+        self.expected
+            .iter()
+            .filter(|r| !self.counts.contains_key(r.as_str()))
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn render(&self) -> Option<String> {
+        if self.counts.is_empty() && self.expected.is_empty() {
+            return None;
+        }
+        let total: usize = self.counts.values().sum();
+        let mut counts: Vec<(&String, &usize)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let mut out = "Finish reasons:\n".to_string();
+        for (reason, n) in counts {
+            out.push_str(&format!(
+                "  {:>7.1}% {:>8}  {}\n",
+                *n as f64 / total as f64 * 100.0,
+                n,
+                reason
+            ));
+        }
+        for reason in self.unseen() {
+            out.push_str(&format!(
+                "  never seen in {} finished games: {}\n",
+                total, reason
+            ));
+        }
+        Some(out)
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::finding;
+
+// Printed when a campaign starts and at the top of its summary, so a log
+// excerpt says what produced it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunHeader {
+    pub campaign_id: String,
+    pub fuzz_version: String,
+    pub seed: Option<u64>,
+    pub workers: usize,
+    pub strategy: String,
+    pub names: String,
+    pub oracles: Vec<String>,
+    pub target: Vec<String>,
+    pub target_hash: Option<String>,
+    // Changes with any setting that affects what the campaign does, but not
+    // with where its output goes.
+    pub config_digest: String,
+}
+
+impl RunHeader {
+    pub fn new(campaign_id: &str, config: &Config) -> Self {
+        let mut strategy = (config.strategy)().name().to_string();
+        if !config.phase_strategies.is_empty() {
+            let phases: Vec<String> = config
+                .phase_strategies
+                .iter()
+                .map(|(phase, s)| format!("{}: {}", phase, s().name()))
+                .collect();
+            strategy.push_str(&format!(" ({})", phases.join(", ")));
+        }
+        if let Some(ref bot) = config.bot {
+            strategy.push_str(&format!(" vs {} in {}", (bot.bot)().name(), bot.seats));
+        }
+        let names = (config.names)().name().to_string();
+        let registry = (config.oracles)();
+        let oracles: Vec<String> = registry
+            .stats()
+            .filter(|(_, enabled, _)| *enabled)
+            .map(|(name, _, _)| {
+                if registry.is_shadow(name) {
+                    format!("{} (shadow)", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect();
+        let settings = format!(
+            "{:?}",
+            (
+                (
+                    config.workers,
+                    config.seed,
+                    config.max_steps,
+                    config.duration,
+                    config.session_checkpoint,
+                    config.keep_going,
+                    &config.trace,
+                ),
+                (
+                    config.spectate,
+                    config.render_sample,
+                    config.check_player_refs,
+                    config.max_rejections,
+                    config.player_counts,
+                    config.warmup,
+                    &config.budgets,
+                ),
+                (
+                    config.fairness_tolerance,
+                    &config.expected_finish_reasons,
+                    &config.plateau,
+                    &config.autoscale,
+                    &config.stop,
+                    (&config.dedup.capacity, &config.dedup.bloom_bits),
+                ),
+                (&strategy, &names, &oracles, &config.target),
+            )
+        );
+        RunHeader {
+            campaign_id: campaign_id.to_string(),
+            fuzz_version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: config.seed,
+            workers: config.workers,
+            strategy,
+            names,
+            oracles,
+            target: config.target.clone(),
+            target_hash: config
+                .target
+                .iter()
+                .map(Path::new)
+                .find(|p| p.is_file())
+                .and_then(|p| fs::read(p).ok())
+                .map(|bytes| finding::bytes_hash(&bytes)),
+            config_digest: finding::bytes_hash(settings.as_bytes()),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "brdgme-fuzz {}   Campaign: {}   Seed: {}   Workers: {}   Config: {}\n",
+            self.fuzz_version,
+            self.campaign_id,
+            self.seed
+                .map_or_else(|| "random".to_string(), |s| s.to_string()),
+            self.workers,
+            self.config_digest
+        );
+        if !self.target.is_empty() {
+            out.push_str(&format!("Target: {}", self.target.join(" ")));
+            if let Some(ref hash) = self.target_hash {
+                out.push_str(&format!(" ({})", hash));
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "Strategy: {}   Names: {}   Oracles: {}\n",
+            self.strategy,
+            self.names,
+            self.oracles.join(", ")
+        ));
+        out
+    }
+}
@@ -0,0 +1,88 @@
+use failure::Error;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::acceptance::{AcceptanceRow, AcceptanceTable, Outcome};
+
+// Generation and acceptance per command head over the course of a campaign,
+// in fixed time buckets, for plotting whether some commands stop being
+// accepted as the campaign goes on.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    bucket: Duration,
+    // Keyed by bucket index and then command head.
+    rows: BTreeMap<(u64, String), AcceptanceRow>,
+}
+
+impl Heatmap {
+    pub fn new(bucket: Duration) -> Self {
+        Heatmap {
+            bucket: bucket.max(Duration::from_secs(1)),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    fn index(&self, elapsed: Duration) -> u64 {
+        elapsed.as_secs() / self.bucket.as_secs()
+    }
+
+    pub fn record(&mut self, elapsed: Duration, head: &str, outcome: Outcome) {
+        let row = self
+            .rows
+            .entry((self.index(elapsed), head.to_string()))
+            .or_default();
+        row.generated += 1;
+        match outcome {
+            Outcome::Accepted => row.accepted += 1,
+            Outcome::Rejected => row.rejected += 1,
+            Outcome::Partial => row.partial += 1,
+        }
+    }
+
+    // Everything in the table is counted in the bucket for `elapsed`.
+    pub fn merge(&mut self, elapsed: Duration, table: &AcceptanceTable) {
+        let index = self.index(elapsed);
+        for (head, r) in &table.rows {
+            let row = self.rows.entry((index, head.clone())).or_default();
+            row.generated += r.generated;
+            row.accepted += r.accepted;
+            row.rejected += r.rejected;
+            row.partial += r.partial;
+        }
+    }
+
+    pub fn csv(&self) -> String {
+        let mut out =
+            "bucket_start_secs,head,generated,accepted,rejected,partial,acceptance_rate\n"
+                .to_string();
+        for ((index, head), row) in &self.rows {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{:.4}\n",
+                index * self.bucket.as_secs(),
+                csv_field(head),
+                row.generated,
+                row.accepted,
+                row.rejected,
+                row.partial,
+                1.0 - row.rejection_rate()
+            ));
+        }
+        out
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.csv())?;
+        Ok(())
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
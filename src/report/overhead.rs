@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+// Where a worker's wall time went, to tell a slow game from a slow fuzzer.
+// Oracles making requests of their own count as checking, pausing for
+// Config::command_delay as pacing, and whatever's left is the fuzzer's
+// bookkeeping.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Overhead {
+    pub generating: Duration,
+    pub transport: Duration,
+    pub checking: Duration,
+    pub pacing: Duration,
+    pub total: Duration,
+}
+
+impl Overhead {
+    pub fn add(&mut self, other: &Overhead) {
+        self.generating += other.generating;
+        self.transport += other.transport;
+        self.checking += other.checking;
+        self.pacing += other.pacing;
+        self.total += other.total;
+    }
+
+    pub fn bookkeeping(&self) -> Duration {
+        self.total
+            .saturating_sub(self.generating + self.transport + self.checking + self.pacing)
+    }
+
+    pub fn render_line(&self) -> String {
+        let total = self.total.as_secs_f64().max(f64::EPSILON);
+        let pct = |d: Duration| d.as_secs_f64() / total * 100.0;
+        let mut line = format!(
+            "generating {:.1}%   transport {:.1}%   checking {:.1}%   bookkeeping {:.1}%",
+            pct(self.generating),
+            pct(self.transport),
+            pct(self.checking),
+            pct(self.bookkeeping())
+        );
+        if self.pacing > Duration::default() {
+            line.push_str(&format!("   pacing {:.1}%", pct(self.pacing)));
+        }
+        line
+    }
+
+    // All workers together, then each on its own when there's more than one.
+    pub fn render(workers: &[Overhead]) -> Option<String> {
+        let mut all = Overhead::default();
+        for w in workers {
+            all.add(w);
+        }
+        if all.total == Duration::default() {
+            return None;
+        }
+        let mut out = format!("Time spent: {}\n", all.render_line());
+        if workers.len() > 1 {
+            for (worker, w) in workers.iter().enumerate() {
+                out.push_str(&format!("  worker {:<3} {}\n", worker, w.render_line()));
+            }
+        }
+        Some(out)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use crate::engine::Placings;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeatWins {
+    pub games: usize,
+    // Games each seat won outright or shared the win of.
+    pub wins: Vec<usize>,
+    pub shared: usize,
+}
+
+// Who won finished games, by seat and player count. Under random play a seat
+// that wins far more or less than its share usually points at a scoring or
+// tie-break bug rather than strategy.
+#[derive(Clone, Debug, Default)]
+pub struct WinTable {
+    pub by_players: BTreeMap<usize, SeatWins>,
+}
+
+impl WinTable {
+    pub fn record(&mut self, players: usize, placings: &Placings) {
+        let row = self.by_players.entry(players).or_default();
+        if row.wins.len() < players {
+            row.wins.resize(players, 0);
+        }
+        row.games += 1;
+        let winners = placings.winners();
+        if winners.len() > 1 {
+            row.shared += 1;
+        }
+        for p in winners {
+            if let Some(w) = row.wins.get_mut(p) {
+                *w += 1;
+            }
+        }
+    }
+
+    pub fn render(&self) -> Option<String> {
+        if self.by_players.is_empty() {
+            return None;
+        }
+        let mut out = "Wins by seat:\n".to_string();
+        for (players, row) in &self.by_players {
+            let seats = row
+                .wins
+                .iter()
+                .enumerate()
+                .map(|(seat, &w)| {
+                    format!(
+                        "seat {} {:.1}%",
+                        seat,
+                        w as f64 / row.games.max(1) as f64 * 100.0
+                    )
+                })
+                .collect::<Vec<_>>();
+            out.push_str(&format!(
+                "  {} players, {} games ({} shared): {}\n",
+                players,
+                row.games,
+                row.shared,
+                seats.join(", ")
+            ));
+        }
+        Some(out)
+    }
+}
@@ -0,0 +1,80 @@
+use failure::Error;
+use prometheus::{Encoder, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+
+use std::sync::OnceLock;
+
+use super::FuzzTally;
+
+struct Metrics {
+    registry: Registry,
+    started: IntGauge,
+    finished: IntGauge,
+    commands: IntGauge,
+    invalid_input: IntGauge,
+    // Percent of the campaign's limits used and seconds left, see
+    // report::eta. Left at -1 when the campaign has no limits.
+    progress: IntGauge,
+    eta: IntGauge,
+    // Always 1, labelled with the running campaign's id.
+    campaign: IntGaugeVec,
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| {
+        let registry = Registry::new_custom(Some("brdgme_fuzz".to_string()), None)
+            .expect("failed to create prometheus registry");
+        let gauge = |name: &str, help: &str| {
+            let g = IntGauge::new(name, help).expect("invalid prometheus gauge");
+            registry
+                .register(Box::new(g.clone()))
+                .expect("failed to register prometheus gauge");
+            g
+        };
+        let campaign = IntGaugeVec::new(
+            Opts::new("campaign", "The running campaign"),
+            &["campaign_id"],
+        )
+        .expect("invalid prometheus gauge");
+        registry
+            .register(Box::new(campaign.clone()))
+            .expect("failed to register prometheus gauge");
+        Metrics {
+            campaign,
+            started: gauge("games_started", "Games started"),
+            finished: gauge("games_finished", "Games finished"),
+            commands: gauge("commands", "Commands sent"),
+            invalid_input: gauge("commands_failed", "Commands rejected as user errors"),
+            progress: gauge("progress_percent", "Percent of the campaign's limits used"),
+            eta: gauge(
+                "eta_seconds",
+                "Seconds until the campaign reaches its limits",
+            ),
+            registry,
+        }
+    })
+}
+
+pub fn observe(tally: &FuzzTally) {
+    let m = metrics();
+    m.started.set(tally.started as i64);
+    m.finished.set(tally.finished as i64);
+    m.commands.set(tally.commands as i64);
+    m.invalid_input.set(tally.invalid_input as i64);
+    let progress = tally.progress.as_ref();
+    m.progress
+        .set(progress.map_or(-1, |p| (p.fraction * 100.0) as i64));
+    m.eta.set(
+        progress
+            .and_then(|p| p.remaining_secs)
+            .map_or(-1, |s| s as i64),
+    );
+    m.campaign.reset();
+    m.campaign.with_label_values(&[&tally.campaign_id]).set(1);
+}
+
+pub fn encode() -> Result<String, Error> {
+    let mut buf = vec![];
+    TextEncoder::new().encode(&metrics().registry.gather(), &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadRow {
+    pub requests: usize,
+    pub failed: usize,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl ReadRow {
+    pub fn avg_latency(&self) -> Duration {
+        if self.requests == 0 {
+            return Duration::default();
+        }
+        self.total_latency / self.requests as u32
+    }
+}
+
+// Latency of the read-only requests interleaved between plays, kept apart
+// from command latency since production traffic is mostly reads.
+#[derive(Clone, Debug, Default)]
+pub struct ReadTable {
+    pub rows: BTreeMap<String, ReadRow>,
+}
+
+impl ReadTable {
+    pub fn record(&mut self, kind: &str, latency: Duration, ok: bool) {
+        let row = self.rows.entry(kind.to_string()).or_default();
+        row.requests += 1;
+        if !ok {
+            row.failed += 1;
+        }
+        row.total_latency += latency;
+        row.max_latency = row.max_latency.max(latency);
+    }
+
+    pub fn render(&self) -> Option<String> {
+        if self.rows.is_empty() {
+            return None;
+        }
+        let mut out = format!(
+            "{:<20} {:>10} {:>10} {:>12} {:>12}\n",
+            "Read request", "Requests", "Failed", "Avg", "Max"
+        );
+        for (kind, row) in &self.rows {
+            out.push_str(&format!(
+                "{:<20} {:>10} {:>10} {:>12} {:>12}\n",
+                kind,
+                row.requests,
+                row.failed,
+                format!("{:?}", row.avg_latency()),
+                format!("{:?}", row.max_latency)
+            ));
+        }
+        Some(out)
+    }
+}
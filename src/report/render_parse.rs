@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+// Time spent parsing sampled renders as a client would. Slow markup hurts
+// every client even when the game logic is fast.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderParse {
+    pub samples: usize,
+    pub failed: usize,
+    pub bytes: usize,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl RenderParse {
+    pub fn record(&mut self, latency: Duration, bytes: usize, ok: bool) {
+        self.samples += 1;
+        if !ok {
+            self.failed += 1;
+        }
+        self.bytes += bytes;
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+    }
+
+    pub fn avg_latency(&self) -> Duration {
+        if self.samples == 0 {
+            return Duration::default();
+        }
+        self.total_latency / self.samples as u32
+    }
+
+    pub fn render(&self) -> Option<String> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(format!(
+            "Render parsing: {} renders sampled, {} failed, avg {} bytes, avg {:?}, max {:?}\n",
+            self.samples,
+            self.failed,
+            self.bytes / self.samples,
+            self.avg_latency(),
+            self.max_latency
+        ))
+    }
+}
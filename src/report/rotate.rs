@@ -0,0 +1,81 @@
+use failure::Error;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub struct RotatingLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+    max_bytes: u64,
+    max_age: Option<Duration>,
+    keep: usize,
+}
+
+impl RotatingLog {
+    pub fn open(
+        path: impl Into<PathBuf>,
+        max_bytes: u64,
+        max_age: Option<Duration>,
+        keep: usize,
+    ) -> Result<Self, Error> {
+        let path = path.into();
+        let file = append(&path)?;
+        Ok(RotatingLog {
+            size: file.metadata()?.len(),
+            path,
+            file,
+            opened_at: Instant::now(),
+            max_bytes,
+            max_age,
+            keep,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<(), Error> {
+        if self.size >= self.max_bytes
+            || self.max_age.is_some_and(|a| self.opened_at.elapsed() >= a)
+        {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(numbered(&self.path, self.keep));
+            for n in (1..self.keep).rev() {
+                let from = numbered(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, numbered(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+        self.file = append(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+}
+
+fn append(path: &Path) -> Result<File, Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
@@ -0,0 +1,272 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use brdgme_game::command::Spec;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::acceptance::Outcome;
+
+// Parses are cut off past this many alternatives, which only ambiguous specs
+// such as long Many chains ever reach.
+const MAX_ALTERNATIVES: usize = 32;
+
+// Which way a generated command went at one node of the spec, such as the
+// value picked for an Enum or whether an Opt was filled in. Nodes are named
+// by their path through the spec, using Doc names where there are any.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Choice {
+    pub node: String,
+    pub choice: String,
+}
+
+type Alternative = (usize, Vec<Choice>);
+
+// Works out the choices behind a generated command by parsing it against
+// the spec it was generated from. None if it doesn't parse, which happens
+// when a strategy doesn't stick to the spec.
+pub fn attribute(spec: &Spec, command: &str, names: &[String]) -> Option<Vec<Choice>> {
+    let input = command.to_lowercase();
+    walk(spec, &input, 0, "", names)
+        .into_iter()
+        .find(|(end, _)| input[*end..].trim().is_empty())
+        .map(|(_, choices)| choices)
+}
+
+fn choice(node: &str, kind: &str, choice: impl Into<String>) -> Choice {
+    Choice {
+        node: format!("{}:{}", if node.is_empty() { "/" } else { node }, kind),
+        choice: choice.into(),
+    }
+}
+
+fn walk(spec: &Spec, input: &str, pos: usize, path: &str, names: &[String]) -> Vec<Alternative> {
+    let rest = &input[pos..];
+    let mut alternatives = match *spec {
+        Spec::Int { min, max } => {
+            let digits = rest.strip_prefix('-').unwrap_or(rest);
+            let len = digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+            let end = rest.len() - digits.len() + len;
+            match rest[..end].parse::<i64>() {
+                Ok(v) if len > 0 => {
+                    let bucket = if min.is_some_and(|m| v < i64::from(m)) {
+                        "below min"
+                    } else if max.is_some_and(|m| v > i64::from(m)) {
+                        "above max"
+                    } else if min.is_some_and(|m| v == i64::from(m)) {
+                        "min"
+                    } else if max.is_some_and(|m| v == i64::from(m)) {
+                        "max"
+                    } else {
+                        "within"
+                    };
+                    vec![(pos + end, vec![choice(path, "int", bucket)])]
+                }
+                _ => vec![],
+            }
+        }
+        Spec::Token(ref token) => {
+            let token = token.to_lowercase();
+            if rest.starts_with(&token) {
+                vec![(pos + token.len(), vec![])]
+            } else {
+                vec![]
+            }
+        }
+        Spec::Enum { ref values, exact } => {
+            let word_len = rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(rest.len());
+            values
+                .iter()
+                .filter_map(|v| {
+                    let lower = v.to_lowercase();
+                    if rest.starts_with(&lower) {
+                        Some((pos + lower.len(), vec![choice(path, "enum", v.as_str())]))
+                    } else if !exact && word_len > 0 && lower.starts_with(&rest[..word_len]) {
+                        Some((pos + word_len, vec![choice(path, "enum", v.as_str())]))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        }
+        Spec::OneOf(ref specs) => specs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s)| {
+                walk(s, input, pos, &format!("{}/{}", path, i), names)
+                    .into_iter()
+                    .map(move |(end, mut choices)| {
+                        choices.insert(0, choice(path, "oneof", i.to_string()));
+                        (end, choices)
+                    })
+            })
+            .collect(),
+        Spec::Chain(ref specs) => {
+            let mut alternatives: Vec<Alternative> = vec![(pos, vec![])];
+            for (i, s) in specs.iter().enumerate() {
+                let child = format!("{}/{}", path, i);
+                alternatives = alternatives
+                    .into_iter()
+                    .flat_map(|(at, choices)| {
+                        walk(s, input, at, &child, names)
+                            .into_iter()
+                            .map(move |(end, more)| {
+                                let mut all = choices.clone();
+                                all.extend(more);
+                                (end, all)
+                            })
+                    })
+                    .take(MAX_ALTERNATIVES)
+                    .collect();
+            }
+            alternatives
+        }
+        Spec::Opt(ref s) => {
+            let mut alternatives: Vec<Alternative> = walk(s, input, pos, path, names)
+                .into_iter()
+                .map(|(end, mut choices)| {
+                    choices.insert(0, choice(path, "opt", "present"));
+                    (end, choices)
+                })
+                .collect();
+            alternatives.push((pos, vec![choice(path, "opt", "absent")]));
+            alternatives
+        }
+        Spec::Many {
+            ref spec,
+            min,
+            max,
+            ref delim,
+        } => {
+            let delim = delim.to_lowercase();
+            let item = format!("{}/item", path);
+            let mut alternatives = vec![];
+            let mut current: Vec<Alternative> = vec![(pos, vec![])];
+            let mut count = 0;
+            while !current.is_empty() && max.is_none_or(|m| count <= m) {
+                if min.is_none_or(|m| count >= m) {
+                    alternatives.extend(current.iter().map(|(end, choices)| {
+                        let mut choices = choices.clone();
+                        choices.insert(0, choice(path, "many", count.to_string()));
+                        (*end, choices)
+                    }));
+                }
+                current = current
+                    .into_iter()
+                    .filter_map(|(at, choices)| {
+                        let at = if count == 0 {
+                            at
+                        } else if input[at..].starts_with(&delim) {
+                            at + delim.len()
+                        } else {
+                            return None;
+                        };
+                        Some((at, choices))
+                    })
+                    .flat_map(|(at, choices)| {
+                        walk(spec, input, at, &item, names)
+                            .into_iter()
+                            .filter(move |(end, _)| *end > at)
+                            .map(move |(end, more)| {
+                                let mut all = choices.clone();
+                                all.extend(more);
+                                (end, all)
+                            })
+                    })
+                    .take(MAX_ALTERNATIVES)
+                    .collect();
+                count += 1;
+            }
+            // Longest first, as generated lists are usually matched whole.
+            alternatives.reverse();
+            alternatives
+        }
+        Spec::Doc {
+            ref name, ref spec, ..
+        } => walk(spec, input, pos, &format!("{}/{}", path, name), names),
+        Spec::Player => names
+            .iter()
+            .map(|n| n.to_lowercase())
+            .enumerate()
+            .filter(|(_, n)| rest.starts_with(n.as_str()))
+            .map(|(i, n)| (pos + n.len(), vec![choice(path, "player", i.to_string())]))
+            .collect(),
+        Spec::Space => {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            if len > 0 {
+                vec![(pos + len, vec![])]
+            } else {
+                vec![]
+            }
+        }
+    };
+    alternatives.truncate(MAX_ALTERNATIVES);
+    alternatives
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChoiceRow {
+    pub node: String,
+    pub choice: String,
+    pub generated: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub partial: usize,
+}
+
+// How often each choice at each spec node was generated and how the game
+// took it, for tuning brdgme_rand_bot's weighting with real acceptance data.
+#[derive(Clone, Debug, Default)]
+pub struct SpecNodeTable {
+    pub rows: BTreeMap<Choice, ChoiceRow>,
+    // Commands that didn't parse against their spec.
+    pub unattributed: usize,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    unattributed: usize,
+    choices: Vec<&'a ChoiceRow>,
+}
+
+impl SpecNodeTable {
+    pub fn record(&mut self, choices: Option<Vec<Choice>>, outcome: Outcome) {
+        let choices = match choices {
+            Some(choices) => choices,
+            None => {
+                self.unattributed += 1;
+                return;
+            }
+        };
+        for c in choices {
+            let row = self.rows.entry(c.clone()).or_insert_with(|| ChoiceRow {
+                node: c.node,
+                choice: c.choice,
+                ..ChoiceRow::default()
+            });
+            row.generated += 1;
+            match outcome {
+                Outcome::Accepted => row.accepted += 1,
+                Outcome::Rejected => row.rejected += 1,
+                Outcome::Partial => row.partial += 1,
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let export = Export {
+            unattributed: self.unattributed,
+            choices: self.rows.values().collect(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(())
+    }
+}
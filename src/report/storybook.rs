@@ -0,0 +1,148 @@
+use brdgme_cmd::api;
+
+use crate::backend::trace::TraceEntry;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Chapter {
+    pub title: String,
+    pub command: Option<String>,
+    pub logs: Vec<String>,
+    pub board: Option<String>,
+    pub outcome: Option<String>,
+}
+
+pub fn chapters(entries: &[TraceEntry]) -> Vec<Chapter> {
+    let mut turn = 0;
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let mut chapter = match entry.request {
+                api::Request::New { players } => Chapter {
+                    title: format!("New game with {} players", players),
+                    ..Chapter::default()
+                },
+                api::Request::Play {
+                    player,
+                    ref command,
+                    ref names,
+                    ..
+                } => {
+                    turn += 1;
+                    Chapter {
+                        title: format!(
+                            "Turn {}: {}",
+                            turn,
+                            names
+                                .get(player)
+                                .cloned()
+                                .unwrap_or_else(|| format!("player {}", player))
+                        ),
+                        command: Some(command.clone()),
+                        ..Chapter::default()
+                    }
+                }
+                _ => return None,
+            };
+            match (&entry.response, &entry.error) {
+                (
+                    Some(api::Response::New {
+                        logs,
+                        public_render,
+                        ..
+                    }),
+                    _,
+                )
+                | (
+                    Some(api::Response::Play {
+                        logs,
+                        public_render,
+                        ..
+                    }),
+                    _,
+                ) => {
+                    chapter.logs = logs.iter().map(|l| l.content.clone()).collect();
+                    chapter.board = Some(public_render.pub_render.clone());
+                }
+                (Some(api::Response::UserError { message }), _) => {
+                    chapter.outcome = Some(format!("Rejected: {}", message))
+                }
+                (Some(other), _) => chapter.outcome = Some(format!("Unexpected: {:?}", other)),
+                (None, Some(error)) => chapter.outcome = Some(format!("Error: {}", error)),
+                (None, None) => {}
+            }
+            Some(chapter)
+        })
+        .collect()
+}
+
+pub fn render(title: &str, chapters: &[Chapter], format: Format) -> String {
+    match format {
+        Format::Markdown => render_markdown(title, chapters),
+        Format::Html => render_html(title, chapters),
+    }
+}
+
+fn render_markdown(title: &str, chapters: &[Chapter]) -> String {
+    let mut out = format!("# {}\n", title);
+    for c in chapters {
+        out.push_str(&format!("\n## {}\n\n", c.title));
+        if let Some(ref command) = c.command {
+            out.push_str(&format!("Command: `{}`\n\n", command));
+        }
+        if let Some(ref outcome) = c.outcome {
+            out.push_str(&format!("**{}**\n\n", outcome));
+        }
+        for log in &c.logs {
+            out.push_str(&format!("> {}\n", log));
+        }
+        if let Some(ref board) = c.board {
+            out.push_str(&format!("\n```\n{}\n```\n", board));
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, chapters: &[Chapter]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head><body>\n<h1>{0}</h1>\n",
+        escape(title)
+    );
+    for c in chapters {
+        out.push_str(&format!("<section>\n<h2>{}</h2>\n", escape(&c.title)));
+        if let Some(ref command) = c.command {
+            out.push_str(&format!(
+                "<p>Command: <code>{}</code></p>\n",
+                escape(command)
+            ));
+        }
+        if let Some(ref outcome) = c.outcome {
+            out.push_str(&format!("<p><strong>{}</strong></p>\n", escape(outcome)));
+        }
+        if !c.logs.is_empty() {
+            out.push_str("<blockquote>\n");
+            for log in &c.logs {
+                out.push_str(&format!("<p>{}</p>\n", escape(log)));
+            }
+            out.push_str("</blockquote>\n");
+        }
+        if let Some(ref board) = c.board {
+            out.push_str(&format!("<pre>{}</pre>\n", escape(board)));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,156 @@
+use brdgme_cmd::requester::Requester;
+use failure::{format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cancel::CancelToken;
+use crate::finding::Finding;
+use crate::oracle::OracleRegistry;
+use crate::replay::{self, Reproduction};
+
+// Where a known finding is in triage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Triage {
+    // Confirmed and awaiting a fix.
+    #[default]
+    Known,
+    // Not going to be fixed.
+    WontFix,
+    // Marked fixed, so seeing it again is a regression and fails the run.
+    Fixed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Suppression {
+    pub signature: String,
+    #[serde(default)]
+    pub state: Triage,
+    #[serde(default)]
+    pub note: String,
+    // A saved finding to replay when re-verifying, see Suppressions::reverify.
+    #[serde(default)]
+    pub artifact: Option<PathBuf>,
+}
+
+// A known issues file, a JSON list of suppressions. Known and wontfix
+// findings are still reported and counted but don't fail the run, so
+// fuzzing can carry on while a bug waits for its fix.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Suppressions {
+    by_signature: BTreeMap<String, Suppression>,
+}
+
+impl Suppressions {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let list: Vec<Suppression> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Suppressions {
+            by_signature: list.into_iter().map(|s| (s.signature.clone(), s)).collect(),
+        })
+    }
+
+    pub fn get(&self, signature: &str) -> Option<&Suppression> {
+        self.by_signature.get(signature)
+    }
+
+    pub fn suppresses(&self, finding: &Finding) -> bool {
+        matches!(
+            self.get(&finding.signature).map(|s| s.state),
+            Some(Triage::Known) | Some(Triage::WontFix)
+        )
+    }
+
+    pub fn regressed(&self, finding: &Finding) -> bool {
+        matches!(
+            self.get(&finding.signature).map(|s| s.state),
+            Some(Triage::Fixed)
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_signature.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Suppression> {
+        self.by_signature.values()
+    }
+
+    // Replaces any suppression with the same signature.
+    pub fn insert(&mut self, suppression: Suppression) {
+        self.by_signature
+            .insert(suppression.signature.clone(), suppression);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let list: Vec<&Suppression> = self.entries().collect();
+        fs::write(path, serde_json::to_string_pretty(&list)?)?;
+        Ok(())
+    }
+
+    // Replays the artifact behind each suppression, so ones that no longer
+    // reproduce can be pruned. Suppressions without an artifact are skipped,
+    // as is everything left once cancelled.
+    pub fn reverify(
+        &self,
+        client: &mut dyn Requester,
+        oracles: &mut OracleRegistry,
+        cancel: &CancelToken,
+    ) -> BTreeMap<String, Result<Reproduction, String>> {
+        self.by_signature
+            .values()
+            .take_while(|_| !cancel.is_cancelled())
+            .filter_map(|s| {
+                let path = s.artifact.as_ref()?;
+                let result = Finding::load(path)
+                    .map_err(|e| format_err!("{}: {}", path.display(), e))
+                    .and_then(|f| {
+                        if f.signature != s.signature {
+                            return Err(format_err!(
+                                "{} is a finding for {}",
+                                path.display(),
+                                f.signature
+                            ));
+                        }
+                        replay::check_repro(client, &f, oracles, cancel)
+                    })
+                    .map_err(|e| e.to_string());
+                Some((s.signature.clone(), result))
+            })
+            .collect()
+    }
+
+    // What re-verification turned up that the file should be updated for.
+    pub fn render_reverified(
+        &self,
+        reverified: &BTreeMap<String, Result<Reproduction, String>>,
+    ) -> Option<String> {
+        let mut out = String::new();
+        for (signature, result) in reverified {
+            let state = self.get(signature).map(|s| s.state);
+            let line = match (state, result) {
+                (Some(Triage::Fixed), Ok(Reproduction::Reproduced)) => {
+                    "marked fixed, still reproduces"
+                }
+                (Some(Triage::Known), Ok(Reproduction::NotReproduced))
+                | (Some(Triage::WontFix), Ok(Reproduction::NotReproduced)) => {
+                    "no longer reproduces, can be pruned"
+                }
+                (_, Ok(Reproduction::Inconclusive)) => "inconclusive",
+                (_, Err(ref e)) => {
+                    out.push_str(&format!("  {}: could not replay, {}\n", signature, e));
+                    continue;
+                }
+                _ => continue,
+            };
+            out.push_str(&format!("  {}: {}\n", signature, line));
+        }
+        if out.is_empty() {
+            None
+        } else {
+            Some(format!("Re-verified suppressions:\n{}", out))
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use crossterm::{cursor, execute, terminal};
+
+use std::io::stderr;
+
+use super::FuzzTally;
+
+pub fn draw(tally: &FuzzTally) {
+    let _ = execute!(
+        stderr(),
+        cursor::MoveToColumn(0),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        crossterm::style::Print(tally.render()),
+    );
+}
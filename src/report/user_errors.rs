@@ -0,0 +1,113 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::strategy::{self, FuzzRng};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserErrorSample {
+    pub command: String,
+    pub message: String,
+    pub state_hash: String,
+}
+
+// Counts user errors by message and keeps a uniform reservoir sample of them.
+// Rejections are expected when fuzzing, but one message suddenly dominating
+// usually means a parser regression rather than bad luck.
+#[derive(Clone, Debug)]
+pub struct UserErrors {
+    pub counts: HashMap<String, usize>,
+    pub samples: Vec<UserErrorSample>,
+    pub seen: usize,
+    capacity: usize,
+    rng: FuzzRng,
+}
+
+impl Default for UserErrors {
+    fn default() -> Self {
+        UserErrors::new(100)
+    }
+}
+
+// Numbers vary with the command, so they're masked to group messages the
+// same way finding signatures are.
+fn message_key(message: &str) -> String {
+    message
+        .chars()
+        .map(|c| if c.is_ascii_digit() { 'N' } else { c })
+        .collect()
+}
+
+impl UserErrors {
+    pub fn new(capacity: usize) -> Self {
+        UserErrors {
+            counts: HashMap::new(),
+            samples: vec![],
+            seen: 0,
+            capacity,
+            rng: strategy::seeded_rng(0),
+        }
+    }
+
+    pub fn record(&mut self, sample: UserErrorSample) {
+        *self.counts.entry(message_key(&sample.message)).or_insert(0) += 1;
+        self.seen += 1;
+        if self.samples.len() < self.capacity {
+            self.samples.push(sample);
+        } else {
+            let i = self.rng.gen_range(0, self.seen);
+            if i < self.capacity {
+                self.samples[i] = sample;
+            }
+        }
+    }
+
+    // Combines two reservoirs so the result is still uniform over everything
+    // either of them saw.
+    pub fn merge(&mut self, other: UserErrors) {
+        for (message, n) in other.counts {
+            *self.counts.entry(message).or_insert(0) += n;
+        }
+        let (mut mine, mut theirs) = (std::mem::take(&mut self.samples), other.samples);
+        let (mut mine_seen, mut theirs_seen) = (self.seen, other.seen);
+        self.seen += other.seen;
+        while self.samples.len() < self.capacity && (!mine.is_empty() || !theirs.is_empty()) {
+            let from_mine = !mine.is_empty()
+                && (theirs.is_empty()
+                    || self.rng.gen_range(0, mine_seen + theirs_seen) < mine_seen);
+            let (pool, seen) = if from_mine {
+                (&mut mine, &mut mine_seen)
+            } else {
+                (&mut theirs, &mut theirs_seen)
+            };
+            // Each remaining sample stands for an equal share of what its
+            // reservoir saw.
+            *seen -= *seen / pool.len();
+            let i = self.rng.gen_range(0, pool.len());
+            self.samples.push(pool.swap_remove(i));
+        }
+    }
+
+    pub fn top(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut top: Vec<(&str, usize)> =
+            self.counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        top.truncate(n);
+        top
+    }
+
+    pub fn render_top(&self, n: usize) -> Option<String> {
+        if self.counts.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Top user errors: {}",
+            self.top(n)
+                .iter()
+                .map(|(m, c)| format!("{:?} x{}", m, c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
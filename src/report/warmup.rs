@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use super::FuzzTally;
+
+// Totals for a stretch of the campaign, for throughput and latency that
+// leave out worker start-up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Window {
+    pub elapsed: Duration,
+    pub commands: usize,
+    pub invalid_input: usize,
+    pub finished: usize,
+    pub finished_turns: usize,
+    pub latency: Duration,
+}
+
+impl Window {
+    pub fn new(tally: &FuzzTally, elapsed: Duration, latency: Duration) -> Self {
+        Window {
+            elapsed,
+            commands: tally.commands,
+            invalid_input: tally.invalid_input,
+            finished: tally.finished,
+            finished_turns: tally.finished_turns,
+            latency,
+        }
+    }
+
+    // What happened between an earlier window and this one.
+    pub fn since(&self, earlier: &Window) -> Window {
+        Window {
+            elapsed: self.elapsed.saturating_sub(earlier.elapsed),
+            commands: self.commands - earlier.commands,
+            invalid_input: self.invalid_input - earlier.invalid_input,
+            finished: self.finished - earlier.finished,
+            finished_turns: self.finished_turns - earlier.finished_turns,
+            latency: self.latency.saturating_sub(earlier.latency),
+        }
+    }
+
+    // Enough of a tally to build a baseline from.
+    pub fn tally(&self) -> FuzzTally {
+        FuzzTally {
+            commands: self.commands,
+            invalid_input: self.invalid_input,
+            finished: self.finished,
+            finished_turns: self.finished_turns,
+            ..FuzzTally::default()
+        }
+    }
+
+    pub fn commands_per_sec(&self) -> f64 {
+        self.commands as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn avg_latency(&self) -> Duration {
+        if self.commands == 0 {
+            return Duration::default();
+        }
+        self.latency / self.commands as u32
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "{:.1}s, {} commands, {:.1} commands/s, {} games finished, avg latency {:?}",
+            self.elapsed.as_secs_f64(),
+            self.commands,
+            self.commands_per_sec(),
+            self.finished,
+            self.avg_latency()
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Windows {
+    pub warmup: Window,
+    pub steady: Window,
+}
+
+impl Windows {
+    pub fn render(&self) -> String {
+        format!(
+            "Warm-up:      {}\nSteady state: {}\n",
+            self.warmup.render(),
+            self.steady.render()
+        )
+    }
+}
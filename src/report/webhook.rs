@@ -0,0 +1,182 @@
+use failure::{bail, Error};
+#[cfg(not(feature = "embedded"))]
+use serde_json::{json, Map, Value};
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+#[cfg(not(feature = "embedded"))]
+use std::sync::mpsc::{channel, Sender};
+#[cfg(not(feature = "embedded"))]
+use std::thread::{self, JoinHandle};
+
+use super::Summary;
+use crate::finding::Finding;
+
+pub const FINDING_TEMPLATE: &str =
+    "New {severity} finding {id}: {message}\nSignature: {signature}\n{artifact}";
+pub const FINISHED_TEMPLATE: &str =
+    "Campaign finished after {elapsed}s: {findings} unique findings, {commands} commands, {games} games";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Slack,
+    Discord,
+    // The rendered text plus every template field as JSON.
+    Generic,
+}
+
+// Where to post new unique findings and the end of a campaign, given as
+// `slack:URL`, `discord:URL` or a plain URL for the generic format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Webhook {
+    pub url: String,
+    pub format: Format,
+    pub finding_template: String,
+    pub finished_template: String,
+}
+
+impl FromStr for Webhook {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (format, url) = match s.split_once(':') {
+            Some(("slack", url)) => (Format::Slack, url),
+            Some(("discord", url)) => (Format::Discord, url),
+            _ => (Format::Generic, s),
+        };
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("expected an http or https URL, got {}", url);
+        }
+        Ok(Webhook {
+            url: url.to_string(),
+            format,
+            finding_template: FINDING_TEMPLATE.to_string(),
+            finished_template: FINISHED_TEMPLATE.to_string(),
+        })
+    }
+}
+
+#[cfg(not(feature = "embedded"))]
+impl Webhook {
+    fn payload(&self, event: &str, template: &str, fields: &BTreeMap<&str, String>) -> Value {
+        let text = render(template, fields);
+        match self.format {
+            Format::Slack => json!({ "text": text }),
+            Format::Discord => json!({ "content": text }),
+            Format::Generic => {
+                let mut payload: Map<String, Value> = fields
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Value::String(v.clone())))
+                    .collect();
+                payload.insert("event".to_string(), json!(event));
+                payload.insert("text".to_string(), json!(text));
+                Value::Object(payload)
+            }
+        }
+    }
+}
+
+// Replaces `{field}` with its value, leaving unknown fields as they are.
+pub fn render(template: &str, fields: &BTreeMap<&str, String>) -> String {
+    let mut out = template.to_string();
+    for (k, v) in fields {
+        out = out.replace(&format!("{{{}}}", k), v);
+    }
+    out
+}
+
+pub fn finding_fields(finding: &Finding, artifact: Option<&str>) -> BTreeMap<&'static str, String> {
+    let mut fields = BTreeMap::new();
+    fields.insert("id", finding.id.clone());
+    fields.insert("severity", format!("{:?}", finding.severity).to_lowercase());
+    fields.insert("category", format!("{:?}", finding.category).to_lowercase());
+    fields.insert("signature", finding.signature.clone());
+    fields.insert("message", finding.message.clone());
+    fields.insert("artifact", artifact.unwrap_or("").to_string());
+    fields.insert(
+        "seed",
+        finding.seed.map(|s| s.to_string()).unwrap_or_default(),
+    );
+    fields
+}
+
+pub fn finished_fields(summary: &Summary) -> BTreeMap<&'static str, String> {
+    let mut fields = BTreeMap::new();
+    fields.insert("elapsed", format!("{:.0}", summary.elapsed.as_secs_f64()));
+    fields.insert("findings", summary.unique_findings().len().to_string());
+    fields.insert("commands", summary.tally.commands.to_string());
+    fields.insert("games", summary.tally.finished.to_string());
+    fields.insert(
+        "status",
+        if summary.failed(false) {
+            "failed"
+        } else {
+            "passed"
+        }
+        .to_string(),
+    );
+    fields
+}
+
+// Posts from a background thread so a slow endpoint doesn't hold up the
+// campaign. Anything queued is still sent when it's closed.
+#[cfg(not(feature = "embedded"))]
+pub struct Notifier {
+    tx: Option<Sender<(String, Value)>>,
+    sender: Option<JoinHandle<()>>,
+    webhooks: Vec<Webhook>,
+}
+
+#[cfg(not(feature = "embedded"))]
+impl Notifier {
+    pub fn new(webhooks: Vec<Webhook>) -> Self {
+        let (tx, rx) = channel::<(String, Value)>();
+        let sender = thread::spawn(move || {
+            for (url, payload) in rx {
+                if let Err(e) = post(&url, &payload) {
+                    eprintln!("\nfailed to post to webhook {}: {}", url, e);
+                }
+            }
+        });
+        Notifier {
+            tx: Some(tx),
+            sender: Some(sender),
+            webhooks,
+        }
+    }
+
+    pub fn finding(&self, finding: &Finding, artifact: Option<&str>) {
+        let fields = finding_fields(finding, artifact);
+        for w in &self.webhooks {
+            self.send(w, w.payload("finding", &w.finding_template, &fields));
+        }
+    }
+
+    pub fn finished(mut self, summary: &Summary) {
+        let fields = finished_fields(summary);
+        for w in &self.webhooks {
+            self.send(w, w.payload("finished", &w.finished_template, &fields));
+        }
+        self.tx = None;
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+
+    fn send(&self, webhook: &Webhook, payload: Value) {
+        if let Some(ref tx) = self.tx {
+            let _ = tx.send((webhook.url.clone(), payload));
+        }
+    }
+}
+
+#[cfg(all(feature = "http", not(feature = "embedded")))]
+fn post(url: &str, payload: &Value) -> Result<(), Error> {
+    ureq::post(url).send_json(payload)?;
+    Ok(())
+}
+
+#[cfg(not(any(feature = "http", feature = "embedded")))]
+fn post(_url: &str, _payload: &Value) -> Result<(), Error> {
+    bail!("webhooks require the http feature")
+}
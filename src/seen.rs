@@ -0,0 +1,137 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::finding::{fnv1a, FNV_OFFSET};
+
+const HASHES: u64 = 4;
+
+// A set of signatures or fingerprints that stays a fixed size. The most
+// recently seen `capacity` entries are kept exactly; older ones are pushed
+// into a bloom filter, so they're still recognised but may collide. The
+// estimated false positive rate says how much accuracy has been lost.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeenSet {
+    capacity: usize,
+    recent: HashMap<String, u64>,
+    order: BTreeMap<u64, String>,
+    clock: u64,
+    bloom: Vec<u64>,
+    evicted: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeenStats {
+    pub recent: usize,
+    pub evicted: usize,
+    pub false_positive_rate: f64,
+}
+
+impl SeenStats {
+    pub fn render(&self) -> String {
+        format!(
+            "{} recent, {} in the bloom filter, ~{:.3}% false positives",
+            self.recent,
+            self.evicted,
+            self.false_positive_rate * 100.0
+        )
+    }
+}
+
+impl SeenSet {
+    pub fn new(capacity: usize, bloom_bits: usize) -> Self {
+        SeenSet {
+            capacity: capacity.max(1),
+            recent: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            bloom: vec![0; bloom_bits.div_ceil(64).max(1)],
+            evicted: 0,
+        }
+    }
+
+    // Returns whether the entry is new. Entries already evicted to the bloom
+    // filter stay there, and may be wrongly reported as seen.
+    pub fn insert(&mut self, entry: &str) -> bool {
+        self.clock += 1;
+        if let Some(last) = self.recent.get_mut(entry) {
+            self.order.remove(last);
+            *last = self.clock;
+            self.order.insert(self.clock, entry.to_string());
+            return false;
+        }
+        if self.in_bloom(entry) {
+            return false;
+        }
+        self.recent.insert(entry.to_string(), self.clock);
+        self.order.insert(self.clock, entry.to_string());
+        while self.recent.len() > self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(evicted) = self.order.remove(&oldest) {
+                self.recent.remove(&evicted);
+                self.add_to_bloom(&evicted);
+                self.evicted += 1;
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, entry: &str) -> bool {
+        self.recent.contains_key(entry) || self.in_bloom(entry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.recent.len() + self.evicted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> SeenStats {
+        let bits = (self.bloom.len() * 64) as f64;
+        let k = HASHES as f64;
+        SeenStats {
+            recent: self.recent.len(),
+            evicted: self.evicted,
+            false_positive_rate: (1.0 - (-k * self.evicted as f64 / bits).exp()).powf(k),
+        }
+    }
+
+    // Lets a campaign pick up where an earlier one left off.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn bits(&self, entry: &str) -> impl Iterator<Item = usize> {
+        let h1 = fnv1a(FNV_OFFSET, entry.as_bytes());
+        let h2 = fnv1a(h1, entry.as_bytes()) | 1;
+        let bits = (self.bloom.len() * 64) as u64;
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+
+    fn in_bloom(&self, entry: &str) -> bool {
+        self.evicted > 0
+            && self
+                .bits(entry)
+                .all(|b| self.bloom[b / 64] & (1 << (b % 64)) != 0)
+    }
+
+    fn add_to_bloom(&mut self, entry: &str) {
+        let bits: Vec<usize> = self.bits(entry).collect();
+        for b in bits {
+            self.bloom[b / 64] |= 1 << (b % 64);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use failure::{bail, Error};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::dictionary::Dictionary;
+use crate::report::baseline::Baseline;
+use crate::report::suppress::{Suppression, Suppressions};
+
+// Files in a target directory, laid out as the daemon keeps them.
+pub const DICTIONARY: &str = "dictionary.txt";
+pub const SUPPRESSIONS: &str = "suppressions.json";
+pub const BASELINE: &str = "baseline.json";
+
+// What one site has learnt about a game, packed into a single file so
+// another site fuzzing the same game can pick it up. Findings' artifact
+// paths only mean something on the machine that wrote them, so they're left
+// out of exported suppressions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Share {
+    pub fuzz_version: String,
+    pub exported_at: u64,
+    #[serde(default)]
+    pub dictionary: Vec<String>,
+    #[serde(default)]
+    pub suppressions: Vec<Suppression>,
+    #[serde(default)]
+    pub baseline: Option<Baseline>,
+}
+
+// Which side wins when both have a suppression for the same signature in
+// different states, or both have a baseline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prefer {
+    Local,
+    Imported,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Imported {
+    pub commands: usize,
+    pub suppressions: usize,
+    pub baseline: bool,
+    pub conflicts: Vec<String>,
+}
+
+impl Imported {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Imported {} new dictionary commands, {} suppressions{}\n",
+            self.commands,
+            self.suppressions,
+            if self.baseline {
+                " and the baseline"
+            } else {
+                ""
+            }
+        );
+        for conflict in &self.conflicts {
+            out.push_str(&format!("  conflict: {}\n", conflict));
+        }
+        out
+    }
+}
+
+pub fn export(target_dir: &Path) -> Result<Share, Error> {
+    if !target_dir.is_dir() {
+        bail!("{} isn't a directory", target_dir.display());
+    }
+    let path = target_dir.join(DICTIONARY);
+    let dictionary = if path.exists() {
+        Dictionary::load(&path)?
+            .commands
+            .into_values()
+            .flatten()
+            .collect()
+    } else {
+        vec![]
+    };
+    let path = target_dir.join(SUPPRESSIONS);
+    let suppressions = if path.exists() {
+        Suppressions::load(&path)?
+            .entries()
+            .map(|s| Suppression {
+                artifact: None,
+                ..s.clone()
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    let path = target_dir.join(BASELINE);
+    let baseline = if path.exists() {
+        Some(Baseline::load(&path)?)
+    } else {
+        None
+    };
+    Ok(Share {
+        fuzz_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        dictionary,
+        suppressions,
+        baseline,
+    })
+}
+
+impl Share {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Dictionaries are unioned. Suppressions only the bundle has are added,
+    // and ones in a different state on each side are conflicts settled by
+    // `prefer`. Throughput depends on the machine, so a local baseline is
+    // only replaced when preferring imported.
+    pub fn import(&self, target_dir: &Path, prefer: Prefer) -> Result<Imported, Error> {
+        fs::create_dir_all(target_dir)?;
+        let mut imported = Imported::default();
+
+        let path = target_dir.join(DICTIONARY);
+        let mut dictionary = if path.exists() {
+            Dictionary::load(&path)?
+        } else {
+            Dictionary::new(usize::MAX)
+        };
+        let before = dictionary.len();
+        for command in &self.dictionary {
+            dictionary.insert(command);
+        }
+        imported.commands = dictionary.len() - before;
+        if imported.commands > 0 {
+            dictionary.save(&path)?;
+        }
+
+        let path = target_dir.join(SUPPRESSIONS);
+        let mut suppressions = if path.exists() {
+            Suppressions::load(&path)?
+        } else {
+            Suppressions::default()
+        };
+        for theirs in &self.suppressions {
+            let take = match suppressions.get(&theirs.signature) {
+                None => true,
+                Some(ours) if ours.state == theirs.state => false,
+                Some(ours) => {
+                    imported.conflicts.push(format!(
+                        "{} is {:?} here and {:?} in the bundle, keeping {}",
+                        theirs.signature,
+                        ours.state,
+                        theirs.state,
+                        match prefer {
+                            Prefer::Local => "local",
+                            Prefer::Imported => "imported",
+                        }
+                    ));
+                    prefer == Prefer::Imported
+                }
+            };
+            if take {
+                // Keeps our artifact, the bundle never has one.
+                let artifact = suppressions
+                    .get(&theirs.signature)
+                    .and_then(|s| s.artifact.clone());
+                suppressions.insert(Suppression {
+                    artifact,
+                    ..theirs.clone()
+                });
+                imported.suppressions += 1;
+            }
+        }
+        if imported.suppressions > 0 {
+            suppressions.save(&path)?;
+        }
+
+        let path = target_dir.join(BASELINE);
+        if let Some(ref baseline) = self.baseline {
+            let ours = if path.exists() {
+                Some(Baseline::load(&path)?)
+            } else {
+                None
+            };
+            match ours {
+                Some(ref ours) if ours == baseline => {}
+                Some(_) if prefer == Prefer::Local => imported
+                    .conflicts
+                    .push("both have a baseline, keeping local".to_string()),
+                _ => {
+                    baseline.save(&path)?;
+                    imported.baseline = true;
+                }
+            }
+        }
+        Ok(imported)
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::dictionary::Dictionary;
+use crate::report::acceptance::{AcceptanceTable, Outcome};
+use crate::report::overhead::Overhead;
+use crate::report::user_errors::{UserErrorSample, UserErrors};
+
+// Per-worker counters for commands, by far the most frequent step, which
+// the coordinator folds in on its own schedule instead of receiving a
+// message per command. The counts are atomics so stop conditions see them
+// straight away; the tables are buffered by the worker's WorkerShard and
+// handed over here when the coordinator drains and when the worker exits.
+#[derive(Default)]
+pub(crate) struct WorkerStats {
+    commands: AtomicUsize,
+    invalid_input: AtomicUsize,
+    latency_nanos: AtomicU64,
+    // Set when the coordinator has drained, asking for the next batch.
+    wanted: AtomicBool,
+    tables: Mutex<Tables>,
+    overhead: Mutex<Overhead>,
+}
+
+type Tables = (AcceptanceTable, UserErrors, Dictionary);
+
+// A worker's own end of its WorkerStats.
+pub(crate) struct WorkerShard {
+    stats: Arc<WorkerStats>,
+    tables: Tables,
+    overhead: Overhead,
+}
+
+impl WorkerShard {
+    pub(crate) fn record(
+        &mut self,
+        head: &str,
+        outcome: Outcome,
+        latency: Duration,
+        user_error: Option<UserErrorSample>,
+        accepted: Option<String>,
+    ) {
+        self.stats.commands.fetch_add(1, Ordering::Relaxed);
+        if outcome != Outcome::Accepted {
+            self.stats.invalid_input.fetch_add(1, Ordering::Relaxed);
+        }
+        self.stats
+            .latency_nanos
+            .fetch_add(latency.as_nanos() as u64, Ordering::Relaxed);
+        self.tables.0.record(head, outcome, latency);
+        if let Some(sample) = user_error {
+            self.tables.1.record(sample);
+        }
+        if let Some(command) = accepted {
+            self.tables.2.insert(&command);
+        }
+    }
+
+    pub(crate) fn add_overhead(&mut self, overhead: &Overhead) {
+        self.overhead.add(overhead);
+    }
+
+    // Hands the buffered tables over if the coordinator has drained since
+    // the last batch.
+    pub(crate) fn hand_over_if_wanted(&mut self) {
+        if self.stats.wanted.swap(false, Ordering::Relaxed) {
+            self.hand_over();
+        }
+    }
+
+    fn hand_over(&mut self) {
+        let (a, u, d) = std::mem::take(&mut self.tables);
+        let mut tables = self.stats.tables.lock().unwrap();
+        tables.0.merge(a);
+        tables.1.merge(u);
+        tables.2.merge(d);
+        drop(tables);
+        let overhead = std::mem::take(&mut self.overhead);
+        self.stats.overhead.lock().unwrap().add(&overhead);
+    }
+}
+
+impl Drop for WorkerShard {
+    fn drop(&mut self) {
+        self.hand_over();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Counts {
+    pub(crate) commands: usize,
+    pub(crate) invalid_input: usize,
+    pub(crate) latency: Duration,
+}
+
+#[derive(Default)]
+pub(crate) struct ShardedStats {
+    shards: Vec<Arc<WorkerStats>>,
+    // Totals at the last drain, to hand out deltas.
+    drained: Counts,
+}
+
+impl ShardedStats {
+    pub(crate) fn shard(&mut self) -> WorkerShard {
+        let stats = Arc::new(WorkerStats::default());
+        self.shards.push(stats.clone());
+        WorkerShard {
+            stats,
+            tables: Tables::default(),
+            overhead: Overhead::default(),
+        }
+    }
+
+    // Cumulative, one per shard in the order they were handed out.
+    pub(crate) fn overheads(&self) -> Vec<Overhead> {
+        self.shards
+            .iter()
+            .map(|s| *s.overhead.lock().unwrap())
+            .collect()
+    }
+
+    pub(crate) fn counts(&self) -> Counts {
+        self.shards.iter().fold(Counts::default(), |c, s| Counts {
+            commands: c.commands + s.commands.load(Ordering::Relaxed),
+            invalid_input: c.invalid_input + s.invalid_input.load(Ordering::Relaxed),
+            latency: c.latency + Duration::from_nanos(s.latency_nanos.load(Ordering::Relaxed)),
+        })
+    }
+
+    // Returns what's been counted since the last drain and moves the
+    // per-command tables handed over so far into the given ones.
+    pub(crate) fn drain(
+        &mut self,
+        acceptance: &mut AcceptanceTable,
+        user_errors: &mut UserErrors,
+        dictionary: &mut Dictionary,
+    ) -> Counts {
+        for shard in &self.shards {
+            let (a, u, d) = std::mem::take(&mut *shard.tables.lock().unwrap());
+            acceptance.merge(a);
+            user_errors.merge(u);
+            dictionary.merge(d);
+            shard.wanted.store(true, Ordering::Relaxed);
+        }
+        let total = self.counts();
+        let delta = Counts {
+            commands: total.commands - self.drained.commands,
+            invalid_input: total.invalid_input - self.drained.invalid_input,
+            latency: total.latency.saturating_sub(self.drained.latency),
+        };
+        self.drained = total;
+        delta
+    }
+}
@@ -0,0 +1,146 @@
+use failure::{bail, format_err, Error};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// When a campaign is done, on top of --duration and --steps. Given as
+// conditions joined with `&` and `|`, `&` binding tighter, such as
+// `findings=1|time=2h&plateau`:
+//
+//   time=DURATION   the campaign has run this long
+//   games=N         this many games have finished
+//   commands=N      this many commands have been sent
+//   findings=N      this many unique findings have been found
+//   plateau         a coverage plateau was hit with no escalations left
+//   file=PATH       PATH exists, for stopping a campaign from outside
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopCondition {
+    Elapsed(Duration),
+    Games(usize),
+    Commands(usize),
+    UniqueFindings(usize),
+    Plateau,
+    File(PathBuf),
+    All(Vec<StopCondition>),
+    Any(Vec<StopCondition>),
+}
+
+// What the coordinator knows at a tick.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    pub elapsed: Duration,
+    pub games: usize,
+    pub commands: usize,
+    pub unique_findings: usize,
+    pub plateaued: bool,
+}
+
+impl StopCondition {
+    pub fn met(&self, progress: &Progress) -> bool {
+        match *self {
+            StopCondition::Elapsed(d) => progress.elapsed >= d,
+            StopCondition::Games(n) => progress.games >= n,
+            StopCondition::Commands(n) => progress.commands >= n,
+            StopCondition::UniqueFindings(n) => progress.unique_findings >= n,
+            StopCondition::Plateau => progress.plateaued,
+            StopCondition::File(ref path) => path.exists(),
+            StopCondition::All(ref cs) => cs.iter().all(|c| c.met(progress)),
+            StopCondition::Any(ref cs) => cs.iter().any(|c| c.met(progress)),
+        }
+    }
+
+    pub fn uses_plateau(&self) -> bool {
+        match *self {
+            StopCondition::Plateau => true,
+            StopCondition::All(ref cs) | StopCondition::Any(ref cs) => {
+                cs.iter().any(StopCondition::uses_plateau)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for StopCondition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let any = s
+            .split('|')
+            .map(|all| {
+                let all = all
+                    .split('&')
+                    .map(|c| atom(c.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(match all.len() {
+                    1 => all.into_iter().next().unwrap(),
+                    _ => StopCondition::All(all),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(match any.len() {
+            1 => any.into_iter().next().unwrap(),
+            _ => StopCondition::Any(any),
+        })
+    }
+}
+
+fn atom(s: &str) -> Result<StopCondition, Error> {
+    let count = |n: &str| {
+        n.parse::<usize>()
+            .map_err(|e| format_err!("invalid stop condition {}: {}", s, e))
+    };
+    Ok(match s.split_once('=') {
+        None if s == "plateau" => StopCondition::Plateau,
+        Some(("time", d)) => StopCondition::Elapsed(parse_duration(d)?),
+        Some(("games", n)) => StopCondition::Games(count(n)?),
+        Some(("commands", n)) => StopCondition::Commands(count(n)?),
+        Some(("findings", n)) => StopCondition::UniqueFindings(count(n)?),
+        Some(("file", path)) if !path.is_empty() => StopCondition::File(PathBuf::from(path)),
+        _ => bail!(
+            "invalid stop condition {}, expected time=, games=, commands=, findings=, file= or plateau",
+            s
+        ),
+    })
+}
+
+impl fmt::Display for StopCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter, cs: &[StopCondition], sep: &str| {
+            for (i, c) in cs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "{}", sep)?;
+                }
+                write!(f, "{}", c)?;
+            }
+            Ok(())
+        };
+        match *self {
+            StopCondition::Elapsed(d) => write!(f, "time={}s", d.as_secs()),
+            StopCondition::Games(n) => write!(f, "games={}", n),
+            StopCondition::Commands(n) => write!(f, "commands={}", n),
+            StopCondition::UniqueFindings(n) => write!(f, "findings={}", n),
+            StopCondition::Plateau => write!(f, "plateau"),
+            StopCondition::File(ref path) => write!(f, "file={}", path.display()),
+            StopCondition::All(ref cs) => join(f, cs, "&"),
+            StopCondition::Any(ref cs) => join(f, cs, "|"),
+        }
+    }
+}
+
+// Such as 90s, 30m, 6h or 1d.
+pub fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let (n, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = n
+        .parse()
+        .map_err(|_| format_err!("invalid duration {}", s))?;
+    let secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid duration {}, expected a unit of s, m, h or d", s),
+    };
+    Ok(Duration::from_secs(n * secs))
+}
@@ -0,0 +1,113 @@
+use failure::{bail, Error};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(all(feature = "s3", not(feature = "embedded")))]
+pub mod s3;
+
+// Where what should outlive a campaign is kept: findings and the other
+// artifacts, and the dedup state. Keys are relative paths with `/`
+// separators, such as `CAMPAIGN/finding-ID.json`.
+pub trait Storage: fmt::Debug + Send + Sync {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Error>;
+
+    // None when nothing is stored under the key.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    // Where the key is kept, for people to find it, such as a path or an
+    // s3:// URL.
+    fn locate(&self, key: &str) -> String;
+}
+
+// A directory, which is what --artifacts and --dedup-state have always
+// written to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalStorage {
+    pub dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LocalStorage { dir: dir.into() }
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.dir.join(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn locate(&self, key: &str) -> String {
+        self.dir.join(key).display().to_string()
+    }
+}
+
+// Another storage with every key under a prefix, such as a campaign's id.
+#[derive(Debug)]
+pub struct Scoped {
+    inner: Arc<dyn Storage>,
+    prefix: String,
+}
+
+impl Storage for Scoped {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.inner.put(&self.key(key), bytes)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.get(&self.key(key))
+    }
+
+    fn locate(&self, key: &str) -> String {
+        self.inner.locate(&self.key(key))
+    }
+}
+
+impl Scoped {
+    fn key(&self, key: &str) -> String {
+        format!("{}/{}", self.prefix, key)
+    }
+}
+
+pub fn scoped(inner: Arc<dyn Storage>, prefix: &str) -> Arc<dyn Storage> {
+    Arc::new(Scoped {
+        inner,
+        prefix: prefix.trim_matches('/').to_string(),
+    })
+}
+
+pub fn local(dir: &Path) -> Arc<dyn Storage> {
+    Arc::new(LocalStorage::new(dir))
+}
+
+// `s3://BUCKET[/PREFIX]` for an S3 compatible bucket, anything else is a
+// directory.
+pub fn parse(s: &str) -> Result<Arc<dyn Storage>, Error> {
+    if s.starts_with("s3://") {
+        #[cfg(all(feature = "s3", not(feature = "embedded")))]
+        return Ok(Arc::new(s3::S3Storage::from_url(s)?));
+        #[cfg(not(all(feature = "s3", not(feature = "embedded"))))]
+        bail!("s3 storage requires the s3 feature");
+    }
+    if s.is_empty() {
+        bail!("expected a directory or s3://BUCKET[/PREFIX]");
+    }
+    Ok(local(Path::new(s)))
+}
@@ -0,0 +1,200 @@
+use failure::{bail, format_err, Error};
+use ring::{digest, hmac};
+
+use std::env;
+use std::fmt;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Storage;
+use crate::wallclock;
+
+// An S3 compatible bucket, signed with AWS signature version 4 from the
+// usual AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN and
+// AWS_REGION variables. AWS_ENDPOINT_URL points it at a store other than
+// AWS, such as MinIO or R2, and requests use path style addressing as those
+// all support it.
+pub struct S3Storage {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    agent: ureq::Agent,
+}
+
+// Leaves the credentials out.
+impl fmt::Debug for S3Storage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("S3Storage")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+impl S3Storage {
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let path = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| format_err!("expected s3://BUCKET[/PREFIX], got {}", url))?;
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            bail!("no bucket in {}", url);
+        }
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = var("AWS_ENDPOINT_URL")
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region))
+            .trim_end_matches('/')
+            .to_string();
+        let host = endpoint
+            .split_once("://")
+            .map_or(endpoint.as_str(), |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        Ok(S3Storage {
+            host,
+            endpoint,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+            region,
+            access_key: var("AWS_ACCESS_KEY_ID")
+                .ok_or_else(|| format_err!("s3 storage requires AWS_ACCESS_KEY_ID"))?,
+            secret_key: var("AWS_SECRET_ACCESS_KEY")
+                .ok_or_else(|| format_err!("s3 storage requires AWS_SECRET_ACCESS_KEY"))?,
+            session_token: var("AWS_SESSION_TOKEN"),
+            agent: ureq::Agent::new(),
+        })
+    }
+
+    fn object(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.prefix, key)
+        }
+    }
+
+    fn request(&self, method: &str, key: &str, payload: &[u8]) -> ureq::Request {
+        let path = format!("/{}/{}", self.bucket, self.object(key))
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<String>>()
+            .join("/");
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        // 20200101T000000Z, and the 20200101 the signing key is scoped to.
+        let amz_date = wallclock::rfc3339(secs).replace(['-', ':'], "");
+        let date = &amz_date[..8];
+        let payload_hash = hex(digest::digest(&digest::SHA256, payload).as_ref());
+
+        let mut headers = vec![
+            ("host", self.host.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(ref token) = self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method, path, canonical_headers, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+        );
+        let signing_key = [date, &self.region, "s3", "aws4_request"].iter().fold(
+            format!("AWS4{}", self.secret_key).into_bytes(),
+            |key, part| sign(&key, part.as_bytes()),
+        );
+        let signature = hex(&sign(&signing_key, string_to_sign.as_bytes()));
+
+        let mut request = self
+            .agent
+            .request(method, &format!("{}{}", self.endpoint, path))
+            .set(
+                "Authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            );
+        // ureq sets the host itself, from the same URL.
+        for (name, value) in headers.iter().skip(1) {
+            request = request.set(name, value);
+        }
+        request
+    }
+}
+
+impl Storage for S3Storage {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.request("PUT", key, bytes).send_bytes(bytes)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.request("GET", key, &[]).call() {
+            Ok(response) => {
+                let mut bytes = vec![];
+                response.into_reader().read_to_end(&mut bytes)?;
+                Ok(Some(bytes))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn locate(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, self.object(key))
+    }
+}
+
+fn sign(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), message)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Percent encodes all but the unreserved characters, as signing expects of
+// each path segment.
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,78 @@
+use failure::{format_err, Error};
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use brdgme_game::command;
+
+pub mod bot;
+pub mod overflow;
+pub mod phase;
+
+pub use self::bot::{Bot, BotSeats};
+pub use self::overflow::OverflowStrategy;
+
+pub type FuzzRng = XorShiftRng;
+
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+    fn command(&mut self, spec: &command::Spec, names: &[String], rng: &mut FuzzRng) -> String;
+
+    fn new_game(&mut self) {}
+}
+
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn command(&mut self, spec: &command::Spec, names: &[String], rng: &mut FuzzRng) -> String {
+        rand_command(spec, names, rng)
+    }
+}
+
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(RandomStrategy)),
+        "overflow" => Some(Box::new(OverflowStrategy::default())),
+        _ => None,
+    }
+}
+
+pub fn rand_command<R: Rng>(
+    command_spec: &command::Spec,
+    players: &[String],
+    rng: &mut R,
+) -> String {
+    brdgme_rand_bot::spec_to_command(command_spec, players, rng).join("")
+}
+
+// One command from the named strategy, for game crates' own tests and
+// simulations. The same arguments always give the same command.
+pub fn generate_command(
+    spec: &command::Spec,
+    names: &[String],
+    strategy: &str,
+    seed: u64,
+) -> Result<String, Error> {
+    let mut strategy =
+        by_name(strategy).ok_or_else(|| format_err!("unknown strategy {}", strategy))?;
+    Ok(strategy.command(spec, names, &mut seeded_rng(seed)))
+}
+
+pub fn seeded_rng(seed: u64) -> FuzzRng {
+    let a = splitmix64(seed);
+    let b = splitmix64(a);
+    XorShiftRng::from_seed([a as u32 | 1, (a >> 32) as u32, b as u32, (b >> 32) as u32])
+}
+
+pub fn derive_seed(seed: u64, n: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(n))
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,100 @@
+use failure::{format_err, Error};
+use rand::Rng;
+
+use brdgme_game::command;
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::FuzzGame;
+use crate::strategy::{FuzzRng, Strategy};
+
+// A player that sees the game it's in, like the heuristic bots game crates
+// ship, where a Strategy only sees the command spec. Seating one against
+// random players reaches competitive states random play never gets to.
+pub trait Bot: Send {
+    fn name(&self) -> &str;
+    fn command(
+        &mut self,
+        player: usize,
+        game: &FuzzGame,
+        spec: &command::Spec,
+        names: &[String],
+        rng: &mut FuzzRng,
+    ) -> String;
+
+    fn new_game(&mut self) {}
+}
+
+// Any strategy can take a bot's seats.
+pub struct StrategyBot(pub Box<dyn Strategy>);
+
+impl Bot for StrategyBot {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn command(
+        &mut self,
+        _player: usize,
+        _game: &FuzzGame,
+        spec: &command::Spec,
+        names: &[String],
+        rng: &mut FuzzRng,
+    ) -> String {
+        self.0.command(spec, names, rng)
+    }
+
+    fn new_game(&mut self) {
+        self.0.new_game()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BotSeats {
+    // These seats whenever the game has them.
+    Fixed(Vec<usize>),
+    // One seat drawn for each game.
+    Random,
+}
+
+impl BotSeats {
+    pub fn pick(&self, players: usize, rng: &mut FuzzRng) -> Vec<usize> {
+        match *self {
+            BotSeats::Fixed(ref seats) => seats.iter().copied().filter(|&s| s < players).collect(),
+            BotSeats::Random if players > 0 => vec![rng.gen_range(0, players)],
+            BotSeats::Random => vec![],
+        }
+    }
+}
+
+impl FromStr for BotSeats {
+    type Err = Error;
+
+    // `random` or a comma separated list of seats.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s == "random" {
+            return Ok(BotSeats::Random);
+        }
+        s.split(',')
+            .map(|seat| {
+                seat.trim()
+                    .parse()
+                    .map_err(|e| format_err!("invalid seat {}: {}", seat, e))
+            })
+            .collect::<Result<_, _>>()
+            .map(BotSeats::Fixed)
+    }
+}
+
+impl fmt::Display for BotSeats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BotSeats::Fixed(ref seats) => {
+                let seats: Vec<String> = seats.iter().map(|s| s.to_string()).collect();
+                write!(f, "seats {}", seats.join(","))
+            }
+            BotSeats::Random => write!(f, "a random seat"),
+        }
+    }
+}
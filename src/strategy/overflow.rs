@@ -0,0 +1,81 @@
+use brdgme_game::command::Spec;
+
+use super::{rand_command, FuzzRng, Strategy};
+
+// Rewrites every Int in the spec into a choice of extreme values before
+// handing it to the random generator, flipping the preferred sign on each
+// command so scores and resources get pushed both ways within a game.
+#[derive(Default)]
+pub struct OverflowStrategy {
+    negative: bool,
+}
+
+impl Strategy for OverflowStrategy {
+    fn name(&self) -> &str {
+        "overflow"
+    }
+
+    fn new_game(&mut self) {
+        self.negative = false;
+    }
+
+    fn command(&mut self, spec: &Spec, names: &[String], rng: &mut FuzzRng) -> String {
+        self.negative = !self.negative;
+        rand_command(&extremes(spec, self.negative), names, rng)
+    }
+}
+
+fn extremes(spec: &Spec, negative: bool) -> Spec {
+    match *spec {
+        Spec::Int { min, max } => {
+            let mut values = vec![];
+            let preferred: &[i64] = if negative {
+                &[i32::MIN as i64, -1_000_000, -1]
+            } else {
+                &[i32::MAX as i64, 1_000_000, 1]
+            };
+            for &v in preferred.iter().chain(&[0]) {
+                if min.is_none_or(|m| v >= m as i64) && max.is_none_or(|m| v <= m as i64) {
+                    values.push(v);
+                }
+            }
+            values.extend(min.iter().chain(max.iter()).map(|&v| v as i64));
+            values.sort_unstable();
+            values.dedup();
+            Spec::OneOf(
+                values
+                    .into_iter()
+                    .map(|v| Spec::Token(v.to_string()))
+                    .collect(),
+            )
+        }
+        Spec::OneOf(ref specs) => {
+            Spec::OneOf(specs.iter().map(|s| extremes(s, negative)).collect())
+        }
+        Spec::Chain(ref specs) => {
+            Spec::Chain(specs.iter().map(|s| extremes(s, negative)).collect())
+        }
+        Spec::Opt(ref s) => Spec::Opt(Box::new(extremes(s, negative))),
+        Spec::Many {
+            ref spec,
+            min,
+            max,
+            ref delim,
+        } => Spec::Many {
+            spec: Box::new(extremes(spec, negative)),
+            min,
+            max,
+            delim: delim.clone(),
+        },
+        Spec::Doc {
+            ref name,
+            ref desc,
+            ref spec,
+        } => Spec::Doc {
+            name: name.clone(),
+            desc: desc.clone(),
+            spec: Box::new(extremes(spec, negative)),
+        },
+        ref other => other.clone(),
+    }
+}
@@ -0,0 +1,45 @@
+use failure::{bail, format_err, Error};
+
+use std::sync::Arc;
+
+use crate::config::GamePhaseHook;
+
+// Labels games by how many commands have been accepted, for per phase
+// strategies, given as `opening:10,midgame:100,endgame`: the first 10
+// commands are the opening, up to 100 the midgame and the rest the endgame.
+pub fn by_turn(spec: &str) -> Result<GamePhaseHook, Error> {
+    let mut phases: Vec<(String, Option<usize>)> = vec![];
+    for part in spec.split(',') {
+        let (label, until) = match part.split_once(':') {
+            Some((label, until)) => (
+                label,
+                Some(
+                    until
+                        .parse::<usize>()
+                        .map_err(|e| format_err!("invalid game phase {}: {}", part, e))?,
+                ),
+            ),
+            None => (part, None),
+        };
+        if label.is_empty() {
+            bail!(
+                "invalid game phase {}, expected LABEL:UNTIL_TURN or LABEL",
+                part
+            );
+        }
+        if let Some(&(ref previous, None)) = phases.last() {
+            bail!(
+                "game phase {} comes after {}, which has no end",
+                label,
+                previous
+            );
+        }
+        phases.push((label.to_string(), until));
+    }
+    Ok(Arc::new(move |_, clock| {
+        phases
+            .iter()
+            .find(|(_, until)| until.is_none_or(|until| clock.turn < until))
+            .map(|(label, _)| label.clone())
+    }))
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+const MAX_WINDOW: Duration = Duration::from_secs(16 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    Process,
+    // The first finding held back in this window.
+    Capped,
+    Throttled,
+}
+
+// Caps how many findings are printed and saved per window, so a target that
+// fails every game doesn't bury the reports under thousands of artifacts.
+// Each window that goes over the cap doubles the next one, up to
+// MAX_WINDOW, and a window that stays under resets it.
+#[derive(Clone, Debug)]
+pub struct FindingThrottle {
+    max: usize,
+    window: Duration,
+    started: Instant,
+    processed: usize,
+    held: usize,
+    // Findings held back over the whole campaign.
+    pub throttled: usize,
+}
+
+impl FindingThrottle {
+    pub fn new(max_per_minute: usize) -> Self {
+        FindingThrottle {
+            max: max_per_minute,
+            window: WINDOW,
+            started: Instant::now(),
+            processed: 0,
+            held: 0,
+            throttled: 0,
+        }
+    }
+
+    pub fn admit(&mut self) -> Admission {
+        if self.started.elapsed() >= self.window {
+            self.window = if self.held > 0 {
+                (self.window * 2).min(MAX_WINDOW)
+            } else {
+                WINDOW
+            };
+            self.started = Instant::now();
+            self.processed = 0;
+            self.held = 0;
+        }
+        if self.processed < self.max {
+            self.processed += 1;
+            return Admission::Process;
+        }
+        self.held += 1;
+        self.throttled += 1;
+        if self.held == 1 {
+            Admission::Capped
+        } else {
+            Admission::Throttled
+        }
+    }
+
+    // Until findings are printed and saved again.
+    pub fn remaining(&self) -> Duration {
+        self.window.saturating_sub(self.started.elapsed())
+    }
+}
@@ -0,0 +1,26 @@
+// What the engine times latencies and overhead with. wasm32 has no clock in
+// std, so the embedded build times nothing and every duration comes out as
+// zero.
+#[cfg(not(feature = "embedded"))]
+pub use std::time::Instant;
+
+#[cfg(feature = "embedded")]
+pub use self::embedded::Instant;
+
+#[cfg(feature = "embedded")]
+mod embedded {
+    use std::time::Duration;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Instant;
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::default()
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use failure::{bail, format_err, Error};
+
+use brdgme_cmd::api;
+use brdgme_cmd::requester::Requester;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancelToken;
+use crate::engine::{exec_command, CommandResponse, FuzzGame, MoveClock, StepContext};
+use crate::finding::{Finding, GameMeta, HistoryEntry};
+use crate::oracle::OracleRegistry;
+use crate::replay;
+
+pub const HELP: &str = "Commands:
+  next [N], n        step forward N commands
+  back [N], b        step back N commands
+  goto N             jump to after the Nth command
+  history, h         list the commands, marking the current position
+  state, s           print the current state
+  render [PLAYER]    print the public render or a player's render
+  play PLAYER CMD    issue a command here, dropping the rest of the history
+  edit CMD           replace the next command, keeping its player
+  check              rerun the oracles on the command leading here
+  help               show this
+  quit, q            exit";
+
+// Steps through a finding's game like a debugger. Games are replayed lazily
+// and cached by position, so stepping back is free and editing a command
+// only throws away what came after it. Jumps start from the closest state
+// checkpointed while fuzzing rather than from the start of the game.
+pub struct Triage<'a> {
+    client: &'a mut dyn Requester,
+    oracles: OracleRegistry,
+    cancel: CancelToken,
+    meta: GameMeta,
+    history: Vec<HistoryEntry>,
+    // The game after each command with how long the command took, keyed by
+    // position, 0 being the start, which is None when the backend can't
+    // answer Status. Positions jumped over aren't replayed.
+    games: BTreeMap<usize, (Option<FuzzGame>, Duration)>,
+    checkpoints: BTreeMap<usize, String>,
+    position: usize,
+}
+
+impl<'a> Triage<'a> {
+    pub fn new(
+        client: &'a mut dyn Requester,
+        finding: &Finding,
+        oracles: OracleRegistry,
+        cancel: &CancelToken,
+    ) -> Result<Self, Error> {
+        let meta = finding
+            .game
+            .clone()
+            .ok_or_else(|| format_err!("finding {} has no game to triage", finding.id))?;
+        let start = match client.request(&api::Request::Status {
+            game: meta.initial_state.clone(),
+        }) {
+            Ok(api::Response::Status {
+                game,
+                player_renders,
+                ..
+            }) => Some(FuzzGame::new(game, player_renders, vec![])),
+            _ => None,
+        };
+        Ok(Triage {
+            client,
+            oracles,
+            cancel: cancel.clone(),
+            checkpoints: (*meta.checkpoints).clone(),
+            meta,
+            history: replay::finding_history(finding),
+            games: BTreeMap::from([(0, (start, Duration::default()))]),
+            position: 0,
+        })
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    fn state(&self, position: usize) -> &str {
+        match self.games[&position].0 {
+            Some(ref game) => &game.game.state,
+            None => &self.meta.initial_state,
+        }
+    }
+
+    pub fn current_state(&self) -> &str {
+        self.state(self.position)
+    }
+
+    // Moves one command forward, replaying it unless it's already cached.
+    pub fn step(&mut self) -> Result<(), Error> {
+        if self.position >= self.history.len() {
+            bail!("at the end of the history");
+        }
+        self.reach(self.position + 1)?;
+        self.position += 1;
+        Ok(())
+    }
+
+    pub fn back(&mut self, n: usize) -> Result<(), Error> {
+        let position = self.position.saturating_sub(n);
+        self.reach(position)?;
+        self.position = position;
+        Ok(())
+    }
+
+    pub fn goto(&mut self, position: usize) -> Result<(), Error> {
+        if position > self.history.len() {
+            bail!("the history only has {} commands", self.history.len());
+        }
+        self.reach(position)?;
+        self.position = position;
+        Ok(())
+    }
+
+    // Makes sure the game at the position is cached, replaying from the
+    // closest cached game or checkpoint before it.
+    fn reach(&mut self, position: usize) -> Result<(), Error> {
+        let mut at = match self.games.range(..=position).next_back() {
+            Some((&at, _)) => at,
+            None => unreachable!("the start is always cached"),
+        };
+        if at == position {
+            return Ok(());
+        }
+        // A checkpoint the backend can't load is skipped rather than failing
+        // the jump.
+        if let Some((&checkpoint, state)) = self.checkpoints.range(at + 1..=position).next_back() {
+            if let Ok(api::Response::Status {
+                game,
+                player_renders,
+                ..
+            }) = self.client.request(&api::Request::Status {
+                game: state.clone(),
+            }) {
+                let game = FuzzGame::new(game, player_renders, vec![]);
+                self.games
+                    .insert(checkpoint, (Some(game), Duration::default()));
+                at = checkpoint;
+            }
+        }
+        while at < position {
+            let game = self.replay(at)?;
+            at += 1;
+            self.games.insert(at, game);
+        }
+        Ok(())
+    }
+
+    // Plays the command at the position on the game cached there.
+    fn replay(&mut self, position: usize) -> Result<(Option<FuzzGame>, Duration), Error> {
+        self.cancel.check()?;
+        let entry = self.history[position].clone();
+        let state = self.state(position).to_string();
+        let start = Instant::now();
+        let response = exec_command(
+            &mut *self.client,
+            entry.command.clone(),
+            state,
+            entry.player,
+            self.meta.names.clone(),
+        )?;
+        let latency = start.elapsed();
+        match response {
+            CommandResponse::Ok(game) => Ok((Some(game), latency)),
+            CommandResponse::UserError { message } => bail!(
+                "{:?} by player {} was rejected: {}",
+                entry.command,
+                entry.player,
+                message
+            ),
+            CommandResponse::PartialParse => bail!(
+                "{:?} by player {} was only partially parsed",
+                entry.command,
+                entry.player
+            ),
+        }
+    }
+
+    // Replaces everything from the current position on with a single command
+    // and steps onto it. A rejected command leaves the history as it was.
+    pub fn play(&mut self, player: usize, command: &str) -> Result<(), Error> {
+        let mut history = self.history[..self.position].to_vec();
+        history.push(HistoryEntry {
+            player,
+            command: command.to_string(),
+        });
+        self.rewrite(history)
+    }
+
+    // Swaps the next command for another by the same player, keeping the rest
+    // of the history to be replayed on top of it.
+    pub fn edit(&mut self, command: &str) -> Result<(), Error> {
+        let mut history = self.history.clone();
+        match history.get_mut(self.position) {
+            Some(entry) => entry.command = command.to_string(),
+            None => bail!("at the end of the history, use play instead"),
+        }
+        self.rewrite(history)
+    }
+
+    fn rewrite(&mut self, history: Vec<HistoryEntry>) -> Result<(), Error> {
+        let old = std::mem::replace(&mut self.history, history);
+        let cached = self.games.split_off(&(self.position + 1));
+        let checkpoints = self.checkpoints.split_off(&(self.position + 1));
+        if let Err(e) = self.step() {
+            self.history = old;
+            self.games.extend(cached);
+            self.checkpoints.extend(checkpoints);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Asks the backend to render the current state, falling back to the
+    // player renders from the last response for backends without Render.
+    pub fn render(&mut self, player: Option<usize>) -> Result<String, Error> {
+        let game = self.current_state().to_string();
+        let response = self.client.request(&api::Request::Render { player, game });
+        let cached = match (player, &self.games[&self.position].0) {
+            (Some(p), Some(game)) => game.player_renders.get(p).map(|r| &r.player_render),
+            _ => None,
+        };
+        if let (Err(_), Some(render)) = (&response, cached) {
+            return Ok(render.clone());
+        }
+        match response? {
+            api::Response::Render {
+                render: api::Render::Pub(render),
+            } => Ok(render.pub_render),
+            api::Response::Render {
+                render: api::Render::Player(render),
+            } => Ok(render.player_render),
+            api::Response::UserError { message } | api::Response::SystemError { message } => {
+                bail!("{}", message)
+            }
+            other => bail!("unexpected response to Render: {:?}", other),
+        }
+    }
+
+    // Reruns the oracles on the command that led to the current position,
+    // playing it again if the position was loaded from a checkpoint.
+    pub fn check(&mut self) -> Result<Vec<Finding>, Error> {
+        if self.position == 0 {
+            bail!("no command has been played yet");
+        }
+        if !self.games.contains_key(&(self.position - 1)) {
+            self.reach(self.position - 1)?;
+            let game = self.replay(self.position - 1)?;
+            self.games.insert(self.position, game);
+        }
+        let entry = &self.history[self.position - 1];
+        // Commands jumped over have no game cached to tell whose turn it
+        // was, so each ends a turn.
+        let mut clock = MoveClock::new(self.meta.names.len());
+        for (i, e) in self.history[..self.position].iter().enumerate() {
+            let whose_turn = |position| match self.games.get(&position) {
+                Some((Some(ref game), _)) => game.whose_turn(),
+                _ => &[],
+            };
+            if i == 0 {
+                clock.offer(whose_turn(0));
+            }
+            clock.record(e.player, whose_turn(i + 1));
+            clock.offer(whose_turn(i + 1));
+        }
+        let (after, latency) = match self.games[&self.position] {
+            (Some(ref game), latency) => (game, latency),
+            (None, _) => unreachable!("only the start can be missing"),
+        };
+        Ok(self.oracles.run(&StepContext {
+            game_id: self.meta.id,
+            clock: &clock,
+            player: Some(entry.player),
+            command: Some(&entry.command),
+            names: &self.meta.names,
+            before: self.games[&(self.position - 1)].0.as_ref(),
+            after,
+            latency,
+            client: RefCell::new(&mut *self.client),
+        }))
+    }
+
+    // Runs one line of input, returning what to print or None to quit.
+    pub fn eval(&mut self, line: &str) -> Result<Option<String>, Error> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let count = |rest: &str| -> Result<usize, Error> {
+            if rest.is_empty() {
+                Ok(1)
+            } else {
+                rest.parse()
+                    .map_err(|_| format_err!("{} isn't a number", rest))
+            }
+        };
+        let out = match word {
+            "" => String::new(),
+            "next" | "n" => {
+                for _ in 0..count(rest)? {
+                    self.step()?;
+                }
+                self.describe()
+            }
+            "back" | "b" => {
+                self.back(count(rest)?)?;
+                self.describe()
+            }
+            "goto" => {
+                self.goto(count(rest)?)?;
+                self.describe()
+            }
+            "history" | "h" => self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    let marker = if i == self.position { ">" } else { " " };
+                    format!("{} {:>4}  player {}: {}\n", marker, i, e.player, e.command)
+                })
+                .collect(),
+            "state" | "s" => {
+                let state = self.current_state();
+                match serde_json::from_str::<serde_json::Value>(state) {
+                    Ok(value) => serde_json::to_string_pretty(&value)?,
+                    Err(_) => state.to_string(),
+                }
+            }
+            "render" | "r" => {
+                let player = if rest.is_empty() {
+                    None
+                } else {
+                    Some(count(rest)?)
+                };
+                self.render(player)?
+            }
+            "play" => {
+                let (player, command) = rest
+                    .split_once(' ')
+                    .ok_or_else(|| format_err!("usage: play PLAYER COMMAND"))?;
+                self.play(count(player)?, command.trim())?;
+                self.describe()
+            }
+            "edit" => {
+                if rest.is_empty() {
+                    bail!("usage: edit COMMAND");
+                }
+                self.edit(rest)?;
+                self.describe()
+            }
+            "check" => {
+                let found = self.check()?;
+                if found.is_empty() {
+                    "no findings".to_string()
+                } else {
+                    found
+                        .iter()
+                        .map(|f| format!("[{:?}] {}\n", f.severity, f.message))
+                        .collect()
+                }
+            }
+            "help" | "?" => HELP.to_string(),
+            "quit" | "q" | "exit" => return Ok(None),
+            _ => bail!("unknown command {:?}, try help", word),
+        };
+        Ok(Some(out))
+    }
+
+    fn describe(&self) -> String {
+        match self.position.checked_sub(1).map(|i| &self.history[i]) {
+            Some(e) => format!(
+                "at {}/{} after player {}: {}",
+                self.position,
+                self.history.len(),
+                e.player,
+                e.command
+            ),
+            None => format!("at the start, {} commands ahead", self.history.len()),
+        }
+    }
+
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), Error> {
+        writeln!(output, "{}\n{}", self.describe(), HELP)?;
+        let mut lines = input.lines();
+        loop {
+            write!(output, "{}/{}> ", self.position, self.history.len())?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match self.eval(&line) {
+                Ok(Some(out)) => {
+                    if !out.is_empty() {
+                        writeln!(output, "{}", out.trim_end())?;
+                    }
+                }
+                Ok(None) => return Ok(()),
+                Err(e) => writeln!(output, "error: {}", e)?,
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use brdgme_cmd::api;
+
+use std::fs;
+use std::path::Path;
+
+use crate::backend;
+use crate::config::Config;
+use crate::report::baseline::Baseline;
+use crate::report::suppress::Suppressions;
+
+// What `--validate-config` found, `problems` being empty when the campaign
+// is good to go.
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub checked: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+impl Validation {
+    fn check(&mut self, what: impl Into<String>, result: Result<(), String>) {
+        let what = what.into();
+        match result {
+            Ok(()) => self.checked.push(what),
+            Err(e) => self.problems.push(format!("{}: {}", what, e)),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for c in &self.checked {
+            out.push_str(&format!("ok       {}\n", c));
+        }
+        for p in &self.problems {
+            out.push_str(&format!("problem  {}\n", p));
+        }
+        out
+    }
+}
+
+// Builds everything a campaign would start with and asks the target for
+// its player counts, without playing anything. Problems that would only
+// surface hours into a campaign, such as an unwritable artifact directory,
+// are reported up front.
+pub fn validate(config: &Config, requester_args: &[String]) -> Validation {
+    let mut v = Validation::default();
+    if config.workers == 0 && config.autoscale.is_none() {
+        v.problems.push("--workers must be at least 1".to_string());
+    }
+    for (flag, p) in [
+        ("--spectate", config.spectate),
+        ("--render-sample", config.render_sample),
+        ("--trace-rate", config.trace.rate),
+    ] {
+        v.check(
+            format!("{} {}", flag, p),
+            if (0.0..=1.0).contains(&p) {
+                Ok(())
+            } else {
+                Err("must be a probability between 0 and 1".to_string())
+            },
+        );
+    }
+
+    let strategy = (config.strategy)();
+    v.checked.push(format!("strategy {}", strategy.name()));
+    let names = (config.names)();
+    v.checked.push(format!("names {}", names.name()));
+    let oracles = (config.oracles)();
+    v.checked.push(format!(
+        "oracles {}",
+        oracles
+            .stats()
+            .map(|(name, _, _)| name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    if let Some(ref path) = config.baseline {
+        v.check(
+            format!("baseline {}", path.display()),
+            Baseline::load(path).map(|_| ()).map_err(|e| e.to_string()),
+        );
+    }
+    if let Some(ref path) = config.suppressions {
+        v.check(
+            format!("suppressions {}", path.display()),
+            Suppressions::load(path)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        );
+    }
+    if let Some(ref path) = config.watch {
+        v.check(
+            format!("hot reload binary {}", path.display()),
+            if path.is_file() {
+                Ok(())
+            } else {
+                Err("not a file".to_string())
+            },
+        );
+    }
+    let mut dirs = vec![];
+    let mut files = vec![];
+    if let Some(ref dir) = config.artifact_dir {
+        dirs.push(("artifact directory", dir.as_path()));
+    }
+    if config.trace.games > 0 || config.trace.rate > 0.0 {
+        dirs.push(("trace directory", config.trace.dir.as_path()));
+    }
+    if let Some(ref dir) = config.dedup.state_dir {
+        dirs.push(("dedup state directory", dir.as_path()));
+    }
+    for (what, path) in [
+        ("save baseline", &config.save_baseline),
+        ("log file", &config.soak.log_file),
+        ("stats file", &config.soak.stats_file),
+        ("dictionary export", &config.dictionary_out),
+        ("spec stats", &config.spec_stats_out),
+        ("heatmap", &config.heatmap_out),
+    ] {
+        if let Some(ref path) = *path {
+            files.push((what, path.as_path()));
+        }
+    }
+    for (what, dir) in dirs {
+        v.check(
+            format!("{} {}", what, dir.display()),
+            if dir.exists() && !dir.is_dir() {
+                Err("exists and isn't a directory".to_string())
+            } else {
+                writable(dir)
+            },
+        );
+    }
+    for (what, file) in files {
+        v.check(
+            format!("{} {}", what, file.display()),
+            if file.is_dir() {
+                Err("is a directory".to_string())
+            } else {
+                file.parent().map_or(Ok(()), writable)
+            },
+        );
+    }
+    // Reading the dedup state is the first thing a campaign does with
+    // storage, and fails the same way on bad credentials or a missing
+    // bucket.
+    if let Some(ref storage) = config.dedup.storage {
+        v.check(
+            format!("dedup state {}", storage.locate("violations.json")),
+            storage
+                .get("violations.json")
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        );
+    }
+    for w in &config.webhooks {
+        v.checked.push(format!("webhook {}", w.url));
+    }
+
+    let backend = format!(
+        "backend {}",
+        requester_args.get(1..).unwrap_or_default().join(" ")
+    );
+    let reached = backend::from_args(requester_args)
+        .and_then(|mut client| client.request(&api::Request::PlayerCounts))
+        .map_err(|e| e.to_string())
+        .and_then(|response| match response {
+            api::Response::PlayerCounts { player_counts } if player_counts.is_empty() => {
+                Err("reports no supported player counts".to_string())
+            }
+            api::Response::PlayerCounts { .. } => Ok(()),
+            other => Err(format!("unexpected response to PlayerCounts: {:?}", other)),
+        });
+    v.check(backend, reached);
+    v
+}
+
+// A path that doesn't exist yet is fine as long as its nearest existing
+// ancestor is a writable directory, as it's created when first needed.
+fn writable(path: &Path) -> Result<(), String> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or_else(|| Path::new("."));
+    let metadata = fs::metadata(existing).map_err(|e| e.to_string())?;
+    if !metadata.is_dir() {
+        return Err(format!("{} isn't a directory", existing.display()));
+    }
+    if metadata.permissions().readonly() {
+        return Err(format!("{} isn't writable", existing.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use rand::Rng;
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::strategy::FuzzRng;
+
+// How the virtual time reaches subprocess and HTTP targets, in seconds since
+// the epoch.
+pub const ENV: &str = "BRDGME_FUZZ_NOW";
+pub const HEADER: &str = "X-Brdgme-Fuzz-Now";
+
+thread_local! {
+    // The clock of whatever is making requests on this thread right now. A
+    // thread can run several workers, as simulations do, so each installs
+    // its own clock for the length of a step, see Scoped.
+    static NOW: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// The virtual time on this thread, if one is set.
+pub fn virtual_now() -> Option<u64> {
+    NOW.with(Cell::get)
+}
+
+pub fn set_virtual_now(now: Option<u64>) {
+    NOW.with(|n| n.set(now));
+}
+
+// Clears the virtual time when dropped, for replays that set it per command
+// and may return early.
+pub struct Reset;
+
+impl Drop for Reset {
+    fn drop(&mut self) {
+        set_virtual_now(None);
+    }
+}
+
+// Sets the virtual time until dropped, then puts back whatever was set
+// before.
+pub struct Scoped {
+    previous: Option<u64>,
+}
+
+pub fn scoped(now: Option<u64>) -> Scoped {
+    let previous = virtual_now();
+    set_virtual_now(now);
+    Scoped { previous }
+}
+
+impl Drop for Scoped {
+    fn drop(&mut self) {
+        set_virtual_now(self.previous);
+    }
+}
+
+// What in-process games should use for the current time, so they follow
+// the virtual clock when fuzzed.
+pub fn now() -> u64 {
+    virtual_now().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    })
+}
+
+// Such as 2020-01-01T00:00:00Z, from days to civil dates as in
+// http://howardhinnant.github.io/date_algorithms.html.
+pub fn rfc3339(secs: u64) -> String {
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let z = days as i64 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// A wall clock for games whose state depends on the time. Each game starts
+// at `start` and moves on `tick` seconds with every command accepted, now
+// and then skipping up to `max_skip` further to reach timeouts and expiries.
+// The times are drawn from the game's seed and kept with findings, so a
+// replay sees the same clock.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WallClock {
+    pub start: u64,
+    pub tick: u64,
+    pub skip_rate: f64,
+    pub max_skip: u64,
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        WallClock {
+            // 2020-01-01.
+            start: 1_577_836_800,
+            tick: 30,
+            skip_rate: 0.01,
+            max_skip: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl WallClock {
+    pub fn advance(&self, now: u64, rng: &mut FuzzRng) -> u64 {
+        let mut next = now + self.tick;
+        if self.max_skip > 0 && rng.gen::<f64>() < self.skip_rate {
+            next += rng.gen_range(1, self.max_skip + 1);
+        }
+        next
+    }
+}
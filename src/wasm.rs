@@ -0,0 +1,13 @@
+use wasm_bindgen::prelude::*;
+
+use brdgme_game::command;
+
+#[wasm_bindgen]
+pub fn rand_command(command_spec: &str, names: &str, seed: u32) -> Result<String, JsValue> {
+    let spec: command::Spec =
+        serde_json::from_str(command_spec).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let names: Vec<String> =
+        serde_json::from_str(names).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let mut rng = crate::strategy::seeded_rng(u64::from(seed));
+    Ok(crate::strategy::rand_command(&spec, &names, &mut rng))
+}